@@ -0,0 +1,136 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::process::Command;
+
+use crate::cidr;
+
+/// Largest network `prime` will poke, to keep a mistyped /8 from sending millions of packets
+pub const MAX_PRIME_HOSTS: u32 = 4096;
+
+/// Vendor prefixes (first three MAC octets) common on LANs and hypervisors
+const OUI_VENDORS: &[(&str, &str)] = &[
+    ("00:50:56", "VMware"),
+    ("00:0c:29", "VMware"),
+    ("00:05:69", "VMware"),
+    ("08:00:27", "VirtualBox"),
+    ("52:54:00", "QEMU/KVM"),
+    ("00:15:5d", "Hyper-V"),
+    ("00:16:3e", "Xen"),
+    ("02:42:ac", "Docker"),
+    ("b8:27:eb", "Raspberry Pi"),
+    ("dc:a6:32", "Raspberry Pi"),
+    ("e4:5f:01", "Raspberry Pi"),
+    ("00:1b:21", "Intel"),
+    ("3c:fd:fe", "Intel"),
+    ("f8:ff:c2", "Apple"),
+    ("a4:83:e7", "Apple"),
+    ("3c:22:fb", "Apple"),
+    ("00:1a:11", "Google"),
+    ("00:00:0c", "Cisco"),
+    ("00:1b:54", "Cisco"),
+    ("24:a4:3c", "Ubiquiti"),
+    ("fc:ec:da", "Ubiquiti"),
+    ("00:11:32", "Synology"),
+    ("50:c7:bf", "TP-Link"),
+    ("00:17:88", "Philips Hue"),
+];
+
+/// One entry from the kernel's neighbor (ARP) table
+#[derive(Debug, Serialize)]
+pub struct Neighbor {
+    pub ip: String,
+    pub mac: String,
+    pub vendor: Option<&'static str>,
+    pub state: String,
+    pub device: String,
+}
+
+/// The vendor for a MAC's OUI, or a note that it is randomized/locally assigned
+pub fn vendor(mac: &str) -> Option<&'static str> {
+    let mac = mac.to_ascii_lowercase();
+    let prefix = mac.get(..8)?;
+    
+    if let Some((_, vendor)) = OUI_VENDORS.iter().find(|(oui, _)| *oui == prefix) {
+        return Some(vendor);
+    }
+    
+    // Second-least-significant bit of the first octet marks locally administered
+    // addresses: cloud ENIs, containers, and phones with MAC randomization
+    let first = u8::from_str_radix(mac.get(..2)?, 16).ok()?;
+    (first & 0x02 != 0).then_some("locally administered")
+}
+
+/// Send one empty UDP datagram to every address in the network so the kernel
+/// resolves (and caches) each host's MAC, replying or not. Needs no privileges.
+pub fn prime(base: u32, prefix: u8) -> Result<()> {
+    let (first, last) = cidr::range(base, prefix);
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    
+    for ip in first..=last {
+        // Port 9 is the discard service; nothing needs to be listening
+        let _ = socket.send_to(&[], SocketAddrV4::new(Ipv4Addr::from(ip), 9));
+    }
+    
+    Ok(())
+}
+
+/// Resolved neighbors inside the network, from `ip neigh` or `/proc/net/arp`
+pub fn neighbors(base: u32, prefix: u8) -> Result<Vec<Neighbor>> {
+    let (first, last) = cidr::range(base, prefix);
+    
+    let mut found = match Command::new("ip").args(["neigh", "show"]).output() {
+        Ok(output) if output.status.success() => parse_ip_neigh(&String::from_utf8_lossy(&output.stdout)),
+        _ => parse_proc_arp(&std::fs::read_to_string("/proc/net/arp")
+            .map_err(|e| anyhow::anyhow!("Cannot read the neighbor table (ip neigh or /proc/net/arp): {}", e))?),
+    };
+    
+    found.retain(|n| n.ip.parse::<Ipv4Addr>().is_ok_and(|ip| (first..=last).contains(&u32::from(ip))));
+    found.sort_by_key(|n| n.ip.parse::<Ipv4Addr>().map(u32::from).unwrap_or(0));
+    found.dedup_by(|a, b| a.ip == b.ip);
+    
+    Ok(found)
+}
+
+/// "192.168.1.1 dev eth0 lladdr aa:bb:cc:dd:ee:ff REACHABLE"; entries without
+/// `lladdr` (FAILED, INCOMPLETE) never resolved and are skipped
+fn parse_ip_neigh(stdout: &str) -> Vec<Neighbor> {
+    stdout.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let after = |key: &str| fields.iter().position(|f| *f == key).and_then(|i| fields.get(i + 1)).copied();
+            
+            let mac = after("lladdr")?.to_ascii_lowercase();
+            Some(Neighbor {
+                ip: fields.first()?.to_string(),
+                vendor: vendor(&mac),
+                mac,
+                state: fields.last().copied().unwrap_or("unknown").to_string(),
+                device: after("dev").unwrap_or("unknown").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `/proc/net/arp` columns: IP, HW type, flags, MAC, mask, device
+fn parse_proc_arp(raw: &str) -> Vec<Neighbor> {
+    raw.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let mac = fields.get(3)?.to_ascii_lowercase();
+            // Flag 0x0 means the entry is incomplete
+            if mac == "00:00:00:00:00:00" || fields.get(2) == Some(&"0x0") {
+                return None;
+            }
+            
+            Some(Neighbor {
+                ip: fields[0].to_string(),
+                vendor: vendor(&mac),
+                mac,
+                state: "cached".to_string(),
+                device: fields.get(5).unwrap_or(&"unknown").to_string(),
+            })
+        })
+        .collect()
+}
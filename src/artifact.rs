@@ -0,0 +1,30 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Where generated files go when the user didn't name them (`--output-dir`);
+/// `None` means the working directory
+static OUTPUT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn configure(dir: Option<PathBuf>) {
+    *OUTPUT_DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = dir;
+}
+
+/// `<command>-<region>-<timestamp>.<ext>`, the name every generated file gets
+pub fn file_name(command: &str, region: &str, ext: &str) -> String {
+    format!("{}-{}-{}.{}", command, region, Utc::now().format("%Y%m%dT%H%M%SZ"), ext)
+}
+
+/// Path for a new auto-named file, creating the output directory if it is missing
+pub fn path(command: &str, region: &str, ext: &str) -> Result<PathBuf> {
+    let name = file_name(command, region, ext);
+    let Some(dir) = OUTPUT_DIR.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone() else {
+        return Ok(PathBuf::from(name));
+    };
+    
+    fs::create_dir_all(&dir)
+        .map_err(|e| anyhow::anyhow!("Cannot create output directory {}: {}", dir.display(), e))?;
+    Ok(dir.join(name))
+}
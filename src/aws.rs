@@ -1,711 +1,4647 @@
+use crate::artifact;
+use crate::awscli;
+use crate::cidr;
+use crate::events;
+use crate::output::{self, Layout, OutputFormat, Table};
+use crate::pricing::{Pricing, Rate};
+use crate::rules::{self, Direction, RiskyPort, Ruleset, Severity};
+use crate::timing;
+use crate::whois;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use colored::*;
+use regex::Regex;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
-use std::process::Command;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 
-pub fn map_vpc_topology(region: &str) -> Result<()> {
-    println!("{}", "🗺️  Mapping VPC Topology...".cyan().bold());
+/// Concurrent per-VPC fetches in `gather_topology`
+const TOPOLOGY_WORKERS: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Topology {
+    region: String,
+    transit_gateways: Vec<TransitGatewayInfo>,
+    vpcs: Vec<VpcInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TransitGatewayInfo {
+    id: String,
+    state: String,
+    attachments: Vec<TgwAttachmentInfo>,
+    /// Empty in snapshots taken before route tables were recorded
+    #[serde(default)]
+    route_tables: Vec<TgwRouteTableInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TgwAttachmentInfo {
+    id: String,
+    resource_id: String,
+    state: String,
+}
+
+/// A TGW route table: which attachments it routes for, which feed it routes, and what it holds
+#[derive(Debug, Serialize, Deserialize)]
+struct TgwRouteTableInfo {
+    id: String,
+    state: String,
+    default_association: bool,
+    default_propagation: bool,
+    /// Attachments whose traffic is looked up in this table
+    associations: Vec<TgwAttachmentInfo>,
+    /// Attachments whose CIDRs are propagated into this table
+    propagations: Vec<TgwAttachmentInfo>,
+    routes: Vec<TgwRouteInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TgwRouteInfo {
+    destination: String,
+    /// "static" or "propagated"
+    route_type: String,
+    /// "active" or "blackhole"
+    state: String,
+    /// Resources the route forwards to (VPC, VPN, peering, ...)
+    targets: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VpcInfo {
+    id: String,
+    /// Primary IPv4 block
+    cidr: String,
+    /// Every associated block, primary first; empty in snapshots taken before it was recorded
+    #[serde(default)]
+    cidr_blocks: Vec<CidrBlockInfo>,
+    is_default: bool,
+    subnets: Vec<SubnetInfo>,
+    route_tables: Vec<RouteTableInfo>,
+    instances: Vec<InstanceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CidrBlockInfo {
+    cidr: String,
+    /// "associated", "associating", "disassociating", "failed", ...
+    state: String,
+    ipv6: bool,
+}
+
+impl CidrBlockInfo {
+    /// Blocks that are mid-association already have subnets carved out of them
+    fn in_use(&self) -> bool {
+        matches!(self.state.as_str(), "associated" | "associating")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SubnetInfo {
+    id: String,
+    cidr: String,
+    availability_zone: String,
+    available_ips: u64,
+    route_table_id: Option<String>,
+    reachability: Reachability,
+    #[serde(default)]
+    name: Option<String>,
+    /// The `Tier` tag (public, private, data, ...) as the owner labeled it
+    #[serde(default)]
+    tier: Option<String>,
+}
+
+impl SubnetInfo {
+    /// True when the `Tier` tag contradicts the route table: tagged public
+    /// without an internet gateway route, or tagged private/data/isolated with one
+    fn tier_mismatch(&self) -> bool {
+        let Some(tier) = self.tier.as_deref().map(str::to_ascii_lowercase) else { return false };
+        match tier.as_str() {
+            "public" | "dmz" => self.reachability != Reachability::Public,
+            "private" | "data" | "db" | "database" | "isolated" | "internal" => self.reachability == Reachability::Public,
+            _ => false,
+        }
+    }
+    
+    /// ` "web-a"` after the ID, when the subnet has a Name
+    fn name_label(&self) -> String {
+        self.name.as_deref().map(|name| format!(" \"{}\"", name)).unwrap_or_default()
+    }
+    
+    /// ` - Tier=private`, plus a warning when the route table disagrees
+    fn tier_label(&self) -> String {
+        let Some(tier) = &self.tier else { return String::new() };
+        let mut label = format!(" - Tier={}", tier).bright_black().to_string();
+        if self.tier_mismatch() {
+            let actual = match self.reachability {
+                Reachability::Public => "routes to an internet gateway",
+                _ => "has no internet gateway route",
+            };
+            label.push_str(&format!(" {} {}", "⚠️ ".red(), format!("tagged {} but {}", tier, actual).red()));
+        }
+        label
+    }
+}
+
+/// Internet reachability of a subnet, derived from the default route of its route table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Reachability {
+    /// 0.0.0.0/0 → igw-*
+    Public,
+    /// 0.0.0.0/0 → nat-*
+    PrivateWithNat,
+    /// No default route to the internet
+    Isolated,
+}
+
+impl Reachability {
+    fn of(route_table: Option<&RouteTableInfo>) -> Self {
+        let default_target = route_table
+            .and_then(|rt| rt.routes.iter()
+                .find(|r| r.destination == "0.0.0.0/0" && r.state != "blackhole"))
+            .map(|r| r.target.as_str());
+        
+        match default_target {
+            Some(target) if target.starts_with("igw-") => Reachability::Public,
+            Some(target) if target.starts_with("nat-") => Reachability::PrivateWithNat,
+            _ => Reachability::Isolated,
+        }
+    }
+    
+    /// Same spelling as the serialized form
+    fn as_str(self) -> &'static str {
+        match self {
+            Reachability::Public => "public",
+            Reachability::PrivateWithNat => "private-with-nat",
+            Reachability::Isolated => "isolated",
+        }
+    }
+    
+    fn label(self) -> ColoredString {
+        match self {
+            Reachability::Public => "🌐 public".green(),
+            Reachability::PrivateWithNat => "🔀 private (NAT)".yellow(),
+            Reachability::Isolated => "🔒 isolated".bright_black(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RouteTableInfo {
+    id: String,
+    /// Implicitly used by subnets without an explicit association
+    is_main: bool,
+    subnet_ids: Vec<String>,
+    routes: Vec<RouteInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RouteInfo {
+    destination: String,
+    target: String,
+    state: String,
+    /// Name of a prefix-list destination (`com.amazonaws.us-east-1.s3`, or a customer list's name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    destination_name: Option<String>,
+    /// What the target is when its ID doesn't say, e.g. "S3 endpoint"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_name: Option<String>,
+}
+
+impl RouteInfo {
+    fn destination_label(&self) -> String {
+        match &self.destination_name {
+            Some(name) => format!("{} ({})", self.destination, name),
+            None => self.destination.clone(),
+        }
+    }
+    
+    fn target_label(&self) -> String {
+        match &self.target_name {
+            Some(name) => format!("{} ({})", self.target, name),
+            None => self.target.clone(),
+        }
+    }
+}
+
+/// How `compliance` arranges its findings table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// One section per source CIDR (or peer), broadest first, then by group
+    Source,
+}
+
+/// Which instances `aws-map` shows and `cost` counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InstanceState {
+    Running,
+    /// Stopped or stopping: no compute charge, but EBS volumes are still billed
+    Stopped,
+    All,
+}
+
+impl InstanceState {
+    fn includes(self, state: &str) -> bool {
+        match self {
+            InstanceState::Running => state == "running",
+            InstanceState::Stopped => matches!(state, "stopped" | "stopping"),
+            InstanceState::All => true,
+        }
+    }
+}
+
+/// `aws-map`'s instance filters: launch window and state
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub state: InstanceState,
+    /// Terminated instances linger in describe output for about an hour; hidden unless set
+    pub include_terminated: bool,
+}
+
+impl InstanceFilter {
+    /// Drop the instances the filter excludes; returns how many were hidden only for being terminated
+    fn apply(&self, topology: &mut Topology) -> usize {
+        let mut terminated = 0;
+        for vpc in &mut topology.vpcs {
+            vpc.instances.retain(|i| {
+                let shown = self.state.includes(&i.state)
+                    && self.since.is_none_or(|since| i.launched_at().is_some_and(|t| t >= since));
+                if shown && !self.include_terminated && i.state == "terminated" {
+                    terminated += 1;
+                    return false;
+                }
+                shown
+            });
+        }
+        terminated
+    }
+    
+    /// Notes above the map saying which instances are left out
+    fn print_notes(&self, layout: &Layout, terminated_hidden: usize) {
+        if terminated_hidden > 0 {
+            layout.line(&format!("{} {}", "🔎".cyan(), format!("{} terminated instance(s) hidden (--include-terminated shows them)", terminated_hidden).bright_black()));
+        }
+        if let Some(since) = self.since {
+            layout.line(&format!("{} {}", "🕐".cyan(), format!("Showing instances launched since {}", since.format("%Y-%m-%d %H:%M UTC")).bright_black()));
+        }
+        match self.state {
+            InstanceState::Running => layout.line(&format!("{} {}", "🔎".cyan(), "Showing running instances only".bright_black())),
+            InstanceState::Stopped => layout.line(&format!("{} {}", "🔎".cyan(), "Showing stopped instances only".bright_black())),
+            InstanceState::All => {}
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceInfo {
+    id: String,
+    name: String,
+    state: String,
+    private_ip: String,
+    instance_type: String,
+    subnet_id: Option<String>,
+    /// RFC 3339 timestamp from describe-instances
+    #[serde(default)]
+    launch_time: Option<String>,
+    #[serde(default)]
+    public_ip: Option<String>,
+    #[serde(default)]
+    security_groups: Vec<String>,
+    /// Sensitive services its security groups open to 0.0.0.0/0 or ::/0,
+    /// only computed for instances with a public IP
+    #[serde(default)]
+    exposed_services: Vec<String>,
+}
+
+impl InstanceInfo {
+    fn launched_at(&self) -> Option<DateTime<Utc>> {
+        self.launch_time.as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+fn gather_topology(region: &str) -> Result<Topology> {
+    let started = Instant::now();
+    let mut transit_gateways = Vec::new();
+    for tgw in get_transit_gateways(region)? {
+        let tgw_id = tgw["TransitGatewayId"].as_str().unwrap_or("unknown");
+        
+        let attachments = get_tgw_attachments(region, tgw_id)?
+            .iter()
+            .map(|att| TgwAttachmentInfo {
+                id: att["TransitGatewayAttachmentId"].as_str().unwrap_or("unknown").to_string(),
+                resource_id: att["ResourceId"].as_str().unwrap_or("unknown").to_string(),
+                state: att["State"].as_str().unwrap_or("unknown").to_string(),
+            })
+            .collect();
+        
+        let route_tables = get_tgw_route_tables(region, tgw_id)?
+            .iter()
+            .map(|rt| gather_tgw_route_table(region, rt))
+            .collect::<Result<Vec<_>>>()?;
+        
+        transit_gateways.push(TransitGatewayInfo {
+            id: tgw_id.to_string(),
+            state: tgw["State"].as_str().unwrap_or("unknown").to_string(),
+            attachments,
+            route_tables,
+        });
+    }
+    
+    let vpc_list = get_vpcs(region)?;
+    report_vpcs(region, &vpc_list);
+    let next_vpc = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    
+    // Each VPC needs three describe calls, so fetch several VPCs at once
+    std::thread::scope(|scope| {
+        for _ in 0..TOPOLOGY_WORKERS.min(vpc_list.len()) {
+            scope.spawn(|| loop {
+                let i = next_vpc.fetch_add(1, Ordering::Relaxed);
+                let Some(vpc) = vpc_list.get(i) else {
+                    break;
+                };
+                
+                let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+                let info = gather_vpc(region, vpc, vpc_id);
+                results.lock().unwrap().push((i, info));
+            });
+        }
+    });
+    
+    // Restore the describe-vpcs order so output is stable between runs
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    let vpcs = results.into_iter()
+        .map(|(_, info)| info)
+        .collect::<Result<Vec<_>>>()?;
+    timing::record(started, || format!("fetched {} VPC(s) and {} TGW(s) in {}", vpcs.len(), transit_gateways.len(), region));
+    
+    Ok(Topology {
+        region: region.to_string(),
+        transit_gateways,
+        vpcs,
+    })
+}
+
+fn gather_tgw_route_table(region: &str, rt: &Value) -> Result<TgwRouteTableInfo> {
+    let rt_id = rt["TransitGatewayRouteTableId"].as_str().unwrap_or("unknown");
+    let attachment = |a: &Value| TgwAttachmentInfo {
+        id: a["TransitGatewayAttachmentId"].as_str().unwrap_or("unknown").to_string(),
+        resource_id: a["ResourceId"].as_str().unwrap_or("unknown").to_string(),
+        state: a["State"].as_str().unwrap_or("unknown").to_string(),
+    };
+    
+    let associations = tgw_route_table_query(region, "get-transit-gateway-route-table-associations", rt_id, "Associations")?
+        .iter()
+        .map(attachment)
+        .collect();
+    let propagations = tgw_route_table_query(region, "get-transit-gateway-route-table-propagations", rt_id, "TransitGatewayRouteTablePropagations")?
+        .iter()
+        .map(attachment)
+        .collect();
+    
+    let routes = tgw_route_table_query(region, "search-transit-gateway-routes", rt_id, "Routes")?
+        .iter()
+        .map(|route| TgwRouteInfo {
+            destination: route["DestinationCidrBlock"].as_str()
+                .or(route["PrefixListId"].as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            route_type: route["Type"].as_str().unwrap_or("unknown").to_string(),
+            state: route["State"].as_str().unwrap_or("unknown").to_string(),
+            targets: route["TransitGatewayAttachments"].as_array()
+                .map(|atts| atts.iter()
+                    .filter_map(|a| a["ResourceId"].as_str().or(a["TransitGatewayAttachmentId"].as_str()))
+                    .map(String::from)
+                    .collect())
+                .unwrap_or_default(),
+        })
+        .collect();
+    
+    Ok(TgwRouteTableInfo {
+        id: rt_id.to_string(),
+        state: rt["State"].as_str().unwrap_or("unknown").to_string(),
+        default_association: rt["DefaultAssociationRouteTable"].as_bool().unwrap_or(false),
+        default_propagation: rt["DefaultPropagationRouteTable"].as_bool().unwrap_or(false),
+        associations,
+        propagations,
+        routes,
+    })
+}
+
+fn gather_vpc(region: &str, vpc: &Value, vpc_id: &str) -> Result<VpcInfo> {
+    let raw_route_tables = get_route_tables(region, Some(vpc_id))?;
+    let prefix_list_ids: BTreeSet<&str> = raw_route_tables.iter()
+        .filter_map(|rt| rt["Routes"].as_array())
+        .flatten()
+        .filter_map(|route| route["DestinationPrefixListId"].as_str())
+        .collect();
+    let prefix_lists = get_prefix_list_names(region, &prefix_list_ids)?;
+    
+    let route_tables: Vec<RouteTableInfo> = raw_route_tables
+        .iter()
+        .map(|rt| {
+            let subnet_ids = rt["Associations"].as_array()
+                .map(|assocs| assocs.iter()
+                    .filter_map(|a| a["SubnetId"].as_str().map(String::from))
+                    .collect())
+                .unwrap_or_default();
+            
+            let routes = rt["Routes"].as_array()
+                .map(|routes| routes.iter()
+                    .map(|route| {
+                        let target = route_target(route);
+                        let destination_name = route["DestinationPrefixListId"].as_str()
+                            .and_then(|id| prefix_lists.get(id))
+                            .cloned();
+                        RouteInfo {
+                            destination: route_destination(route).to_string(),
+                            target: target.to_string(),
+                            state: route["State"].as_str().unwrap_or("active").to_string(),
+                            target_name: endpoint_label(target, destination_name.as_deref()),
+                            destination_name,
+                        }
+                    })
+                    .collect())
+                .unwrap_or_default();
+            
+            RouteTableInfo {
+                id: rt["RouteTableId"].as_str().unwrap_or("unknown").to_string(),
+                is_main: rt["Associations"].as_array()
+                    .is_some_and(|assocs| assocs.iter().any(|a| a["Main"].as_bool() == Some(true))),
+                subnet_ids,
+                routes,
+            }
+        })
+        .collect();
+    
+    let subnets = get_subnets(region, vpc_id)?
+        .iter()
+        .map(|subnet| {
+            let subnet_id = subnet["SubnetId"].as_str().unwrap_or("unknown");
+            let explicit = route_tables.iter()
+                .find(|rt| rt.subnet_ids.iter().any(|s| s == subnet_id));
+            let effective = explicit.or_else(|| route_tables.iter().find(|rt| rt.is_main));
+            
+            SubnetInfo {
+                id: subnet_id.to_string(),
+                cidr: subnet["CidrBlock"].as_str().unwrap_or("unknown").to_string(),
+                availability_zone: subnet["AvailabilityZone"].as_str().unwrap_or("unknown").to_string(),
+                available_ips: subnet["AvailableIpAddressCount"].as_u64().unwrap_or(0),
+                route_table_id: explicit.map(|rt| rt.id.clone()),
+                reachability: Reachability::of(effective),
+                name: tag_value(subnet, "Name").map(String::from),
+                tier: tag_value(subnet, "Tier").or(tag_value(subnet, "tier")).map(String::from),
+            }
+        })
+        .collect();
+    
+    let raw_instances = get_instances(region, vpc_id)?;
+    
+    // Security groups only matter for instances reachable from outside
+    let exposure = if raw_instances.iter().any(|i| i["PublicIpAddress"].is_string()) {
+        world_open_ports(region, &Ruleset::default())?
+    } else {
+        BTreeMap::new()
+    };
+    
+    let instances = raw_instances.iter()
+        .map(|instance| {
+            let public_ip = instance["PublicIpAddress"].as_str().map(String::from);
+            let security_groups: Vec<String> = instance["SecurityGroups"].as_array()
+                .map(|groups| groups.iter()
+                    .filter_map(|g| g["GroupId"].as_str().map(String::from))
+                    .collect())
+                .unwrap_or_default();
+            
+            let mut exposed_services: Vec<String> = Vec::new();
+            if public_ip.is_some() {
+                let open_ports = security_groups.iter().filter_map(|g| exposure.get(g)).flatten();
+                for port in open_ports {
+                    // All traffic is named as such rather than as every risky port
+                    let services = if port.protocol == "all" { vec!["ALL".to_string()] } else { port.services.clone() };
+                    for service in services {
+                        if !exposed_services.contains(&service) {
+                            exposed_services.push(service);
+                        }
+                    }
+                }
+            }
+            
+            InstanceInfo {
+                id: instance["InstanceId"].as_str().unwrap_or("unknown").to_string(),
+                name: tag_value(instance, "Name").unwrap_or("unnamed").to_string(),
+                state: instance["State"]["Name"].as_str().unwrap_or("unknown").to_string(),
+                private_ip: instance["PrivateIpAddress"].as_str().unwrap_or("N/A").to_string(),
+                instance_type: instance["InstanceType"].as_str().unwrap_or("unknown").to_string(),
+                subnet_id: instance["SubnetId"].as_str().map(String::from),
+                launch_time: instance["LaunchTime"].as_str().map(String::from),
+                public_ip,
+                security_groups,
+                exposed_services,
+            }
+        })
+        .collect();
+    
+    Ok(VpcInfo {
+        id: vpc_id.to_string(),
+        cidr: vpc["CidrBlock"].as_str().unwrap_or("unknown").to_string(),
+        cidr_blocks: vpc_cidr_blocks(vpc),
+        is_default: vpc["IsDefault"].as_bool().unwrap_or(false),
+        subnets,
+        route_tables,
+        instances,
+    })
+}
+
+/// All IPv4 and IPv6 blocks from the VPC's association sets, primary block first.
+/// Responses without the sets fall back to the primary `CidrBlock`.
+fn vpc_cidr_blocks(vpc: &Value) -> Vec<CidrBlockInfo> {
+    let primary = vpc["CidrBlock"].as_str();
+    let sets = [
+        ("CidrBlockAssociationSet", "CidrBlock", "CidrBlockState", false),
+        ("Ipv6CidrBlockAssociationSet", "Ipv6CidrBlock", "Ipv6CidrBlockState", true),
+    ];
+    
+    let mut blocks: Vec<CidrBlockInfo> = sets.iter()
+        .flat_map(|(set, cidr_key, state_key, ipv6)| {
+            vpc[*set].as_array().into_iter().flatten().filter_map(move |assoc| {
+                Some(CidrBlockInfo {
+                    cidr: assoc[*cidr_key].as_str()?.to_string(),
+                    state: assoc[*state_key]["State"].as_str().unwrap_or("associated").to_string(),
+                    ipv6: *ipv6,
+                })
+            })
+        })
+        .collect();
+    
+    if let Some(primary) = primary {
+        if !blocks.iter().any(|b| b.cidr == primary) {
+            blocks.push(CidrBlockInfo { cidr: primary.to_string(), state: "associated".to_string(), ipv6: false });
+        }
+        blocks.sort_by_key(|b| b.cidr != primary);
+    }
+    
+    blocks
+}
+
+/// "10.0.0.0/16, 100.64.0.0/16, 2600:1f18::/56 (IPv6)" with any state other than associated noted
+fn cidr_blocks_label(blocks: &[CidrBlockInfo], primary: &str) -> String {
+    if blocks.is_empty() {
+        return primary.green().to_string();
+    }
+    
+    blocks.iter()
+        .map(|block| {
+            let mut label = block.cidr.green().to_string();
+            if block.ipv6 {
+                label.push_str(&" (IPv6)".bright_black().to_string());
+            }
+            if block.state != "associated" {
+                label.push_str(&format!(" [{}]", block.state).yellow().to_string());
+            }
+            label
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn tag_value<'a>(resource: &'a Value, key: &str) -> Option<&'a str> {
+    resource["Tags"]
+        .as_array()
+        .and_then(|tags| {
+            tags.iter()
+                .find(|t| t["Key"].as_str() == Some(key))
+                .and_then(|t| t["Value"].as_str())
+        })
+}
+
+fn route_destination(route: &Value) -> &str {
+    route["DestinationCidrBlock"].as_str()
+        .or(route["DestinationPrefixListId"].as_str())
+        .unwrap_or("unknown")
+}
+
+/// The route's target ID. Gateway endpoints (prefix-list routes to S3 and
+/// DynamoDB) arrive as a `vpce-` ID in `GatewayId`; local routes as `local`.
+fn route_target(route: &Value) -> &str {
+    route["GatewayId"].as_str()
+        .or(route["NatGatewayId"].as_str())
+        .or(route["TransitGatewayId"].as_str())
+        .or(route["VpcPeeringConnectionId"].as_str())
+        .or(route["EgressOnlyInternetGatewayId"].as_str())
+        .or(route["LocalGatewayId"].as_str())
+        .or(route["CarrierGatewayId"].as_str())
+        .or(route["CoreNetworkArn"].as_str())
+        .or(route["NetworkInterfaceId"].as_str())
+        .or(route["InstanceId"].as_str())
+        .unwrap_or("unknown")
+}
+
+/// "S3 endpoint" for a gateway endpoint target, named after the service in
+/// its prefix list (`com.amazonaws.us-east-1.s3`)
+fn endpoint_label(target: &str, prefix_list_name: Option<&str>) -> Option<String> {
+    if !target.starts_with("vpce-") {
+        return None;
+    }
+    
+    let service = prefix_list_name
+        .filter(|name| name.starts_with("com.amazonaws."))
+        .and_then(|name| name.rsplit('.').next());
+    Some(match service {
+        Some("s3") => "S3 endpoint".to_string(),
+        Some("dynamodb") => "DynamoDB endpoint".to_string(),
+        Some(service) => format!("{} endpoint", service),
+        None => "VPC endpoint".to_string(),
+    })
+}
+
+/// Names for prefix-list IDs. Customer-managed lists and the AWS-managed
+/// lists both appear in `describe-managed-prefix-lists`; `describe-prefix-lists`
+/// covers the service lists on accounts where that call is denied. Names are
+/// cosmetic, so failed lookups only leave the IDs unnamed.
+fn get_prefix_list_names(region: &str, ids: &BTreeSet<&str>) -> Result<HashMap<String, String>> {
+    let mut names = HashMap::new();
+    
+    for operation in ["describe-managed-prefix-lists", "describe-prefix-lists"] {
+        let missing: Vec<&str> = ids.iter().copied().filter(|id| !names.contains_key(*id)).collect();
+        if missing.is_empty() {
+            break;
+        }
+        
+        let filter = format!("Name=prefix-list-id,Values={}", missing.join(","));
+        let Ok(output) = awscli::run(&["ec2", operation, "--region", region, "--filters", &filter]) else { continue };
+        if !output.success {
+            continue;
+        }
+        
+        let json: Value = serde_json::from_slice(&output.stdout)?;
+        for list in json["PrefixLists"].as_array().into_iter().flatten() {
+            if let (Some(id), Some(name)) = (list["PrefixListId"].as_str(), list["PrefixListName"].as_str()) {
+                names.insert(id.to_string(), name.to_string());
+            }
+        }
+    }
+    
+    Ok(names)
+}
+
+#[derive(Debug, Serialize)]
+struct InstancePath {
+    instance_id: String,
+    vpc_id: String,
+    subnet_id: String,
+    route_table_id: Option<String>,
+    private_ip: Option<String>,
+    public_ip: Option<String>,
+    /// Hops from the instance outward, ending at the internet when it is reachable
+    path: Vec<String>,
+    /// `None` when the path leaves the VPC somewhere netkit can't follow (TGW, appliance)
+    reaches_internet: Option<bool>,
+    note: String,
+}
+
+pub fn instance_path(region: &str, instance_id: &str, format: OutputFormat) -> Result<()> {
+    let output = awscli::run(&["ec2", "describe-instances", "--region", region, "--instance-ids", instance_id])?;
+    if !output.success {
+        return Err(anyhow::anyhow!("Instance {} not found in {}", instance_id, region));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let instance = json["Reservations"].as_array().into_iter().flatten()
+        .filter_map(|r| r["Instances"].as_array())
+        .flatten()
+        .find(|i| i["InstanceId"].as_str() == Some(instance_id))
+        .ok_or_else(|| anyhow::anyhow!("Instance {} not found in {}", instance_id, region))?;
+    
+    let (Some(subnet_id), Some(vpc_id)) = (instance["SubnetId"].as_str(), instance["VpcId"].as_str()) else {
+        return Err(anyhow::anyhow!("Instance {} has no subnet (is it terminated?)", instance_id));
+    };
+    let private_ip = instance["PrivateIpAddress"].as_str().map(String::from);
+    let public_ip = instance["PublicIpAddress"].as_str().map(String::from);
+    
+    let route_tables = get_route_tables(region, Some(vpc_id))?;
+    let addresses = [private_ip.as_deref(), public_ip.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" / ");
+    let mut path = vec![if addresses.is_empty() { instance_id.to_string() } else { format!("{} ({})", instance_id, addresses) }];
+    let route_table_id = subnet_route_table(&route_tables, subnet_id)
+        .and_then(|rt| rt["RouteTableId"].as_str())
+        .map(String::from);
+    
+    let (reaches_internet, note) = trace_default_route(region, &route_tables, subnet_id, public_ip.is_some(), &mut path, true)?;
+    
+    let report = InstancePath {
+        instance_id: instance_id.to_string(),
+        vpc_id: vpc_id.to_string(),
+        subnet_id: subnet_id.to_string(),
+        route_table_id,
+        private_ip,
+        public_ip,
+        path,
+        reaches_internet,
+        note,
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &report);
+    }
+    
+    println!("{}", format!("🧭 Egress path for {}", report.instance_id).cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("{}", report.path.join(" → "));
+    println!();
+    match report.reaches_internet {
+        Some(true) => println!("{} {}", "✅".green(), report.note.green()),
+        Some(false) => println!("{} {}", "⛔".red(), report.note.red()),
+        None => println!("{} {}", "❓".yellow(), report.note.yellow()),
+    }
+    println!("{}", "═".repeat(70).bright_black());
+    
+    Ok(())
+}
+
+/// Longest `check_from` waits for the SSM command to finish
+const SSM_COMMAND_WAIT: std::time::Duration = std::time::Duration::from_secs(90);
+
+#[derive(Debug, Serialize)]
+struct CheckFromResult {
+    instance_id: String,
+    /// Host name the SSM agent reports, when it reports one
+    computer_name: Option<String>,
+    target: String,
+    port: u16,
+    /// "open", "closed" (refused or unreachable), or "timeout"
+    result: &'static str,
+    command_id: String,
+}
+
+/// TCP connect test run on the instance itself through SSM Run Command, so
+/// security groups, NACLs, routes, and host firewalls all get their say.
+/// Returns whether the port was open.
+pub fn check_from(region: &str, instance_id: &str, target: &str, port: u16, timeout_secs: u64, format: OutputFormat) -> Result<bool> {
+    // The target is pasted into a shell script on the instance
+    if target.is_empty() || !target.chars().all(|c| c.is_ascii_alphanumeric() || ".-:_".contains(c)) {
+        return Err(anyhow::anyhow!("Invalid target '{}': expected a host name or IP address", target));
+    }
+    
+    let filter = format!("Key=InstanceIds,Values={}", instance_id);
+    let output = awscli::run(&["ssm", "describe-instance-information", "--region", region, "--filters", &filter])?;
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to query SSM for {}: {}", instance_id, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let info = json["InstanceInformationList"].as_array().and_then(|list| list.first()).ok_or_else(|| anyhow::anyhow!(
+        "Instance {} is not managed by SSM in {}: it needs a running SSM agent and an instance profile allowing it (e.g. AmazonSSMManagedInstanceCore)",
+        instance_id, region))?;
+    let ping_status = info["PingStatus"].as_str().unwrap_or("Unknown");
+    if ping_status != "Online" {
+        return Err(anyhow::anyhow!("SSM agent on {} is {} (last seen {}); it can't run commands right now",
+            instance_id, ping_status, info["LastPingDateTime"].as_str().unwrap_or("never")));
+    }
+    
+    let (document, script) = if info["PlatformType"].as_str() == Some("Windows") {
+        ("AWS-RunPowerShellScript", format!(
+            "$c = New-Object System.Net.Sockets.TcpClient; try {{ if ($c.ConnectAsync('{}', {}).Wait({})) {{ 'NETKIT_RESULT=open' }} else {{ 'NETKIT_RESULT=timeout' }} }} catch {{ 'NETKIT_RESULT=closed' }}",
+            target, port, timeout_secs * 1000))
+    } else {
+        // bash's /dev/tcp needs no extra tools; `timeout` exits 124 when it has to kill the attempt
+        ("AWS-RunShellScript", format!(
+            "timeout {} bash -c 'exec 3<>/dev/tcp/{}/{}' 2>/dev/null; case $? in 0) echo NETKIT_RESULT=open;; 124) echo NETKIT_RESULT=timeout;; *) echo NETKIT_RESULT=closed;; esac",
+            timeout_secs, target, port))
+    };
+    let parameters = serde_json::json!({ "commands": [script] }).to_string();
+    
+    let output = awscli::run(&[
+        "ssm", "send-command", "--region", region, "--instance-ids", instance_id,
+        "--document-name", document, "--parameters", &parameters, "--comment", "netkit check-from",
+    ])?;
+    if !output.success {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("AccessDenied") {
+            return Err(anyhow::anyhow!("Access denied sending the test to {} (needs ssm:SendCommand)", instance_id));
+        }
+        return Err(anyhow::anyhow!("Failed to send the test to {}: {}", instance_id, stderr.trim()));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let command_id = json["Command"]["CommandId"].as_str()
+        .ok_or_else(|| anyhow::anyhow!("SSM returned no command ID"))?
+        .to_string();
+    
+    if !format.is_structured() {
+        println!("{} Running connect test on {} (command {})...", "→".cyan(), instance_id.yellow(), command_id.bright_black());
+    }
+    
+    let started = Instant::now();
+    let invocation = loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let output = awscli::run(&[
+            "ssm", "get-command-invocation", "--region", region, "--command-id", &command_id, "--instance-id", instance_id,
+        ])?;
+        // The invocation takes a moment to exist after send-command returns
+        if output.success {
+            let invocation: Value = serde_json::from_slice(&output.stdout)?;
+            if !matches!(invocation["Status"].as_str(), Some("Pending" | "InProgress" | "Delayed")) {
+                break invocation;
+            }
+        } else if !String::from_utf8_lossy(&output.stderr).contains("InvocationDoesNotExist") {
+            return Err(anyhow::anyhow!("Failed to read the result of command {}: {}",
+                command_id, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        if started.elapsed() > SSM_COMMAND_WAIT {
+            return Err(anyhow::anyhow!("Command {} did not finish on {} within {}s", command_id, instance_id, SSM_COMMAND_WAIT.as_secs()));
+        }
+    };
+    
+    let stdout = invocation["StandardOutputContent"].as_str().unwrap_or_default();
+    let result = match stdout.lines().find_map(|line| line.trim().strip_prefix("NETKIT_RESULT=")) {
+        Some("open") => "open",
+        Some("timeout") => "timeout",
+        Some(_) => "closed",
+        None => return Err(anyhow::anyhow!("Command {} ended {} on {}: {}",
+            command_id,
+            invocation["Status"].as_str().unwrap_or("unknown").to_lowercase(),
+            instance_id,
+            invocation["StandardErrorContent"].as_str().map(str::trim).filter(|e| !e.is_empty()).unwrap_or("no output"))),
+    };
+    
+    let report = CheckFromResult {
+        instance_id: instance_id.to_string(),
+        computer_name: info["ComputerName"].as_str().map(String::from),
+        target: target.to_string(),
+        port,
+        result,
+        command_id,
+    };
+    
+    if format.is_structured() {
+        output::print(format, &report)?;
+        return Ok(result == "open");
+    }
+    
+    let from = match &report.computer_name {
+        Some(name) => format!("{} ({})", report.instance_id, name),
+        None => report.instance_id.clone(),
+    };
+    let to = format!("{}:{}", report.target, report.port);
+    println!();
+    match result {
+        "open" => println!("{} {} can reach {}", "✅".green(), from.cyan(), to.green().bold()),
+        "timeout" => println!("{} {} got no answer from {} within {}s (dropped by a security group, NACL, route, or firewall)",
+            "⏱️ ".yellow(), from.cyan(), to.yellow().bold(), timeout_secs),
+        _ => println!("{} {} was refused by {} (reachable host, nothing listening, or the name didn't resolve)",
+            "❌".red(), from.cyan(), to.red().bold()),
+    }
+    
+    Ok(result == "open")
+}
+
+/// The table a subnet uses: its explicit association, or the VPC's main table
+fn subnet_route_table<'a>(route_tables: &'a [Value], subnet_id: &str) -> Option<&'a Value> {
+    let associations = |rt: &Value| rt["Associations"].as_array().cloned().unwrap_or_default();
+    route_tables.iter()
+        .find(|rt| associations(rt).iter().any(|a| a["SubnetId"].as_str() == Some(subnet_id)))
+        .or_else(|| route_tables.iter().find(|rt| associations(rt).iter().any(|a| a["Main"].as_bool() == Some(true))))
+}
+
+/// Follow a subnet's 0.0.0.0/0 route, appending each hop to `path`. A NAT
+/// gateway is followed once more through its own subnet's route table.
+fn trace_default_route(
+    region: &str,
+    route_tables: &[Value],
+    subnet_id: &str,
+    has_public_ip: bool,
+    path: &mut Vec<String>,
+    follow_nat: bool,
+) -> Result<(Option<bool>, String)> {
+    path.push(subnet_id.to_string());
+    
+    let Some(rt) = subnet_route_table(route_tables, subnet_id) else {
+        return Ok((Some(false), format!("No route table found for {}", subnet_id)));
+    };
+    let rt_id = rt["RouteTableId"].as_str().unwrap_or("unknown");
+    let is_main = !rt["Associations"].as_array().into_iter().flatten().any(|a| a["SubnetId"].as_str() == Some(subnet_id));
+    path.push(if is_main { format!("{} (main)", rt_id) } else { rt_id.to_string() });
+    
+    let Some(route) = rt["Routes"].as_array().into_iter().flatten()
+        .find(|r| r["DestinationCidrBlock"].as_str() == Some("0.0.0.0/0")) else {
+        return Ok((Some(false), format!("{} has no default route; only VPC-local destinations are reachable", rt_id)));
+    };
+    
+    let target = route_target(route).to_string();
+    path.push(target.clone());
+    
+    if route["State"].as_str() == Some("blackhole") {
+        return Ok((Some(false), format!("The default route is a blackhole: {} was deleted or detached", target)));
+    }
+    
+    match target.split('-').next().unwrap_or_default() {
+        "igw" if has_public_ip => {
+            path.push("internet".to_string());
+            Ok((Some(true), format!("Reaches the internet directly through {}", target)))
+        }
+        "igw" => Ok((Some(false), format!("{} routes to {}, but the instance has no public IP", subnet_id, target))),
+        "nat" if follow_nat => {
+            let nat_subnet = awscli::run(&["ec2", "describe-nat-gateways", "--region", region, "--nat-gateway-ids", &target])
+                .ok()
+                .filter(|output| output.success)
+                .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+                .and_then(|json| json["NatGateways"].as_array().and_then(|nats| nats.iter()
+                    .find(|n| n["NatGatewayId"].as_str() == Some(target.as_str()))
+                    .and_then(|n| n["SubnetId"].as_str().map(String::from))));
+            
+            let Some(nat_subnet) = nat_subnet else {
+                return Ok((None, format!("Leaves through {}, whose subnet could not be looked up", target)));
+            };
+            // The NAT gateway's Elastic IP stands in for the instance's public address
+            let (reaches, note) = trace_default_route(region, route_tables, &nat_subnet, true, path, false)?;
+            let note = if reaches == Some(true) {
+                format!("Reaches the internet through {} in {}", target, nat_subnet)
+            } else {
+                format!("Routes to {} in {}, but: {}", target, nat_subnet, note)
+            };
+            Ok((reaches, note))
+        }
+        "tgw" => Ok((None, format!("Leaves the VPC through {}; egress continues in the transit gateway's route tables", target))),
+        "vgw" => Ok((None, format!("Sent to on-premises through VPN gateway {}", target))),
+        "pcx" => Ok((Some(false), format!("Sent to peering connection {}, which never forwards to the internet", target))),
+        _ => Ok((None, format!("Sent to {}; any further path depends on that appliance", target))),
+    }
+}
+
+pub fn map_vpc_topology(
+    region: &str,
+    save: Option<&Path>,
+    filter: InstanceFilter,
+    by_az: bool,
+    compact: bool,
+    layout: Layout,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut topology = gather_topology(region)?;
+    
+    if let Some(path) = save {
+        std::fs::write(path, serde_json::to_string_pretty(&topology)?)?;
+        if compact {
+            tracing::info!("Snapshot saved to {}", path.display());
+        } else if !format.is_structured() {
+            println!("{} Snapshot saved to {}", "💾".green(), path.display().to_string().yellow());
+            println!();
+        }
+    }
+    
+    // Snapshots stay complete; --since, --instance-state, and terminated hiding only narrow what is shown
+    let terminated_hidden = filter.apply(&mut topology);
+    
+    if format.is_structured() {
+        return output::print(format, &topology);
+    }
+    
+    if compact {
+        print_topology_compact(&topology);
+        return Ok(());
+    }
+    
+    filter.print_notes(&layout, terminated_hidden);
+    print_topology(&topology, by_az, &layout);
+    Ok(())
+}
+
+/// Redraw the topology every `interval` until interrupted, listing what was
+/// added or removed since the previous poll under the map. A failed refresh
+/// keeps the last good map on screen rather than ending the watch.
+pub fn watch_vpc_topology(
+    region: &str,
+    interval: std::time::Duration,
+    filter: InstanceFilter,
+    by_az: bool,
+    layout: Layout,
+) -> Result<()> {
+    use std::io::IsTerminal;
+    let clear = std::io::stdout().is_terminal();
+    let mut previous: Option<Topology> = None;
+    
+    loop {
+        match gather_topology(region) {
+            Ok(mut topology) => {
+                let terminated_hidden = filter.apply(&mut topology);
+                
+                if clear {
+                    print!("\x1b[2J\x1b[H");
+                } else if previous.is_some() {
+                    println!();
+                }
+                
+                filter.print_notes(&layout, terminated_hidden);
+                print_topology(&topology, by_az, &layout);
+                
+                if let Some(previous) = &previous {
+                    print_poll_changes(previous, &topology, &layout);
+                }
+                layout.line(&format!("{} {}", "🔄".cyan(), format!("Refreshed {}; next in {}s (Ctrl-C to stop)",
+                    Utc::now().format("%H:%M:%S UTC"), interval.as_secs()).bright_black()));
+                previous = Some(topology);
+            }
+            Err(e) if previous.is_none() => return Err(e),
+            Err(e) => {
+                layout.line(&format!("{} {}", "⚠️ ".yellow(), format!("Refresh failed at {}: {}; showing the last good map",
+                    Utc::now().format("%H:%M:%S UTC"), e).yellow()));
+            }
+        }
+        
+        std::io::stdout().flush()?;
+        std::thread::sleep(interval);
+    }
+}
+
+/// VPCs, subnets, instances, and routes that appeared or went away between two polls
+fn print_poll_changes(old: &Topology, new: &Topology, layout: &Layout) {
+    let [old_vpcs, old_subnets, old_instances, old_routes] = snapshot_index(old);
+    let [new_vpcs, new_subnets, new_instances, new_routes] = snapshot_index(new);
+    let sections = [
+        ("VPC", ChangeSet::between(old_vpcs, new_vpcs)),
+        ("subnet", ChangeSet::between(old_subnets, new_subnets)),
+        ("instance", ChangeSet::between(old_instances, new_instances)),
+        ("route", ChangeSet::between(old_routes, new_routes)),
+    ];
+    
+    if sections.iter().all(|(_, changes)| changes.is_empty()) {
+        layout.line(&"No changes since the last poll".bright_black().to_string());
+        return;
+    }
+    
+    layout.line(&"⚡ Changes since the last poll:".yellow().bold().to_string());
+    for (kind, changes) in &sections {
+        for item in &changes.added {
+            layout.line(&format!("  {} {} {}", "+".green().bold(), kind, item.green().bold()));
+        }
+        for item in &changes.removed {
+            layout.line(&format!("  {} {} {}", "−".red().bold(), kind, item.red().bold()));
+        }
+    }
+    layout.line("");
+}
+
+/// One tab-separated row per resource, kind first, for grep and awk. Rows
+/// below a VPC carry its ID, and `-` stands in for missing values.
+fn print_topology_compact(topology: &Topology) {
+    let row = |fields: &[&str]| {
+        let fields: Vec<&str> = fields.iter().map(|f| if f.is_empty() { "-" } else { f }).collect();
+        println!("{}", fields.join("\t"));
+    };
+    
+    for tgw in &topology.transit_gateways {
+        row(&["TGW", &tgw.id, &tgw.state]);
+        for att in &tgw.attachments {
+            row(&["TGW-ATTACHMENT", &att.id, &att.resource_id, &att.state, &tgw.id]);
+        }
+        for rt in &tgw.route_tables {
+            row(&["TGW-ROUTE-TABLE", &rt.id, &rt.state, &tgw.id]);
+        }
+    }
+    
+    for vpc in &topology.vpcs {
+        let cidrs: Vec<&str> = if vpc.cidr_blocks.is_empty() {
+            vec![vpc.cidr.as_str()]
+        } else {
+            vpc.cidr_blocks.iter().map(|b| b.cidr.as_str()).collect()
+        };
+        row(&["VPC", &vpc.id, &cidrs.join(","), if vpc.is_default { "default" } else { "" }]);
+        
+        for subnet in &vpc.subnets {
+            row(&[
+                "SUBNET",
+                &subnet.id,
+                &subnet.cidr,
+                &subnet.availability_zone,
+                subnet.reachability.as_str(),
+                &subnet.available_ips.to_string(),
+                &vpc.id,
+                subnet.tier.as_deref().unwrap_or_default(),
+                if subnet.tier_mismatch() { "tier-mismatch" } else { "" },
+                subnet.name.as_deref().unwrap_or_default(),
+            ]);
+        }
+        for rt in &vpc.route_tables {
+            row(&["ROUTE-TABLE", &rt.id, if rt.is_main { "main" } else { "" }, &rt.subnet_ids.join(","), &vpc.id]);
+        }
+        for instance in &vpc.instances {
+            row(&[
+                "INSTANCE",
+                &instance.id,
+                &instance.state,
+                &instance.instance_type,
+                &instance.private_ip,
+                instance.public_ip.as_deref().unwrap_or_default(),
+                instance.subnet_id.as_deref().unwrap_or_default(),
+                &vpc.id,
+                &instance.name,
+            ]);
+        }
+    }
+}
+
+/// Added and removed items between two snapshots, as display labels
+#[derive(Debug, Default, Serialize)]
+struct ChangeSet {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl ChangeSet {
+    fn between(old: BTreeMap<String, String>, new: BTreeMap<String, String>) -> Self {
+        ChangeSet {
+            added: new.iter().filter(|(k, _)| !old.contains_key(*k)).map(|(_, v)| v.clone()).collect(),
+            removed: old.iter().filter(|(k, _)| !new.contains_key(*k)).map(|(_, v)| v.clone()).collect(),
+        }
+    }
+    
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TopologyDiff {
+    old_region: String,
+    new_region: String,
+    vpcs: ChangeSet,
+    subnets: ChangeSet,
+    instances: ChangeSet,
+    routes: ChangeSet,
+}
+
+fn load_snapshot(path: &Path) -> Result<Topology> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read snapshot {}: {}", path.display(), e))?;
+    serde_json::from_str(&raw)
+        .map_err(|e| anyhow::anyhow!("Invalid snapshot {}: {}", path.display(), e))
+}
+
+/// Index a snapshot's resources by ID, with a human-readable label for each
+fn snapshot_index(topology: &Topology) -> [BTreeMap<String, String>; 4] {
+    let mut vpcs = BTreeMap::new();
+    let mut subnets = BTreeMap::new();
+    let mut instances = BTreeMap::new();
+    let mut routes = BTreeMap::new();
+    
+    for vpc in &topology.vpcs {
+        vpcs.insert(vpc.id.clone(), format!("{} ({})", vpc.id, vpc.cidr));
+        
+        for subnet in &vpc.subnets {
+            subnets.insert(subnet.id.clone(), format!("{} ({}) in {}", subnet.id, subnet.cidr, vpc.id));
+        }
+        for instance in &vpc.instances {
+            instances.insert(instance.id.clone(), format!("{} ({}, {}) in {}", instance.id, instance.name, instance.instance_type, vpc.id));
+        }
+        for rt in &vpc.route_tables {
+            for route in &rt.routes {
+                let label = format!("{}: {} → {}", rt.id, route.destination, route.target);
+                routes.insert(label.clone(), label);
+            }
+        }
+    }
+    
+    [vpcs, subnets, instances, routes]
+}
+
+pub fn diff_topology_snapshots(old_path: &Path, new_path: &Path, format: OutputFormat) -> Result<()> {
+    let old = load_snapshot(old_path)?;
+    let new = load_snapshot(new_path)?;
+    
+    let [old_vpcs, old_subnets, old_instances, old_routes] = snapshot_index(&old);
+    let [new_vpcs, new_subnets, new_instances, new_routes] = snapshot_index(&new);
+    
+    let diff = TopologyDiff {
+        old_region: old.region,
+        new_region: new.region,
+        vpcs: ChangeSet::between(old_vpcs, new_vpcs),
+        subnets: ChangeSet::between(old_subnets, new_subnets),
+        instances: ChangeSet::between(old_instances, new_instances),
+        routes: ChangeSet::between(old_routes, new_routes),
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &diff);
+    }
+    
+    println!("{}", "🕰️  Topology Drift".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Old: {} ({})", old_path.display().to_string().yellow(), diff.old_region);
+    println!("New: {} ({})", new_path.display().to_string().yellow(), diff.new_region);
+    println!();
+    
+    let sections = [
+        ("🏢 VPCs:", &diff.vpcs),
+        ("📊 Subnets:", &diff.subnets),
+        ("💻 Instances:", &diff.instances),
+        ("🗺️  Routes:", &diff.routes),
+    ];
+    
+    let mut changed = false;
+    for (title, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        changed = true;
+        
+        println!("{}", title.yellow().bold());
+        for item in &changes.added {
+            println!("  {} {}", "+".green(), item.green());
+        }
+        for item in &changes.removed {
+            println!("  {} {}", "−".red(), item.red());
+        }
+        println!();
+    }
+    
+    if !changed {
+        println!("{}", "✅ No changes between snapshots".green().bold());
+        println!();
+    }
+    
+    println!("{}", "═".repeat(70).bright_black());
+    Ok(())
+}
+
+/// Subnets under one header per Availability Zone, with subnet and instance totals,
+/// so lopsided AZ placement stands out
+fn print_subnets_by_az(vpc: &VpcInfo, layout: &Layout) {
+    let mut zones: BTreeMap<&str, Vec<&SubnetInfo>> = BTreeMap::new();
+    for subnet in &vpc.subnets {
+        zones.entry(subnet.availability_zone.as_str()).or_default().push(subnet);
+    }
+    
+    let instances_in = |subnet_id: &str| vpc.instances.iter()
+        .filter(|i| i.subnet_id.as_deref() == Some(subnet_id))
+        .count();
+    
+    for (zone, subnets) in zones {
+        let zone_instances: usize = subnets.iter().map(|s| instances_in(&s.id)).sum();
+        layout.line(&format!("│   📍 {} - {} subnet(s), {} instance(s)", 
+            zone.yellow().bold(),
+            subnets.len(),
+            zone_instances.to_string().yellow()
+        ));
+        
+        for subnet in subnets {
+            layout.line(&format!("│      • {}{} ({}) - {} IPs available - {} instance(s) - {}{}", 
+                subnet.id.cyan(),
+                subnet.name_label(),
+                subnet.cidr.green(),
+                subnet.available_ips.to_string().yellow(),
+                instances_in(&subnet.id),
+                subnet.reachability.label(),
+                subnet.tier_label()
+            ));
+        }
+    }
+}
+
+fn print_tgw_route_table(rt: &TgwRouteTableInfo, layout: &Layout) {
+    let defaults: Vec<&str> = [
+        (rt.default_association, "default association"),
+        (rt.default_propagation, "default propagation"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, label)| *label)
+    .collect();
+    
+    layout.line(&format!("    {} Route table {} - {}{}", 
+        "📋".cyan(),
+        rt.id.cyan(),
+        rt.state.bright_black(),
+        if defaults.is_empty() { String::new() } else { format!(" ({})", defaults.join(", ")).bright_black().to_string() }
+    ));
+    
+    let attachments = |atts: &[TgwAttachmentInfo]| if atts.is_empty() {
+        "none".bright_black().to_string()
+    } else {
+        atts.iter()
+            .map(|a| format!("{} ({})", a.resource_id.green(), a.id.bright_black()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    layout.line(&format!("       Associated: {}", attachments(&rt.associations)));
+    layout.line(&format!("       Propagating: {}", attachments(&rt.propagations)));
+    
+    if rt.routes.is_empty() {
+        layout.line(&format!("       {}", "No routes".yellow()));
+    }
+    for route in &rt.routes {
+        let target = if route.state == "blackhole" {
+            "blackhole".red().bold().to_string()
+        } else {
+            route.targets.join(", ").green().to_string()
+        };
+        layout.line(&format!("       • {} → {} ({})", route.destination, target, route.route_type.bright_black()));
+    }
+}
+
+fn print_topology(topology: &Topology, by_az: bool, layout: &Layout) {
+    layout.line(&"🗺️  Mapping VPC Topology...".cyan().bold().to_string());
+    layout.line(&layout.rule().bright_black().to_string());
+    layout.line(&format!("Region: {}", topology.region.yellow()));
+    layout.line("");
+    
+    // Transit Gateways first
+    if !topology.transit_gateways.is_empty() {
+        layout.line(&"🌐 Transit Gateways:".yellow().bold().to_string());
+        for tgw in &topology.transit_gateways {
+            layout.line(&format!("  {} {} - {}", 
+                "🔗".cyan(),
+                tgw.id.cyan().bold(),
+                tgw.state.bright_black()
+            ));
+            
+            for att in &tgw.attachments {
+                layout.line(&format!("    ↳ {} → {} ({})", 
+                    att.id.bright_black(),
+                    att.resource_id.green(),
+                    att.state.yellow()
+                ));
+            }
+            
+            for rt in &tgw.route_tables {
+                print_tgw_route_table(rt, layout);
+            }
+        }
+        layout.line("");
+    }
+    
+    if topology.vpcs.is_empty() {
+        layout.line(&"No VPCs found in this region".yellow().to_string());
+        return;
+    }
+    
+    for vpc in &topology.vpcs {
+        layout.line(&layout.box_edge(true).bright_black().to_string());
+        layout.line(&format!("│ {} VPC: {} ({})", 
+            if vpc.is_default { "🏠" } else { "🏢" },
+            vpc.id.cyan().bold(),
+            cidr_blocks_label(&vpc.cidr_blocks, &vpc.cidr)
+        ));
+        
+        if !vpc.subnets.is_empty() {
+            layout.line(&format!("│ {}", "Subnets:".yellow()));
+            if by_az {
+                print_subnets_by_az(vpc, layout);
+            } else {
+                for subnet in &vpc.subnets {
+                    layout.line(&format!("│   • {}{} ({}) - {} - {} IPs available - {}{}", 
+                        subnet.id.cyan(),
+                        subnet.name_label(),
+                        subnet.cidr.green(),
+                        subnet.availability_zone.bright_black(),
+                        subnet.available_ips.to_string().yellow(),
+                        subnet.reachability.label(),
+                        subnet.tier_label()
+                    ));
+                }
+            }
+            
+            // Show routes for each route table that subnets use
+            layout.line(&format!("│ {}", "Route Tables:".yellow()));
+            for rt in &vpc.route_tables {
+                let using_subnets = vpc.subnets.iter()
+                    .filter(|s| s.route_table_id.as_deref() == Some(rt.id.as_str()))
+                    .count();
+                
+                if using_subnets > 0 {
+                    layout.line(&format!("│   📋 {} (used by {} subnet(s))", 
+                        rt.id.bright_black(),
+                        using_subnets.to_string().yellow()
+                    ));
+                    
+                    for route in &rt.routes {
+                        if route.state == "blackhole" {
+                            layout.line(&format!("│      {} {} → {} ({})", 
+                                "⚠️ ".red(),
+                                route.destination_label().red(),
+                                route.target_label().red(),
+                                "blackhole".red().bold()
+                            ));
+                            continue;
+                        }
+                        
+                        let icon = if route.target.starts_with("igw-") {
+                            "🌐"  // Internet Gateway
+                        } else if route.target.starts_with("nat-") {
+                            "🔀"  // NAT Gateway
+                        } else if route.target.starts_with("tgw-") {
+                            "🔗"  // Transit Gateway
+                        } else if route.target.starts_with("vpce-") {
+                            "🔌"  // Gateway endpoint
+                        } else if route.target == "local" {
+                            "🏠"  // Local
+                        } else {
+                            "→"
+                        };
+                        
+                        layout.line(&format!("│      {} {} → {} ({})", 
+                            icon,
+                            route.destination_label().green(),
+                            route.target_label().cyan(),
+                            route.state.bright_black()
+                        ));
+                    }
+                }
+            }
+        }
+        
+        // Counted across every table, including ones no subnet uses yet
+        let blackholes = vpc.route_tables.iter()
+            .flat_map(|rt| &rt.routes)
+            .filter(|r| r.state == "blackhole")
+            .count();
+        if blackholes > 0 {
+            layout.line(&format!("│ {} {}", 
+                "⚠️ ".red(),
+                format!("{} blackhole route(s): their target was deleted or detached, so matching traffic is dropped", blackholes).red()
+            ));
+        }
+        
+        if !vpc.instances.is_empty() {
+            layout.line(&format!("│ {}", "Instances:".yellow()));
+            for instance in &vpc.instances {
+                let state_icon = match instance.state.as_str() {
+                    "running" => "✅",
+                    "stopped" => "⏸️",
+                    "terminated" => "❌",
+                    _ => "⚠️",
+                };
+                
+                layout.line(&format!("│   {} {} ({}) - {} / {} - {} - {}{}", 
+                    state_icon,
+                    instance.name.cyan().bold(),
+                    instance.id.bright_black(),
+                    instance.private_ip.green(),
+                    instance.public_ip.as_deref().unwrap_or("—").cyan(),
+                    instance.instance_type.yellow(),
+                    instance.state.bright_black(),
+                    instance.launched_at()
+                        .map(|t| match instance.state.as_str() {
+                            "running" => format!(" - launched {} (up {})", t.format("%Y-%m-%d"), age_label(Utc::now() - t)),
+                            _ => format!(" - launched {}", t.format("%Y-%m-%d")),
+                        })
+                        .unwrap_or_default()
+                        .bright_black()
+                ));
+                
+                if !instance.exposed_services.is_empty() {
+                    layout.line(&format!("│      {} {}", 
+                        "⚠️ ".red(),
+                        format!("Public IP with {} open to the internet", instance.exposed_services.join(", ")).red()
+                    ));
+                }
+            }
+        }
+        
+        layout.line(&layout.box_edge(false).bright_black().to_string());
+        layout.line("");
+    }
+    
+    layout.line(&layout.rule().bright_black().to_string());
+}
+
+/// Coarse age for instance listings: "45m", "5h", "12d", "1y 3mo"
+fn age_label(age: chrono::Duration) -> String {
+    let minutes = age.num_minutes().max(0);
+    let days = minutes / (60 * 24);
+    match minutes {
+        0..=59 => format!("{}m", minutes),
+        60..=1439 => format!("{}h", minutes / 60),
+        _ if days < 365 => format!("{}d", days),
+        _ if days % 365 < 30 => format!("{}y", days / 365),
+        _ => format!("{}y {}mo", days / 365, days % 365 / 30),
+    }
+}
+
+/// Build describe arguments whose `--filters` combine `filters` with the global `--tag` selectors
+fn tagged(base: &[&str], filters: &[&str]) -> Vec<String> {
+    let mut args: Vec<String> = base.iter().map(|a| a.to_string()).collect();
+    let tags = awscli::tag_filters();
+    
+    if !filters.is_empty() || !tags.is_empty() {
+        args.push("--filters".to_string());
+        args.extend(filters.iter().map(|f| f.to_string()));
+        args.extend(tags);
+    }
+    
+    args
+}
+
+fn get_vpcs(region: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&tagged(&["ec2", "describe-vpcs", "--region", region], &[]))?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe VPCs"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["Vpcs"].as_array().cloned().unwrap_or_default())
+}
+
+/// VPCs already sent as `vpc_discovered` events, so watch polls and commands
+/// that list VPCs more than once report each one a single time
+static REPORTED_VPCS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Emit a `vpc_discovered` event for each VPC not reported yet this run
+fn report_vpcs<'a>(region: &str, vpcs: impl IntoIterator<Item = &'a Value>) {
+    let mut reported = REPORTED_VPCS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for vpc in vpcs {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        if reported.insert(format!("{}/{}", region, vpc_id)) {
+            events::emit("vpc_discovered", &serde_json::json!({
+                "region": region,
+                "vpc_id": vpc_id,
+                "cidr_blocks": vpc_cidr_blocks(vpc),
+                "is_default": vpc["IsDefault"],
+            }));
+        }
+    }
+}
+
+fn get_subnets(region: &str, vpc_id: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&tagged(
+            &["ec2", "describe-subnets", "--region", region],
+            &[&format!("Name=vpc-id,Values={}", vpc_id)]
+        ))?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["Subnets"].as_array().unwrap_or(&vec![]).clone())
+}
+
+fn get_instances(region: &str, vpc_id: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&tagged(
+            &["ec2", "describe-instances", "--region", region],
+            &[&format!("Name=vpc-id,Values={}", vpc_id)]
+        ))?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    
+    let mut instances = vec![];
+    if let Some(reservations) = json["Reservations"].as_array() {
+        for reservation in reservations {
+            if let Some(insts) = reservation["Instances"].as_array() {
+                instances.extend(insts.clone());
+            }
+        }
+    }
+    
+    Ok(instances)
+}
+
+fn get_transit_gateways(region: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&["ec2", "describe-transit-gateways", "--region", region])?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["TransitGateways"].as_array().unwrap_or(&vec![]).clone())
+}
+
+fn get_tgw_attachments(region: &str, tgw_id: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&[
+            "ec2", "describe-transit-gateway-attachments",
+            "--region", region,
+            "--filters", &format!("Name=transit-gateway-id,Values={}", tgw_id)
+        ])?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["TransitGatewayAttachments"].as_array().unwrap_or(&vec![]).clone())
+}
+
+/// Active connections only; pending, rejected, and deleted ones carry no traffic
+fn get_vpc_peering_connections(region: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&["ec2", "describe-vpc-peering-connections", "--region", region])?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["VpcPeeringConnections"].as_array()
+        .map(|pcxs| pcxs.iter().filter(|p| p["Status"]["Code"].as_str() == Some("active")).cloned().collect())
+        .unwrap_or_default())
+}
+
+fn get_tgw_route_tables(region: &str, tgw_id: &str) -> Result<Vec<Value>> {
+    let output = awscli::run(&[
+            "ec2", "describe-transit-gateway-route-tables",
+            "--region", region,
+            "--filters", &format!("Name=transit-gateway-id,Values={}", tgw_id)
+        ])?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["TransitGatewayRouteTables"].as_array().unwrap_or(&vec![]).clone())
+}
+
+/// One of the per-route-table TGW calls, returning the array under `key`
+fn tgw_route_table_query(region: &str, operation: &str, rt_id: &str, key: &str) -> Result<Vec<Value>> {
+    let mut args = vec!["ec2", operation, "--region", region, "--transit-gateway-route-table-id", rt_id];
+    // Route search requires a filter; these two cover every route in the table
+    if operation == "search-transit-gateway-routes" {
+        args.extend(["--filters", "Name=type,Values=static,propagated"]);
+    }
+    
+    // Details are supplementary: a table whose lookups are refused is still
+    // listed, just without them
+    let output = awscli::run(&args)?;
+    if !output.success {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("AccessDenied") || stderr.contains("UnauthorizedOperation") {
+            tracing::warn!("Access denied for {} on {}, listing it without those details", operation, rt_id);
+        } else {
+            tracing::warn!("{} failed for {}: {}", operation, rt_id, stderr.trim());
+        }
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json[key].as_array().unwrap_or(&vec![]).clone())
+}
+
+fn get_route_tables(region: &str, vpc_filter: Option<&str>) -> Result<Vec<Value>> {
+    let mut args = vec!["ec2".to_string(), "describe-route-tables".to_string(), "--region".to_string(), region.to_string()];
+    if let Some(vpc_id) = vpc_filter {
+        args.push("--filters".to_string());
+        args.push(format!("Name=vpc-id,Values={}", vpc_id));
+    }
+    
+    let output = awscli::run(&args)?;
+    
+    if !output.success {
+        return Ok(vec![]);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["RouteTables"].as_array().unwrap_or(&vec![]).clone())
+}
+
+pub fn export_dot(region: &str) -> Result<()> {
+    println!("{}", "📊 Exporting to DOT format...".cyan().bold());
+    
+    let dot = render_dot(&gather_topology(region)?);
+    
+    let path = artifact::path("aws-map", region, "dot")?;
+    let mut file = File::create(&path)?;
+    file.write_all(dot.as_bytes())?;
+    
+    println!("{} Exported to: {}", "✅".green(), path.display().to_string().cyan().bold());
+    println!("View at: {}", "https://dreampuf.github.io/GraphvizOnline/".yellow());
+    
+    Ok(())
+}
+
+/// The region's VPCs, subnets, instances, and gateways as a Graphviz digraph
+fn render_dot(topology: &Topology) -> String {
+    let mut dot = String::from("digraph AWS {\n");
+    dot.push_str("  rankdir=LR;\n");
+    dot.push_str("  node [shape=box, style=rounded];\n\n");
+    
+    for vpc in &topology.vpcs {
+        let vpc_id = &vpc.id;
+        let cidrs: Vec<&str> = vpc.cidr_blocks.iter()
+            .filter(|block| block.in_use())
+            .map(|block| block.cidr.as_str())
+            .collect();
+        
+        // VPC node
+        dot.push_str(&format!("  \"{}\" [label=\"VPC\\n{}\", color=blue, penwidth=2];\n", 
+            vpc_id, cidrs.join("\\n")));
+        
+        // Subnets
+        for subnet in &vpc.subnets {
+            dot.push_str(&format!("  \"{}\" [label=\"Subnet\\n{}\", color=green];\n", 
+                subnet.id, subnet.cidr));
+            dot.push_str(&format!("  \"{}\" -> \"{}\";\n", vpc_id, subnet.id));
+        }
+        
+        // Instances
+        for instance in &vpc.instances {
+            let color = if instance.state == "running" { "green" } else { "red" };
+            
+            dot.push_str(&format!("  \"{}\" [label=\"{}\\n{}\\n{}\", color={}, shape=ellipse];\n", 
+                instance.id, instance.name, instance.id, instance.private_ip, color));
+            
+            // Connect to subnet
+            if let Some(subnet_id) = &instance.subnet_id {
+                dot.push_str(&format!("  \"{}\" -> \"{}\";\n", subnet_id, instance.id));
+            }
+        }
+        
+        // Route tables
+        for route in vpc.route_tables.iter().flat_map(|rt| &rt.routes) {
+            let (label, color) = if route.target.starts_with("tgw-") {
+                ("TGW", "purple")
+            } else if route.target.starts_with("igw-") {
+                ("IGW", "orange")
+            } else {
+                continue;
+            };
+            dot.push_str(&format!("  \"{}\" [label=\"{}\\n{}\", color={}, shape=diamond];\n", 
+                route.target, label, route.target, color));
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"{}\"];\n", 
+                vpc_id, route.target, route.destination));
+        }
+        
+        dot.push('\n');
+    }
+    
+    dot.push_str("}\n");
+    dot
+}
+
+/// Write a self-contained HTML page: the DOT diagram rendered to an embedded
+/// PNG by Graphviz, then a text summary of each VPC. Without a working `dot`
+/// the page carries the DOT source instead, so it is still worth sharing.
+pub fn export_report(region: &str, path: &Path, filter: InstanceFilter) -> Result<()> {
+    println!("{}", "📊 Building topology report...".cyan().bold());
+    
+    let mut topology = gather_topology(region)?;
+    filter.apply(&mut topology);
+    // Drawn from the same filtered topology as the summary below
+    let dot = render_dot(&topology);
+    
+    let diagram = match render_png(&dot) {
+        Ok(png) => format!(
+            "<img alt=\"VPC topology diagram\" src=\"data:image/png;base64,{}\">\n<details><summary>DOT source</summary><pre>{}</pre></details>\n",
+            output::base64(&png), output::html_escape(&dot)),
+        Err(e) => {
+            tracing::warn!("Could not render the diagram ({}); the report includes the DOT source instead", e);
+            format!(
+                "<p class=\"note\">Graphviz was not available, so the diagram is included as DOT source. Paste it into https://dreampuf.github.io/GraphvizOnline/ to view it.</p>\n<pre>{}</pre>\n",
+                output::html_escape(&dot))
+        }
+    };
+    
+    let html = report_html(&topology, &diagram);
+    std::fs::write(path, html)
+        .map_err(|e| anyhow::anyhow!("Cannot write report {}: {}", path.display(), e))?;
+    
+    println!("{} Report written to: {}", "✅".green(), path.display().to_string().cyan().bold());
+    Ok(())
+}
+
+/// `dot -Tpng` with the graph on stdin
+fn render_png(dot: &str) -> Result<Vec<u8>> {
+    use std::process::{Command, Stdio};
+    
+    let mut child = Command::new("dot")
+        .arg("-Tpng")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("cannot run dot: {}", e))?;
+    
+    // Dropping stdin once written closes it, which tells dot the graph is complete
+    child.stdin.take()
+        .ok_or_else(|| anyhow::anyhow!("cannot write to dot"))?
+        .write_all(dot.as_bytes())?;
+    let output = child.wait_with_output()?;
+    
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(anyhow::anyhow!("dot failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+fn report_html(topology: &Topology, diagram: &str) -> String {
+    let esc = output::html_escape;
+    let mut html = String::new();
+    
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>VPC topology: {}</title>\n", esc(&topology.region)));
+    html.push_str("<style>\n\
+        body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+        img { max-width: 100%; border: 1px solid #ddd; }\n\
+        table { border-collapse: collapse; margin: 0.5em 0 1.5em; }\n\
+        th, td { border: 1px solid #ddd; padding: 0.3em 0.7em; text-align: left; }\n\
+        th { background: #f4f4f4; }\n\
+        td.num { text-align: right; }\n\
+        pre { background: #f8f8f8; padding: 1em; overflow-x: auto; }\n\
+        .note { color: #777; }\n\
+        </style>\n</head>\n<body>\n");
+    html.push_str(&format!("<h1>VPC topology: {}</h1>\n", esc(&topology.region)));
+    html.push_str(&format!("<p class=\"note\">Generated {}</p>\n", Utc::now().format("%Y-%m-%d %H:%M UTC")));
+    
+    html.push_str("<h2>Diagram</h2>\n");
+    html.push_str(diagram);
+    
+    if !topology.transit_gateways.is_empty() {
+        html.push_str("<h2>Transit Gateways</h2>\n");
+        html.push_str("<table>\n<tr><th>ID</th><th>State</th><th>Attachments</th></tr>\n");
+        for tgw in &topology.transit_gateways {
+            let attachments: Vec<String> = tgw.attachments.iter()
+                .map(|att| format!("{} ({})", att.resource_id, att.state))
+                .collect();
+            html.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                esc(&tgw.id), esc(&tgw.state), esc(&attachments.join(", "))));
+        }
+        html.push_str("</table>\n");
+    }
+    
+    for vpc in &topology.vpcs {
+        let cidrs: Vec<&str> = if vpc.cidr_blocks.is_empty() {
+            vec![vpc.cidr.as_str()]
+        } else {
+            vpc.cidr_blocks.iter().map(|block| block.cidr.as_str()).collect()
+        };
+        let default = if vpc.is_default { " (default)" } else { "" };
+        html.push_str(&format!("<h2>{}{}</h2>\n", esc(&vpc.id), default));
+        html.push_str(&format!("<p>CIDR blocks: {}. {} subnet(s), {} route table(s), {} instance(s).</p>\n",
+            esc(&cidrs.join(", ")), vpc.subnets.len(), vpc.route_tables.len(), vpc.instances.len()));
+        
+        if !vpc.subnets.is_empty() {
+            html.push_str("<table>\n<tr><th>Subnet</th><th>Name</th><th>CIDR</th><th>AZ</th><th>Reachability</th><th>Free IPs</th></tr>\n");
+            for subnet in &vpc.subnets {
+                html.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>\n",
+                    esc(&subnet.id), esc(subnet.name.as_deref().unwrap_or("")), esc(&subnet.cidr),
+                    esc(&subnet.availability_zone), subnet.reachability.as_str(), subnet.available_ips));
+            }
+            html.push_str("</table>\n");
+        }
+        
+        if !vpc.instances.is_empty() {
+            html.push_str("<table>\n<tr><th>Instance</th><th>Name</th><th>State</th><th>Type</th><th>Private IP</th><th>Public IP</th><th>Subnet</th></tr>\n");
+            for instance in &vpc.instances {
+                html.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    esc(&instance.id), esc(&instance.name), esc(&instance.state), esc(&instance.instance_type),
+                    esc(&instance.private_ip), esc(instance.public_ip.as_deref().unwrap_or("")),
+                    esc(instance.subnet_id.as_deref().unwrap_or(""))));
+            }
+            html.push_str("</table>\n");
+        }
+    }
+    
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Whether a `--vpc` filter (one ID or a comma-separated list) keeps this VPC
+fn vpc_selected(vpc_filter: Option<&str>, vpc_id: &str) -> bool {
+    vpc_filter.is_none_or(|filter| filter.split(',').any(|id| id == vpc_id))
+}
+
+fn get_security_groups(region: &str, vpc_filter: Option<&str>) -> Result<Vec<Value>> {
+    let vpc_arg = vpc_filter.map(|vpc| format!("Name=vpc-id,Values={}", vpc));
+    let filters: Vec<&str> = vpc_arg.as_deref().into_iter().collect();
+    
+    let output = awscli::run(&tagged(&["ec2", "describe-security-groups", "--region", region], &filters))?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe security groups"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["SecurityGroups"].as_array().unwrap_or(&vec![]).clone())
+}
+
+#[derive(Debug, Serialize)]
+struct SecurityGroupInfo {
+    group_id: String,
+    group_name: String,
+    vpc_id: String,
+    /// Rule counts as AWS applies them to the per-group quota
+    ingress_rule_count: RuleCount,
+    egress_rule_count: RuleCount,
+    ingress: Vec<SgRuleInfo>,
+    egress: Vec<SgRuleInfo>,
+    /// Referenced groups that no longer exist or protect nothing, making those rules dead
+    stale_references: Vec<StaleReference>,
+    /// Ingress rules that can be deleted without changing what the group allows
+    redundant_rules: Vec<RedundantRule>,
+    /// Compliance findings against the group, with `--sort-by-risk`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    risk: Option<SgRisk>,
+}
+
+/// A group's exposure: its compliance findings by severity, weighted like the
+/// posture score, so 0.0.0.0/0 on a risky port dominates
+#[derive(Debug, Serialize)]
+struct SgRisk {
+    score: u32,
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+}
+
+impl SgRisk {
+    fn assess(sg: &Value, rules: &Ruleset) -> Self {
+        let mut issues = Vec::new();
+        check_security_group(&mut issues, sg, rules);
+        
+        let count = |severity| issues.iter().filter(|i| i.severity == severity).count();
+        let (critical, high, medium, low) = (count("CRITICAL"), count("HIGH"), count("MEDIUM"), count("LOW"));
+        SgRisk { score: rules.score_weights.penalty(critical, high, medium, low), critical, high, medium, low }
+    }
+    
+    /// "risk 30 (1 critical, 1 high)", colored by the worst finding
+    fn label(&self) -> ColoredString {
+        let counts: Vec<String> = [(self.critical, "critical"), (self.high, "high"), (self.medium, "medium"), (self.low, "low")]
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, severity)| format!("{} {}", count, severity))
+            .collect();
+        let label = if counts.is_empty() {
+            format!("risk {}", self.score)
+        } else {
+            format!("risk {} ({})", self.score, counts.join(", "))
+        };
+        
+        if self.critical > 0 {
+            label.red().bold()
+        } else if self.high > 0 {
+            label.yellow().bold()
+        } else if self.medium > 0 {
+            label.bright_yellow()
+        } else {
+            label.green()
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RedundantRule {
+    rule: String,
+    /// The rule that already allows everything `rule` does
+    covered_by: String,
+    /// "duplicate" or "shadowed"
+    reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct StaleReference {
+    group_id: String,
+    reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct SgRuleInfo {
+    protocol: String,
+    ports: String,
+    /// CIDR block or security group ID on the other side of the rule
+    peer: String,
+    peer_type: &'static str,
+    /// Name of the referenced security group, when it could be resolved
+    peer_name: Option<String>,
+    /// Owning account of a referenced group that lives in another account
+    peer_account: Option<String>,
+    /// VPC of a referenced group outside this group's VPC
+    peer_vpc: Option<String>,
+    /// Peering connection a cross-VPC reference relies on
+    peering_connection: Option<String>,
+    description: Option<String>,
+    /// Numeric port range; `None` when the rule covers every port of its protocol
+    #[serde(skip)]
+    port_range: Option<(i64, i64)>,
+}
+
+impl SgRuleInfo {
+    /// "tcp :22 from 10.0.0.0/16", for pointing at one rule in a finding
+    fn label(&self, direction: &str) -> String {
+        let peer = if self.peer_type == "security-group" { self.sg_peer_label() } else { self.peer.clone() };
+        format!("{} :{} {} {}", self.protocol, self.ports, direction, peer)
+    }
+    
+    /// True when `self` allows at least everything `other` does
+    fn covers(&self, other: &SgRuleInfo) -> bool {
+        let protocol = self.protocol == "ALL" || self.protocol == other.protocol;
+        let ports = match (self.port_range, other.port_range) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some((from, to)), Some((other_from, other_to))) => from <= other_from && to >= other_to,
+        };
+        let peer = match (self.peer_type, other.peer_type) {
+            ("cidr", "cidr") => match (cidr::parse_cidr(&self.peer), cidr::parse_cidr(&other.peer)) {
+                (Ok((base, prefix)), Ok((other_base, other_prefix))) => {
+                    let (outer, inner) = (cidr::range(base, prefix), cidr::range(other_base, other_prefix));
+                    outer.0 <= inner.0 && outer.1 >= inner.1
+                }
+                _ => self.peer == other.peer,
+            },
+            ("security-group", "security-group") => self.peer == other.peer && self.peer_account == other.peer_account,
+            _ => false,
+        };
+        
+        protocol && ports && peer
+    }
+    
+    /// "SG my-web-sg (sg-0123)", plus the account for cross-account references
+    /// and the VPC and peering connection for cross-VPC ones
+    fn sg_peer_label(&self) -> String {
+        let mut label = match &self.peer_name {
+            Some(name) => format!("SG {} ({})", name, self.peer),
+            None => format!("SG {}", self.peer),
+        };
+        if let Some(account) = &self.peer_account {
+            label.push_str(&format!(" [account {}]", account));
+        }
+        match (&self.peer_vpc, &self.peering_connection) {
+            (Some(vpc), Some(pcx)) => label.push_str(&format!(" [{} via {}]", vpc, pcx)),
+            (Some(vpc), None) => label.push_str(&format!(" [{}]", vpc)),
+            (None, Some(pcx)) => label.push_str(&format!(" [via {}]", pcx)),
+            (None, None) => {}
+        }
+        label
+    }
+    
+    fn is_cross_vpc(&self) -> bool {
+        self.peer_vpc.is_some() || self.peering_connection.is_some()
+    }
+}
+
+/// Exact duplicates and rules fully inside a broader rule of the same group.
+/// Each redundant rule is reported once, against the broadest rule covering it.
+fn find_redundant_rules(rules: &[SgRuleInfo]) -> Vec<RedundantRule> {
+    let mut redundant = Vec::new();
+    
+    for (i, rule) in rules.iter().enumerate() {
+        let broader = rules.iter().enumerate()
+            .filter(|(j, other)| *j != i && other.covers(rule) && !rule.covers(other))
+            .find(|(_, other)| !rules.iter().any(|wider| wider.covers(other) && !other.covers(wider)));
+        let duplicate = rules[..i].iter().find(|other| other.covers(rule) && rule.covers(other));
+        
+        let (covered_by, reason) = match (broader, duplicate) {
+            (Some((_, other)), _) => (other, "shadowed"),
+            (None, Some(other)) => (other, "duplicate"),
+            (None, None) => continue,
+        };
+        redundant.push(RedundantRule {
+            rule: rule.label("from"),
+            covered_by: covered_by.label("from"),
+            reason,
+        });
+    }
+    
+    redundant
+}
+
+fn parse_sg_rules(rules: &Value, owner_id: Option<&str>, owner_vpc: Option<&str>) -> Vec<SgRuleInfo> {
+    let mut parsed = Vec::new();
+    let Some(rules) = rules.as_array() else { return parsed };
+    
+    for rule in rules {
+        let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
+        let from_port = rule["FromPort"].as_i64();
+        let to_port = rule["ToPort"].as_i64();
+        
+        let ports = match (from_port, to_port) {
+            (Some(f), Some(t)) if f == t => f.to_string(),
+            (Some(f), Some(t)) => format!("{}−{}", f, t),
+            _ => String::from("ALL"),
+        };
+        
+        let proto = if protocol == "-1" { "ALL" } else { protocol };
+        // ICMP uses -1 for "every type"
+        let port_range = match (from_port, to_port) {
+            (Some(f), Some(t)) if f >= 0 && protocol != "-1" => Some((f, t)),
+            _ => None,
+        };
+        
+        // IP ranges
+        if let Some(ip_ranges) = rule["IpRanges"].as_array() {
+            for ip_range in ip_ranges {
+                parsed.push(SgRuleInfo {
+                    protocol: proto.to_string(),
+                    ports: ports.clone(),
+                    peer: ip_range["CidrIp"].as_str().unwrap_or("unknown").to_string(),
+                    peer_type: "cidr",
+                    peer_name: None,
+                    peer_account: None,
+                    peer_vpc: None,
+                    peering_connection: None,
+                    description: ip_range["Description"].as_str().map(String::from),
+                    port_range,
+                });
+            }
+        }
+        
+        // Security group sources
+        if let Some(sg_pairs) = rule["UserIdGroupPairs"].as_array() {
+            for pair in sg_pairs {
+                parsed.push(SgRuleInfo {
+                    protocol: proto.to_string(),
+                    ports: ports.clone(),
+                    peer: pair["GroupId"].as_str().unwrap_or("unknown").to_string(),
+                    peer_type: "security-group",
+                    peer_name: pair["GroupName"].as_str().map(String::from),
+                    peer_account: pair["UserId"].as_str()
+                        .filter(|account| owner_id.is_some_and(|owner| owner != *account))
+                        .map(String::from),
+                    peer_vpc: pair["VpcId"].as_str()
+                        .filter(|vpc| owner_vpc != Some(*vpc))
+                        .map(String::from),
+                    peering_connection: pair["VpcPeeringConnectionId"].as_str().map(String::from),
+                    description: pair["Description"].as_str().map(String::from),
+                    port_range,
+                });
+            }
+        }
+    }
+    
+    parsed
+}
+
+/// Percentage of the rules-per-group quota at which a group gets a warning
+const SG_QUOTA_WARN_PERCENT: usize = 80;
+
+/// One direction's rules, counted the way the rules-per-security-group quota
+/// does: separately for IPv4 and IPv6, one per CIDR, with prefix list and
+/// group references counting toward both
+#[derive(Debug, Clone, Copy, Serialize)]
+struct RuleCount {
+    ipv4: usize,
+    ipv6: usize,
+}
+
+impl RuleCount {
+    fn of(rules: &Value) -> Self {
+        let entries = |key: &str| rules.as_array()
+            .map_or(0, |rules| rules.iter().map(|rule| rule[key].as_array().map_or(0, Vec::len)).sum());
+        let shared = entries("PrefixListIds") + entries("UserIdGroupPairs");
+        RuleCount { ipv4: entries("IpRanges") + shared, ipv6: entries("Ipv6Ranges") + shared }
+    }
+    
+    /// The family closer to the quota, which is the one that fails first
+    fn busiest(self) -> usize {
+        self.ipv4.max(self.ipv6)
+    }
+}
+
+/// One CIDR, prefix list, or group reference per entry, for plain rule counts
+fn rule_entry_count(rules: &Value) -> usize {
+    let Some(rules) = rules.as_array() else { return 0 };
+    
+    rules.iter()
+        .map(|rule| ["IpRanges", "Ipv6Ranges", "PrefixListIds", "UserIdGroupPairs"].iter()
+            .map(|key| rule[*key].as_array().map_or(0, |entries| entries.len()))
+            .sum::<usize>())
+        .sum()
+}
+
+/// "12/60" colored by how close a rule count is to the quota, spelled out
+/// per family ("12/60 IPv4, 3/60 IPv6") when the two differ
+fn quota_usage(count: RuleCount, quota: usize) -> String {
+    let colored = |count: usize| {
+        let usage = format!("{}/{}", count, quota);
+        if count >= quota {
+            usage.red().bold()
+        } else if count * 100 >= quota * SG_QUOTA_WARN_PERCENT {
+            usage.yellow().bold()
+        } else {
+            usage.green()
+        }
+    };
+    
+    if count.ipv4 != count.ipv6 {
+        format!("{} IPv4, {} IPv6", colored(count.ipv4), colored(count.ipv6))
+    } else {
+        colored(count.ipv4).to_string()
+    }
+}
+
+/// With `sort_by_risk`, each group is scored against that ruleset and the
+/// riskiest are listed first
+#[allow(clippy::too_many_arguments)]
+pub fn analyze_security_groups(
+    region: &str,
+    vpc_filter: Option<&str>,
+    rule_quota: usize,
+    ingress_only: bool,
+    egress_only: bool,
+    summary: bool,
+    sort_by_risk: Option<&Ruleset>,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut sgs: Vec<SecurityGroupInfo> = get_security_groups(region, vpc_filter)?
+        .iter()
+        .map(|sg| SecurityGroupInfo {
+            group_id: sg["GroupId"].as_str().unwrap_or("unknown").to_string(),
+            group_name: sg["GroupName"].as_str().unwrap_or("unnamed").to_string(),
+            vpc_id: sg["VpcId"].as_str().unwrap_or("N/A").to_string(),
+            ingress_rule_count: RuleCount::of(&sg["IpPermissions"]),
+            egress_rule_count: RuleCount::of(&sg["IpPermissionsEgress"]),
+            ingress: parse_sg_rules(&sg["IpPermissions"], sg["OwnerId"].as_str(), sg["VpcId"].as_str()),
+            egress: parse_sg_rules(&sg["IpPermissionsEgress"], sg["OwnerId"].as_str(), sg["VpcId"].as_str()),
+            stale_references: Vec::new(),
+            redundant_rules: Vec::new(),
+            risk: sort_by_risk.map(|rules| SgRisk::assess(sg, rules)),
+        })
+        .collect();
+    
+    // Stable, so equally risky groups keep the API order
+    if sort_by_risk.is_some() {
+        sgs.sort_by_key(|sg| std::cmp::Reverse(sg.risk.as_ref().map_or(0, |risk| risk.score)));
+    }
+    
+    // Resolve group references against the groups we already fetched
+    let names: BTreeMap<String, String> = sgs.iter()
+        .map(|sg| (sg.group_id.clone(), sg.group_name.clone()))
+        .collect();
+    for rule in sgs.iter_mut().flat_map(|sg| sg.ingress.iter_mut().chain(sg.egress.iter_mut())) {
+        if rule.peer_type == "security-group" && rule.peer_name.is_none() {
+            rule.peer_name = names.get(&rule.peer).cloned();
+        }
+    }
+    
+    // Groups attached to at least one ENI; skipped if the ENI listing is unavailable
+    let attached: Option<HashSet<String>> = get_network_interfaces(region, vpc_filter, None).ok()
+        .map(|enis| enis.iter()
+            .filter_map(|eni| eni["Groups"].as_array())
+            .flatten()
+            .filter_map(|group| group["GroupId"].as_str().map(String::from))
+            .collect());
+    
+    // A --tag filter hides untagged groups, so absence from the list proves nothing
+    let complete_listing = awscli::tag_filters().is_empty();
+    
+    for sg in &mut sgs {
+        let mut stale: Vec<StaleReference> = Vec::new();
+        for rule in sg.ingress.iter().chain(sg.egress.iter()) {
+            if rule.peer_type != "security-group" || rule.peer_account.is_some() {
+                continue;
+            }
+            if stale.iter().any(|s| s.group_id == rule.peer) {
+                continue;
+            }
+            
+            let reason = if !names.contains_key(&rule.peer) {
+                // Groups in a peered VPC are outside this listing (or region)
+                if !complete_listing || rule.is_cross_vpc() {
+                    continue;
+                }
+                "group not found"
+            } else if attached.as_ref().is_some_and(|attached| !attached.contains(&rule.peer)) {
+                "group is attached to nothing"
+            } else {
+                continue;
+            };
+            stale.push(StaleReference { group_id: rule.peer.clone(), reason });
+        }
+        sg.stale_references = stale;
+        sg.redundant_rules = find_redundant_rules(&sg.ingress);
+    }
+    
+    for sg in &mut sgs {
+        if ingress_only {
+            sg.egress.clear();
+        }
+        if egress_only {
+            sg.ingress.clear();
+            sg.redundant_rules.clear();
+        }
+    }
+    
+    if format.is_structured() {
+        return output::print(format, &sgs);
+    }
+    
+    println!("{}", "🔒 Analyzing Security Groups...".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", region.yellow());
+    if let Some(vpc) = vpc_filter {
+        println!("VPC Filter: {}", vpc.yellow());
+    }
+    println!();
+    
+    if summary && !sgs.is_empty() {
+        let mut headers = vec!["GROUP", "GROUP ID", "VPC", "INGRESS", "EGRESS"];
+        if sort_by_risk.is_some() {
+            headers.push("RISK");
+        }
+        let mut table = Table::new(&headers).right_align(3).right_align(4);
+        for sg in &sgs {
+            let mut row = vec![
+                sg.group_name.cyan().bold().to_string(),
+                sg.group_id.bright_black().to_string(),
+                sg.vpc_id.green().to_string(),
+                quota_usage(sg.ingress_rule_count, rule_quota),
+                quota_usage(sg.egress_rule_count, rule_quota),
+            ];
+            row.extend(sg.risk.as_ref().map(|risk| risk.label().to_string()));
+            table.row(row);
+        }
+        table.print();
+    }
+    
+    for sg in sgs.iter().filter(|_| !summary) {
+        let risk = sg.risk.as_ref().map(|risk| format!(" - {}", risk.label())).unwrap_or_default();
+        
+        println!("{}", "┌─────────────────────────────────────────────────────────────────┐".bright_black());
+        println!("│ 🛡️  {} ({}){}", sg.group_name.cyan().bold(), sg.group_id.bright_black(), risk);
+        println!("│ VPC: {} - Rules: {} in, {} out", 
+            sg.vpc_id.green(),
+            quota_usage(sg.ingress_rule_count, rule_quota),
+            quota_usage(sg.egress_rule_count, rule_quota)
+        );
+        
+        let busiest = sg.ingress_rule_count.busiest().max(sg.egress_rule_count.busiest());
+        if busiest * 100 >= rule_quota * SG_QUOTA_WARN_PERCENT {
+            println!("│ {} {}", "⚠️ ".yellow(), format!("Approaching the {}-rule quota; new rules may fail to apply", rule_quota).yellow());
+        }
+        
+        // Ingress rules
+        if !egress_only && !sg.ingress.is_empty() {
+            println!("│");
+            println!("│ {} Inbound Rules:", "⬇️".green());
+            
+            for rule in &sg.ingress {
+                if rule.peer_type == "security-group" {
+                    let peer = rule.sg_peer_label();
+                    println!("│   • {} {} from {}", 
+                        rule.protocol.yellow(),
+                        format!(":{}", rule.ports).cyan(),
+                        if rule.is_cross_vpc() { peer.magenta().bold() } else { peer.green() }
+                    );
+                } else {
+                    let desc = rule.description.as_deref().unwrap_or("");
+                    println!("│   • {} {} from {} {}", 
+                        rule.protocol.yellow(),
+                        format!(":{}", rule.ports).cyan(),
+                        rule.peer.green(),
+                        if !desc.is_empty() { format!("({})", desc) } else { String::new() }.bright_black()
+                    );
+                }
+            }
+        }
+        
+        // Egress rules
+        if !ingress_only && !sg.egress.is_empty() {
+            println!("│");
+            println!("│ {} Outbound Rules:", "⬆️".red());
+            
+            for rule in &sg.egress {
+                let peer = if rule.peer_type == "security-group" {
+                    rule.sg_peer_label()
+                } else {
+                    rule.peer.clone()
+                };
+                println!("│   • {} {} to {}", 
+                    rule.protocol.yellow(),
+                    format!(":{}", rule.ports).cyan(),
+                    peer.green()
+                );
+            }
+        }
+        
+        if !sg.stale_references.is_empty() {
+            println!("│");
+            println!("│ {} Stale references:", "⚠️ ".yellow());
+            for stale in &sg.stale_references {
+                println!("│   • {} - {}", stale.group_id.yellow(), stale.reason.bright_black());
+            }
+        }
+        
+        if !sg.redundant_rules.is_empty() {
+            println!("│");
+            println!("│ {} Cleanup suggestions:", "🧹".cyan());
+            for redundant in &sg.redundant_rules {
+                let relation = if redundant.reason == "duplicate" { "duplicate of" } else { "shadowed by" };
+                println!("│   • {} - {} {}", 
+                    redundant.rule.yellow(),
+                    relation,
+                    redundant.covered_by.bright_black()
+                );
+            }
+        }
+        
+        println!("{}", "└─────────────────────────────────────────────────────────────────┘".bright_black());
+        println!();
+    }
+    
+    if summary && !sgs.is_empty() {
+        println!();
+    }
+    
+    let near_quota = sgs.iter()
+        .filter(|sg| sg.ingress_rule_count.busiest().max(sg.egress_rule_count.busiest()) * 100 >= rule_quota * SG_QUOTA_WARN_PERCENT)
+        .count();
+    
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Total: {} security group(s)", sgs.len().to_string().green().bold());
+    let cross_vpc = sgs.iter()
+        .flat_map(|sg| sg.ingress.iter().chain(sg.egress.iter()))
+        .filter(|rule| rule.is_cross_vpc())
+        .count();
+    if cross_vpc > 0 {
+        println!("{} rule(s) reference groups in other VPCs over peering", cross_vpc.to_string().magenta().bold());
+    }
+    let redundant: usize = sgs.iter().map(|sg| sg.redundant_rules.len()).sum();
+    if redundant > 0 {
+        println!("{} redundant ingress rule(s) can be removed", redundant.to_string().yellow().bold());
+    }
+    if near_quota > 0 {
+        println!("{} group(s) at or above {}% of the {}-rule quota", 
+            near_quota.to_string().yellow().bold(),
+            SG_QUOTA_WARN_PERCENT,
+            rule_quota
+        );
+    }
+    
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct VpcDiffSide {
+    vpc_id: String,
+    region: String,
+    cidr: String,
+    cidr_blocks: Vec<CidrBlockInfo>,
+    subnets: usize,
+    instances_total: usize,
+    instances_running: usize,
+    instance_types: Vec<String>,
+    route_tables: usize,
+    /// Instances per availability zone, including AZs with subnets but no instances
+    instances_by_az: BTreeMap<String, usize>,
+    /// Instances per subnet ID, including empty subnets
+    instances_by_subnet: BTreeMap<String, usize>,
+    /// "single-az", "skewed", "balanced", or "n/a" with fewer than two instances
+    az_spread: &'static str,
+}
+
+/// Share of instances in the busiest AZ above which a multi-AZ spread is "skewed"
+const AZ_SKEW_PERCENT: usize = 67;
+
+/// How evenly instances are spread over the AZs that have subnets
+fn az_spread(by_az: &BTreeMap<String, usize>) -> &'static str {
+    let total: usize = by_az.values().sum();
+    let busiest = by_az.values().copied().max().unwrap_or(0);
+    if total < 2 {
+        "n/a"
+    } else if busiest == total {
+        "single-az"
+    } else if busiest * 100 > total * AZ_SKEW_PERCENT {
+        "skewed"
+    } else {
+        "balanced"
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct VpcDiff {
+    vpc1: VpcDiffSide,
+    vpc2: VpcDiffSide,
+    /// Set when one VPC concentrates its instances in one AZ and the other spreads them
+    ha_mismatch: Option<String>,
+    subnets_only_in_vpc1: Vec<String>,
+    subnets_only_in_vpc2: Vec<String>,
+    routes_only_in_vpc1: Vec<String>,
+    routes_only_in_vpc2: Vec<String>,
+    common_routes: Vec<String>,
+}
+
+fn sorted(items: Vec<&String>) -> Vec<String> {
+    let mut items: Vec<String> = items.into_iter().cloned().collect();
+    items.sort();
+    items
+}
+
+fn route_destinations(route_tables: &[Value]) -> HashSet<String> {
+    let mut dests = HashSet::new();
+    
+    for rt in route_tables {
+        if let Some(routes) = rt["Routes"].as_array() {
+            for route in routes {
+                if let Some(dest) = route["DestinationCidrBlock"].as_str() {
+                    if dest != "local" {
+                        dests.insert(dest.to_string());
+                    }
+                }
+            }
+        }
+    }
+    
+    dests
+}
+
+pub fn diff_vpcs(region1: &str, region2: &str, vpc1_id: &str, vpc2_id: &str, format: OutputFormat) -> Result<()> {
+    // Get VPC info, each from its own region
+    let vpcs1 = get_vpcs(region1)?;
+    let vpc1 = vpcs1.iter().find(|v| v["VpcId"].as_str() == Some(vpc1_id))
+        .ok_or_else(|| anyhow::anyhow!("VPC {} not found in {}", vpc1_id, region1))?;
+    report_vpcs(region1, [vpc1]);
+    
+    let vpcs2 = if region2 == region1 { vpcs1.clone() } else { get_vpcs(region2)? };
+    let vpc2 = vpcs2.iter().find(|v| v["VpcId"].as_str() == Some(vpc2_id))
+        .ok_or_else(|| anyhow::anyhow!("VPC {} not found in {}", vpc2_id, region2))?;
+    report_vpcs(region2, [vpc2]);
+    
+    let subnets1 = get_subnets(region1, vpc1_id)?;
+    let subnets2 = get_subnets(region2, vpc2_id)?;
+    let instances1 = get_instances(region1, vpc1_id)?;
+    let instances2 = get_instances(region2, vpc2_id)?;
+    let rts1 = get_route_tables(region1, Some(vpc1_id))?;
+    let rts2 = get_route_tables(region2, Some(vpc2_id))?;
+    
+    let side = |vpc: &Value, region: &str, subnets: &[Value], instances: &[Value], rts: &[Value]| {
+        let types: HashSet<String> = instances.iter()
+            .filter_map(|i| i["InstanceType"].as_str().map(String::from))
+            .collect();
+        
+        let mut by_az: BTreeMap<String, usize> = BTreeMap::new();
+        let mut by_subnet: BTreeMap<String, usize> = BTreeMap::new();
+        let mut subnet_azs: HashMap<&str, &str> = HashMap::new();
+        for subnet in subnets {
+            let id = subnet["SubnetId"].as_str().unwrap_or("unknown");
+            let az = subnet["AvailabilityZone"].as_str().unwrap_or("unknown");
+            subnet_azs.insert(id, az);
+            by_subnet.insert(id.to_string(), 0);
+            by_az.entry(az.to_string()).or_insert(0);
+        }
+        for instance in instances {
+            let subnet = instance["SubnetId"].as_str();
+            let az = instance["Placement"]["AvailabilityZone"].as_str()
+                .or_else(|| subnet.and_then(|s| subnet_azs.get(s).copied()))
+                .unwrap_or("unknown");
+            *by_az.entry(az.to_string()).or_insert(0) += 1;
+            if let Some(subnet) = subnet {
+                *by_subnet.entry(subnet.to_string()).or_insert(0) += 1;
+            }
+        }
+        
+        VpcDiffSide {
+            vpc_id: vpc["VpcId"].as_str().unwrap_or("unknown").to_string(),
+            region: region.to_string(),
+            cidr: vpc["CidrBlock"].as_str().unwrap_or("unknown").to_string(),
+            cidr_blocks: vpc_cidr_blocks(vpc),
+            subnets: subnets.len(),
+            instances_total: instances.len(),
+            instances_running: instances.iter().filter(|i| i["State"]["Name"].as_str() == Some("running")).count(),
+            instance_types: sorted(types.iter().collect()),
+            route_tables: rts.len(),
+            az_spread: az_spread(&by_az),
+            instances_by_az: by_az,
+            instances_by_subnet: by_subnet,
+        }
+    };
+    
+    let cidrs1: HashSet<String> = subnets1.iter()
+        .filter_map(|s| s["CidrBlock"].as_str().map(String::from))
+        .collect();
+    let cidrs2: HashSet<String> = subnets2.iter()
+        .filter_map(|s| s["CidrBlock"].as_str().map(String::from))
+        .collect();
+    
+    // Extract unique route destinations
+    let dests1 = route_destinations(&rts1);
+    let dests2 = route_destinations(&rts2);
+    
+    let vpc1 = side(vpc1, region1, &subnets1, &instances1, &rts1);
+    let vpc2 = side(vpc2, region2, &subnets2, &instances2, &rts2);
+    // AZ names differ between regions, so only the shape of the spread is compared
+    let concentrated = |spread: &str| spread == "single-az" || spread == "skewed";
+    let ha_mismatch = match (vpc1.az_spread, vpc2.az_spread) {
+        (one, "balanced") if concentrated(one) => Some(format!("VPC 1 is {} while VPC 2 is balanced across AZs", one)),
+        ("balanced", two) if concentrated(two) => Some(format!("VPC 2 is {} while VPC 1 is balanced across AZs", two)),
+        _ => None,
+    };
+    
+    let diff = VpcDiff {
+        vpc1,
+        vpc2,
+        ha_mismatch,
+        subnets_only_in_vpc1: sorted(cidrs1.difference(&cidrs2).collect()),
+        subnets_only_in_vpc2: sorted(cidrs2.difference(&cidrs1).collect()),
+        routes_only_in_vpc1: sorted(dests1.difference(&dests2).collect()),
+        routes_only_in_vpc2: sorted(dests2.difference(&dests1).collect()),
+        common_routes: sorted(dests1.intersection(&dests2).collect()),
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &diff);
+    }
+    
+    print_vpc_diff(&diff);
+    Ok(())
+}
+
+fn print_vpc_diff(diff: &VpcDiff) {
+    println!("{}", "🔍 Comparing VPCs...".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    
+    println!("VPC 1: {} ({}) - {}", 
+        diff.vpc1.vpc_id.cyan().bold(), 
+        cidr_blocks_label(&diff.vpc1.cidr_blocks, &diff.vpc1.cidr),
+        diff.vpc1.region.yellow()
+    );
+    println!("VPC 2: {} ({}) - {}", 
+        diff.vpc2.vpc_id.cyan().bold(), 
+        cidr_blocks_label(&diff.vpc2.cidr_blocks, &diff.vpc2.cidr),
+        diff.vpc2.region.yellow()
+    );
+    println!();
+    
+    // Compare subnets
+    println!("{}", "📊 Subnets:".yellow().bold());
+    println!("  VPC 1: {} subnet(s)", diff.vpc1.subnets.to_string().cyan());
+    println!("  VPC 2: {} subnet(s)", diff.vpc2.subnets.to_string().cyan());
+    
+    if !diff.subnets_only_in_vpc1.is_empty() {
+        println!("  {} Only in VPC 1: {}", "−".red(), diff.subnets_only_in_vpc1.join(", ").red());
+    }
+    if !diff.subnets_only_in_vpc2.is_empty() {
+        println!("  {} Only in VPC 2: {}", "+".green(), diff.subnets_only_in_vpc2.join(", ").green());
+    }
+    println!();
+    
+    // Compare instances
+    println!("{}", "💻 Instances:".yellow().bold());
+    println!("  VPC 1: {} total, {} running", 
+        diff.vpc1.instances_total.to_string().cyan(),
+        diff.vpc1.instances_running.to_string().green()
+    );
+    println!("  VPC 2: {} total, {} running", 
+        diff.vpc2.instances_total.to_string().cyan(),
+        diff.vpc2.instances_running.to_string().green()
+    );
+    
+    if !diff.vpc1.instance_types.is_empty() || !diff.vpc2.instance_types.is_empty() {
+        println!("  Instance types:");
+        println!("    VPC 1: {}", diff.vpc1.instance_types.join(", ").bright_black());
+        println!("    VPC 2: {}", diff.vpc2.instance_types.join(", ").bright_black());
+    }
+    println!();
+    
+    println!("{}", "🏢 Instance Distribution:".yellow().bold());
+    for (label, side) in [("VPC 1", &diff.vpc1), ("VPC 2", &diff.vpc2)] {
+        let azs: Vec<String> = side.instances_by_az.iter().map(|(az, count)| format!("{} {}", az, count)).collect();
+        let spread = match side.az_spread {
+            "balanced" => side.az_spread.green(),
+            "n/a" => side.az_spread.bright_black(),
+            _ => side.az_spread.yellow().bold(),
+        };
+        println!("  {}: {} ({})", label, azs.join(", ").cyan(), spread);
+        let subnets: Vec<String> = side.instances_by_subnet.iter()
+            .map(|(subnet, count)| format!("{} {}", subnet, count))
+            .collect();
+        if !subnets.is_empty() {
+            println!("    {}", subnets.join(", ").bright_black());
+        }
+    }
+    if let Some(mismatch) = &diff.ha_mismatch {
+        println!("  {} {}", "⚠️ ".yellow(), mismatch.yellow());
+    }
+    println!();
+    
+    // Compare route tables
+    println!("{}", "🗺️  Route Tables:".yellow().bold());
+    println!("  VPC 1: {} route table(s)", diff.vpc1.route_tables.to_string().cyan());
+    println!("  VPC 2: {} route table(s)", diff.vpc2.route_tables.to_string().cyan());
+    
+    if !diff.routes_only_in_vpc1.is_empty() {
+        println!("  {} Routes only in VPC 1: {}", "−".red(), diff.routes_only_in_vpc1.join(", ").red());
+    }
+    if !diff.routes_only_in_vpc2.is_empty() {
+        println!("  {} Routes only in VPC 2: {}", "+".green(), diff.routes_only_in_vpc2.join(", ").green());
+    }
+    
+    if !diff.common_routes.is_empty() {
+        println!("  {} Common routes: {}", "=".yellow(), diff.common_routes.join(", ").bright_black());
+    }
+    
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+}
+
+#[derive(Debug, Serialize)]
+struct SubnetUsage {
+    vpc_id: String,
+    subnet_id: String,
+    cidr: String,
+    availability_zone: String,
+    usable_ips: u64,
+    available_ips: u64,
+    used_ips: u64,
+    utilization_percent: f64,
+    near_exhaustion: bool,
+}
+
+impl SubnetUsage {
+    fn new(vpc_id: &str, subnet_id: &str, cidr: &str, availability_zone: &str, available_ips: u64, threshold: f64) -> Result<Self> {
+        let (_, prefix) = cidr::parse_cidr(cidr)?;
+        let usable_ips = cidr::aws_usable_hosts(prefix);
+        let used_ips = usable_ips.saturating_sub(available_ips);
+        let utilization_percent = if usable_ips > 0 {
+            used_ips as f64 / usable_ips as f64 * 100.0
+        } else {
+            0.0
+        };
+        
+        Ok(SubnetUsage {
+            vpc_id: vpc_id.to_string(),
+            subnet_id: subnet_id.to_string(),
+            cidr: cidr.to_string(),
+            availability_zone: availability_zone.to_string(),
+            usable_ips,
+            available_ips,
+            used_ips,
+            utilization_percent,
+            near_exhaustion: utilization_percent >= threshold,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SubnetUsageReport {
+    region: String,
+    threshold_percent: f64,
+    subnets: Vec<SubnetUsage>,
+    near_exhaustion: usize,
+}
+
+pub fn subnet_usage(region: &str, vpc_filter: Option<&str>, threshold: f64, format: OutputFormat) -> Result<()> {
+    let mut subnets = Vec::new();
+    let mut vpc_cidrs = HashMap::new();
+    
+    for vpc in get_vpcs(region)? {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        if !vpc_selected(vpc_filter, vpc_id) {
+            continue;
+        }
+        report_vpcs(region, [&vpc]);
+        vpc_cidrs.insert(vpc_id.to_string(), cidr_blocks_label(&vpc_cidr_blocks(&vpc), vpc["CidrBlock"].as_str().unwrap_or("unknown")));
+        
+        for subnet in get_subnets(region, vpc_id)? {
+            subnets.push(SubnetUsage::new(
+                vpc_id,
+                subnet["SubnetId"].as_str().unwrap_or("unknown"),
+                subnet["CidrBlock"].as_str().unwrap_or("unknown"),
+                subnet["AvailabilityZone"].as_str().unwrap_or("unknown"),
+                subnet["AvailableIpAddressCount"].as_u64().unwrap_or(0),
+                threshold,
+            )?);
+        }
+    }
+    
+    let report = SubnetUsageReport {
+        region: region.to_string(),
+        threshold_percent: threshold,
+        near_exhaustion: subnets.iter().filter(|s| s.near_exhaustion).count(),
+        subnets,
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &report);
+    }
+    
+    println!("{}", "📈 Subnet IP Utilization".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", report.region.yellow());
+    println!();
+    
+    if report.subnets.is_empty() {
+        println!("{}", "No subnets found".yellow());
+        return Ok(());
+    }
+    
+    let mut current_vpc = "";
+    for usage in &report.subnets {
+        if usage.vpc_id != current_vpc {
+            current_vpc = &usage.vpc_id;
+            println!("{} {} ({})", "🏢".cyan(), current_vpc.cyan().bold(), vpc_cidrs[current_vpc]);
+        }
+        
+        let pct = format!("{:.1}%", usage.utilization_percent);
+        let pct = if usage.near_exhaustion {
+            pct.red().bold()
+        } else if usage.utilization_percent >= threshold * 0.75 {
+            pct.yellow()
+        } else {
+            pct.green()
+        };
+        
+        println!("  • {} ({}) - {} - {}/{} used - {}{}", 
+            usage.subnet_id.cyan(),
+            usage.cidr.green(),
+            usage.availability_zone.bright_black(),
+            usage.used_ips,
+            usage.usable_ips,
+            pct,
+            if usage.near_exhaustion { " ⚠️  near exhaustion".red().to_string() } else { String::new() }
+        );
+    }
+    
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+    if report.near_exhaustion > 0 {
+        println!("{} subnet(s) at or above {}% utilization", 
+            report.near_exhaustion.to_string().red().bold(),
+            threshold
+        );
+    } else {
+        println!("{}", format!("✅ All subnets below {}% utilization", threshold).green());
+    }
+    
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct VpcDetail {
+    region: String,
+    id: String,
+    cidr: String,
+    cidr_blocks: Vec<CidrBlockInfo>,
+    is_default: bool,
+    subnets: Vec<SubnetUsage>,
+    route_tables: Vec<RouteTableInfo>,
+    gateways: Vec<GatewayInfo>,
+    security_groups: Vec<SecurityGroupSummary>,
+    endpoints: Vec<EndpointInfo>,
+    instance_count: usize,
+    instances_by_state: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct GatewayInfo {
+    /// "internet", "nat", "transit", or "vpn"
+    kind: &'static str,
+    id: String,
+    state: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SecurityGroupSummary {
+    id: String,
+    name: String,
+    ingress_rules: usize,
+    egress_rules: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct EndpointInfo {
+    id: String,
+    service: String,
+    endpoint_type: String,
+    state: String,
+}
+
+/// Everything about one VPC: the `aws-map` view of it plus gateways, groups, and endpoints
+pub fn describe_vpc(region: &str, vpc_id: &str, threshold: f64, format: OutputFormat) -> Result<()> {
+    let vpc = get_vpcs(region)?
+        .into_iter()
+        .find(|vpc| vpc["VpcId"].as_str() == Some(vpc_id))
+        .ok_or_else(|| anyhow::anyhow!("VPC {} not found in {}", vpc_id, region))?;
+    report_vpcs(region, [&vpc]);
+    let info = gather_vpc(region, &vpc, vpc_id)?;
+    
+    let subnets = info.subnets.iter()
+        .map(|s| SubnetUsage::new(vpc_id, &s.id, &s.cidr, &s.availability_zone, s.available_ips, threshold))
+        .collect::<Result<Vec<_>>>()?;
+    
+    let security_groups = get_security_groups(region, Some(vpc_id))?
+        .iter()
+        .map(|sg| SecurityGroupSummary {
+            id: sg["GroupId"].as_str().unwrap_or("unknown").to_string(),
+            name: sg["GroupName"].as_str().unwrap_or("unnamed").to_string(),
+            ingress_rules: rule_entry_count(&sg["IpPermissions"]),
+            egress_rules: rule_entry_count(&sg["IpPermissionsEgress"]),
+        })
+        .collect();
+    
+    let mut instances_by_state: BTreeMap<String, usize> = BTreeMap::new();
+    for instance in &info.instances {
+        *instances_by_state.entry(instance.state.clone()).or_default() += 1;
+    }
+    
+    let detail = VpcDetail {
+        region: region.to_string(),
+        id: info.id,
+        cidr: info.cidr,
+        cidr_blocks: info.cidr_blocks,
+        is_default: info.is_default,
+        subnets,
+        route_tables: info.route_tables,
+        gateways: get_vpc_gateways(region, vpc_id),
+        security_groups,
+        endpoints: get_vpc_endpoints(region, vpc_id),
+        instance_count: info.instances.len(),
+        instances_by_state,
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &detail);
+    }
+    
+    println!("{}", format!("🔎 VPC {}", detail.id).cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", detail.region.yellow());
+    println!("CIDR: {}{}", cidr_blocks_label(&detail.cidr_blocks, &detail.cidr), if detail.is_default { " (default VPC)".bright_black().to_string() } else { String::new() });
+    
+    let states: Vec<String> = detail.instances_by_state.iter().map(|(state, n)| format!("{} {}", n, state)).collect();
+    println!("Instances: {}{}", detail.instance_count.to_string().cyan(),
+        if states.is_empty() { String::new() } else { format!(" ({})", states.join(", ")).bright_black().to_string() });
+    println!();
+    
+    println!("{}", format!("Subnets ({}):", detail.subnets.len()).yellow().bold());
+    for usage in &detail.subnets {
+        let pct = format!("{:.1}%", usage.utilization_percent);
+        let pct = if usage.near_exhaustion { pct.red().bold() } else { pct.green() };
+        println!("  • {} ({}) - {} - {}/{} used - {}", 
+            usage.subnet_id.cyan(),
+            usage.cidr.green(),
+            usage.availability_zone.bright_black(),
+            usage.used_ips,
+            usage.usable_ips,
+            pct
+        );
+    }
+    println!();
+    
+    println!("{}", format!("Route Tables ({}):", detail.route_tables.len()).yellow().bold());
+    for rt in &detail.route_tables {
+        let usage = if rt.is_main { "main".to_string() } else { format!("{} subnet(s)", rt.subnet_ids.len()) };
+        println!("  📋 {} {}", rt.id.cyan(), format!("({})", usage).bright_black());
+        for route in &rt.routes {
+            let line = format!("{} → {} ({})", route.destination, route.target, route.state);
+            if route.state == "blackhole" {
+                println!("     {} {}", "⚠️ ".red(), line.red());
+            } else {
+                println!("     {}", line);
+            }
+        }
+    }
+    println!();
+    
+    println!("{}", format!("Gateways ({}):", detail.gateways.len()).yellow().bold());
+    for gateway in &detail.gateways {
+        println!("  • {} {} {}", gateway.kind, gateway.id.cyan(), format!("({})", gateway.state).bright_black());
+    }
+    println!();
+    
+    println!("{}", format!("Security Groups ({}):", detail.security_groups.len()).yellow().bold());
+    for sg in &detail.security_groups {
+        println!("  🛡️  {} ({}) - {} in, {} out", sg.name.cyan(), sg.id.bright_black(), sg.ingress_rules, sg.egress_rules);
+    }
+    println!();
+    
+    println!("{}", format!("Endpoints ({}):", detail.endpoints.len()).yellow().bold());
+    for endpoint in &detail.endpoints {
+        println!("  • {} {} - {} {}", endpoint.id.cyan(), endpoint.service, endpoint.endpoint_type.bright_black(),
+            format!("({})", endpoint.state).bright_black());
+    }
+    
+    println!("{}", "═".repeat(70).bright_black());
+    
+    Ok(())
+}
+
+/// Internet, NAT, transit, and VPN gateways attached to a VPC; best-effort, like the other optional lookups
+fn get_vpc_gateways(region: &str, vpc_id: &str) -> Vec<GatewayInfo> {
+    let describe = |operation: &str, filter: &str, collection: &str| -> Vec<Value> {
+        awscli::run(&["ec2", operation, "--region", region, "--filters", &format!("Name={},Values={}", filter, vpc_id)])
+            .ok()
+            .filter(|output| output.success)
+            .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+            .and_then(|json| json[collection].as_array().cloned())
+            .unwrap_or_default()
+    };
+    let attachment_state = |gw: &Value| gw["Attachments"].as_array()
+        .and_then(|attachments| attachments.iter().find(|a| a["VpcId"].as_str() == Some(vpc_id)))
+        .and_then(|a| a["State"].as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    
+    let mut gateways = Vec::new();
+    for igw in describe("describe-internet-gateways", "attachment.vpc-id", "InternetGateways") {
+        gateways.push(GatewayInfo {
+            kind: "internet",
+            id: igw["InternetGatewayId"].as_str().unwrap_or("unknown").to_string(),
+            state: attachment_state(&igw),
+        });
+    }
+    for nat in describe("describe-nat-gateways", "vpc-id", "NatGateways") {
+        gateways.push(GatewayInfo {
+            kind: "nat",
+            id: nat["NatGatewayId"].as_str().unwrap_or("unknown").to_string(),
+            state: nat["State"].as_str().unwrap_or("unknown").to_string(),
+        });
+    }
+    for attachment in describe("describe-transit-gateway-attachments", "resource-id", "TransitGatewayAttachments") {
+        gateways.push(GatewayInfo {
+            kind: "transit",
+            id: attachment["TransitGatewayId"].as_str().unwrap_or("unknown").to_string(),
+            state: attachment["State"].as_str().unwrap_or("unknown").to_string(),
+        });
+    }
+    for vgw in describe("describe-vpn-gateways", "attachment.vpc-id", "VpnGateways") {
+        gateways.push(GatewayInfo {
+            kind: "vpn",
+            id: vgw["VpnGatewayId"].as_str().unwrap_or("unknown").to_string(),
+            state: attachment_state(&vgw),
+        });
+    }
+    
+    gateways
+}
+
+fn get_vpc_endpoints(region: &str, vpc_id: &str) -> Vec<EndpointInfo> {
+    awscli::run(&["ec2", "describe-vpc-endpoints", "--region", region, "--filters", &format!("Name=vpc-id,Values={}", vpc_id)])
+        .ok()
+        .filter(|output| output.success)
+        .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+        .and_then(|json| json["VpcEndpoints"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .map(|endpoint| EndpointInfo {
+            id: endpoint["VpcEndpointId"].as_str().unwrap_or("unknown").to_string(),
+            service: endpoint["ServiceName"].as_str().unwrap_or("unknown").to_string(),
+            endpoint_type: endpoint["VpcEndpointType"].as_str().unwrap_or("unknown").to_string(),
+            state: endpoint["State"].as_str().unwrap_or("unknown").to_string(),
+        })
+        .collect()
+}
+
+/// Every resource kind as a flat list, for CMDB imports and other tooling
+/// that wants rows rather than the nested `aws-map` topology
+#[derive(Debug, Serialize)]
+struct Inventory {
+    region: String,
+    vpcs: Vec<InventoryVpc>,
+    subnets: Vec<InVpc<SubnetInfo>>,
+    route_tables: Vec<InVpc<RouteTableInfo>>,
+    instances: Vec<InVpc<InstanceInfo>>,
+    security_groups: Vec<InVpc<SecurityGroupSummary>>,
+    /// Internet, NAT, VPN gateways and transit gateway attachments, per VPC
+    gateways: Vec<InVpc<GatewayInfo>>,
+    endpoints: Vec<InVpc<EndpointInfo>>,
+    /// Including transit gateways no VPC in the region is attached to
+    transit_gateways: Vec<InventoryTransitGateway>,
+}
+
+#[derive(Debug, Serialize)]
+struct InventoryVpc {
+    id: String,
+    name: Option<String>,
+    cidr: String,
+    cidr_blocks: Vec<CidrBlockInfo>,
+    is_default: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct InventoryTransitGateway {
+    id: String,
+    state: String,
+    owner_id: Option<String>,
+}
+
+/// A row tagged with the VPC it belongs to
+#[derive(Debug, Serialize)]
+struct InVpc<T> {
+    vpc_id: String,
+    #[serde(flatten)]
+    item: T,
+}
+
+impl<T> InVpc<T> {
+    fn of(vpc_id: &str) -> impl Fn(T) -> InVpc<T> + '_ {
+        move |item| InVpc { vpc_id: vpc_id.to_string(), item }
+    }
+}
+
+pub fn inventory(region: &str, format: OutputFormat) -> Result<()> {
+    let mut inventory = Inventory {
+        region: region.to_string(),
+        vpcs: Vec::new(),
+        subnets: Vec::new(),
+        route_tables: Vec::new(),
+        instances: Vec::new(),
+        security_groups: Vec::new(),
+        gateways: Vec::new(),
+        endpoints: Vec::new(),
+        transit_gateways: Vec::new(),
+    };
+    
+    let vpcs = get_vpcs(region)?;
+    report_vpcs(region, &vpcs);
+    
+    for vpc in vpcs {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        let info = gather_vpc(region, &vpc, vpc_id)?;
+        
+        inventory.vpcs.push(InventoryVpc {
+            id: info.id.clone(),
+            name: tag_value(&vpc, "Name").map(String::from),
+            cidr: info.cidr,
+            cidr_blocks: info.cidr_blocks,
+            is_default: info.is_default,
+        });
+        inventory.subnets.extend(info.subnets.into_iter().map(InVpc::of(vpc_id)));
+        inventory.route_tables.extend(info.route_tables.into_iter().map(InVpc::of(vpc_id)));
+        inventory.instances.extend(info.instances.into_iter().map(InVpc::of(vpc_id)));
+        inventory.security_groups.extend(get_security_groups(region, Some(vpc_id))?
+            .iter()
+            .map(|sg| SecurityGroupSummary {
+                id: sg["GroupId"].as_str().unwrap_or("unknown").to_string(),
+                name: sg["GroupName"].as_str().unwrap_or("unnamed").to_string(),
+                ingress_rules: rule_entry_count(&sg["IpPermissions"]),
+                egress_rules: rule_entry_count(&sg["IpPermissionsEgress"]),
+            })
+            .map(InVpc::of(vpc_id)));
+        inventory.gateways.extend(get_vpc_gateways(region, vpc_id).into_iter().map(InVpc::of(vpc_id)));
+        inventory.endpoints.extend(get_vpc_endpoints(region, vpc_id).into_iter().map(InVpc::of(vpc_id)));
+    }
+    
+    inventory.transit_gateways = get_transit_gateways(region)?
+        .iter()
+        .map(|tgw| InventoryTransitGateway {
+            id: tgw["TransitGatewayId"].as_str().unwrap_or("unknown").to_string(),
+            state: tgw["State"].as_str().unwrap_or("unknown").to_string(),
+            owner_id: tgw["OwnerId"].as_str().map(String::from),
+        })
+        .collect();
+    
+    if format.is_structured() {
+        return output::print(format, &inventory);
+    }
+    
+    println!("{}", "📦 Inventory".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", region.yellow());
+    println!();
+    
+    let mut table = Table::new(&["RESOURCE", "COUNT"]).right_align(1);
+    for (kind, count) in [
+        ("VPCs", inventory.vpcs.len()),
+        ("Subnets", inventory.subnets.len()),
+        ("Route tables", inventory.route_tables.len()),
+        ("Instances", inventory.instances.len()),
+        ("Security groups", inventory.security_groups.len()),
+        ("Gateways", inventory.gateways.len()),
+        ("Endpoints", inventory.endpoints.len()),
+        ("Transit gateways", inventory.transit_gateways.len()),
+    ] {
+        table.row(vec![kind.to_string(), count.to_string()]);
+    }
+    table.print();
+    println!();
+    println!("{}", "Use --output json (or yaml) for the full inventory".bright_black());
+    
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct CidrOverlap {
+    vpc_a: String,
+    cidr_a: String,
+    vpc_b: String,
+    cidr_b: String,
+    overlap_start: String,
+    overlap_end: String,
+    overlap_addresses: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct OverlapReport {
+    region: String,
+    vpcs: usize,
+    overlaps: Vec<CidrOverlap>,
+}
+
+pub fn find_cidr_overlaps(region: &str, format: OutputFormat) -> Result<()> {
+    let vpcs = get_vpcs(region)?;
+    report_vpcs(region, &vpcs);
+    
+    // Secondary blocks count too: a peer that overlaps any of them can't be routed
+    let mut blocks = Vec::new();
+    for vpc in &vpcs {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        for block in vpc_cidr_blocks(vpc).into_iter().filter(|b| !b.ipv6 && b.in_use()) {
+            let (base, prefix) = cidr::parse_cidr(&block.cidr)?;
+            blocks.push((vpc_id.to_string(), block.cidr, cidr::range(base, prefix)));
+        }
+    }
+    
+    let mut overlaps = Vec::new();
+    for (i, (vpc_a, cidr_a, range_a)) in blocks.iter().enumerate() {
+        for (vpc_b, cidr_b, range_b) in &blocks[i + 1..] {
+            if vpc_a == vpc_b {
+                continue;
+            }
+            if let Some((start, end)) = cidr::overlap(*range_a, *range_b) {
+                overlaps.push(CidrOverlap {
+                    vpc_a: vpc_a.clone(),
+                    cidr_a: cidr_a.clone(),
+                    vpc_b: vpc_b.clone(),
+                    cidr_b: cidr_b.clone(),
+                    overlap_start: cidr::format_ip(start),
+                    overlap_end: cidr::format_ip(end),
+                    overlap_addresses: u64::from(end - start) + 1,
+                });
+            }
+        }
+    }
+    
+    let report = OverlapReport {
+        region: region.to_string(),
+        vpcs: vpcs.len(),
+        overlaps,
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &report);
+    }
+    
+    println!("{}", "🧩 Checking VPC CIDR Overlaps...".cyan().bold());
     println!("{}", "═".repeat(70).bright_black());
-    println!("Region: {}", region.yellow());
+    println!("Region: {} ({} VPCs)", report.region.yellow(), report.vpcs.to_string().cyan());
     println!();
     
-    // Check for Transit Gateways first
-    let tgws = get_transit_gateways(region)?;
-    if !tgws.is_empty() {
-        println!("{}", "🌐 Transit Gateways:".yellow().bold());
-        for tgw in &tgws {
-            let tgw_id = tgw["TransitGatewayId"].as_str().unwrap_or("unknown");
-            let state = tgw["State"].as_str().unwrap_or("unknown");
-            
-            println!("  {} {} - {}", 
-                "🔗".cyan(),
-                tgw_id.cyan().bold(),
-                state.bright_black()
+    if report.overlaps.is_empty() {
+        println!("{}", "✅ No overlapping CIDR blocks".green().bold());
+    } else {
+        for o in &report.overlaps {
+            println!("{} {} ({}) ↔ {} ({})", 
+                "⚠️ ".red(),
+                o.vpc_a.cyan().bold(),
+                o.cidr_a.green(),
+                o.vpc_b.cyan().bold(),
+                o.cidr_b.green()
+            );
+            println!("   Overlap: {} - {} ({} addresses)", 
+                o.overlap_start.red(),
+                o.overlap_end.red(),
+                o.overlap_addresses.to_string().yellow()
             );
-            
-            // Get attachments for this TGW
-            let attachments = get_tgw_attachments(region, tgw_id)?;
-            for att in &attachments {
-                let att_id = att["TransitGatewayAttachmentId"].as_str().unwrap_or("unknown");
-                let vpc_id = att["ResourceId"].as_str().unwrap_or("unknown");
-                let att_state = att["State"].as_str().unwrap_or("unknown");
-                
-                println!("    ↳ {} → {} ({})", 
-                    att_id.bright_black(),
-                    vpc_id.green(),
-                    att_state.yellow()
-                );
-            }
         }
         println!();
+        println!("{} overlapping pair(s) - these VPCs cannot be peered or routed through a TGW cleanly", 
+            report.overlaps.len().to_string().red().bold()
+        );
+    }
+    
+    println!("{}", "═".repeat(70).bright_black());
+    Ok(())
+}
+
+/// Whether one side of a connection has routes back to the other side's CIDRs
+#[derive(Debug, Serialize)]
+struct RouteCoverage {
+    from_vpc: String,
+    to_vpc: String,
+    destinations: Vec<String>,
+    /// "routed" (every route table in use), "partial", or "missing"
+    status: &'static str,
+    /// Route tables in use that lack a route through the connection
+    missing_in: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConnectionCheck {
+    /// "peering" or "transit-gateway"
+    kind: &'static str,
+    via: String,
+    vpc_a: String,
+    vpc_b: String,
+    a_to_b: RouteCoverage,
+    /// None when the other VPC is in another region or account, so its route tables can't be read
+    b_to_a: Option<RouteCoverage>,
+}
+
+impl ConnectionCheck {
+    fn is_one_way(&self) -> bool {
+        match &self.b_to_a {
+            Some(back) => (self.a_to_b.status == "routed") != (back.status == "routed"),
+            None => false,
+        }
     }
     
-    // Get VPCs
+    fn is_healthy(&self) -> bool {
+        self.a_to_b.status == "routed" && self.b_to_a.as_ref().is_none_or(|back| back.status == "routed")
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RoutingCheckReport {
+    region: String,
+    connections: Vec<ConnectionCheck>,
+    one_way: usize,
+    unhealthy: usize,
+}
+
+/// For each peering connection and each pair of VPCs attached to the same
+/// transit gateway, check that both sides route to each other through it
+pub fn check_return_routes(region: &str, format: OutputFormat) -> Result<()> {
     let vpcs = get_vpcs(region)?;
+    report_vpcs(region, &vpcs);
+    let route_tables = get_route_tables(region, None)?;
     
-    if vpcs.is_empty() {
-        println!("{}", "No VPCs found in this region".yellow());
-        return Ok(());
+    let vpc_cidrs = |vpc_id: &str| -> Vec<String> {
+        vpcs.iter()
+            .find(|v| v["VpcId"].as_str() == Some(vpc_id))
+            .map(|v| vpc_cidr_blocks(v).into_iter().filter(|b| !b.ipv6 && b.in_use()).map(|b| b.cidr).collect())
+            .unwrap_or_default()
+    };
+    let coverage = |from: &str, to: &str, to_cidrs: Vec<String>, via: &str| {
+        let tables: Vec<&Value> = route_tables.iter()
+            .filter(|rt| rt["VpcId"].as_str() == Some(from))
+            .collect();
+        route_coverage(from, to, &tables, to_cidrs, via)
+    };
+    
+    let mut connections = Vec::new();
+    
+    for pcx in get_vpc_peering_connections(region)? {
+        let via = pcx["VpcPeeringConnectionId"].as_str().unwrap_or("unknown");
+        let side = |info: &Value| -> (String, Vec<String>, bool) {
+            let vpc_id = info["VpcId"].as_str().unwrap_or("unknown").to_string();
+            let local = info["Region"].as_str().is_none_or(|r| r == region) && vpcs.iter().any(|v| v["VpcId"].as_str() == Some(&vpc_id));
+            let cidrs = info["CidrBlockSet"].as_array()
+                .map(|set| set.iter().filter_map(|c| c["CidrBlock"].as_str().map(String::from)).collect::<Vec<_>>())
+                .filter(|set| !set.is_empty())
+                .or_else(|| info["CidrBlock"].as_str().map(|c| vec![c.to_string()]))
+                .unwrap_or_default();
+            (vpc_id, cidrs, local)
+        };
+        
+        // Keep a visible VPC on the left so there is always one direction to check
+        let (mut a, mut b) = (side(&pcx["RequesterVpcInfo"]), side(&pcx["AccepterVpcInfo"]));
+        if !a.2 {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let ((vpc_a, cidrs_a, a_local), (vpc_b, cidrs_b, b_local)) = (a, b);
+        if !a_local {
+            continue;
+        }
+        
+        connections.push(ConnectionCheck {
+            kind: "peering",
+            via: via.to_string(),
+            a_to_b: coverage(&vpc_a, &vpc_b, cidrs_b, via),
+            b_to_a: b_local.then(|| coverage(&vpc_b, &vpc_a, cidrs_a, via)),
+            vpc_a,
+            vpc_b,
+        });
     }
     
-    for vpc in vpcs {
-        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
-        let cidr = vpc["CidrBlock"].as_str().unwrap_or("unknown");
-        let is_default = vpc["IsDefault"].as_bool().unwrap_or(false);
+    for tgw in get_transit_gateways(region)? {
+        let via = tgw["TransitGatewayId"].as_str().unwrap_or("unknown");
+        let mut attached: Vec<String> = get_tgw_attachments(region, via)?
+            .iter()
+            .filter(|att| att["ResourceType"].as_str().is_none_or(|t| t == "vpc"))
+            .filter(|att| att["State"].as_str() == Some("available"))
+            .filter_map(|att| att["ResourceId"].as_str().map(String::from))
+            .filter(|vpc_id| vpcs.iter().any(|v| v["VpcId"].as_str() == Some(vpc_id)))
+            .collect();
+        attached.sort();
+        attached.dedup();
         
-        println!("{}", "┌─────────────────────────────────────────────────────────────────┐".bright_black());
-        println!("│ {} VPC: {} ({})", 
-            if is_default { "🏠" } else { "🏢" },
-            vpc_id.cyan().bold(),
-            cidr.green()
+        for (i, vpc_a) in attached.iter().enumerate() {
+            for vpc_b in &attached[i + 1..] {
+                connections.push(ConnectionCheck {
+                    kind: "transit-gateway",
+                    via: via.to_string(),
+                    vpc_a: vpc_a.clone(),
+                    vpc_b: vpc_b.clone(),
+                    a_to_b: coverage(vpc_a, vpc_b, vpc_cidrs(vpc_b), via),
+                    b_to_a: Some(coverage(vpc_b, vpc_a, vpc_cidrs(vpc_a), via)),
+                });
+            }
+        }
+    }
+    
+    let report = RoutingCheckReport {
+        region: region.to_string(),
+        one_way: connections.iter().filter(|c| c.is_one_way()).count(),
+        unhealthy: connections.iter().filter(|c| !c.is_healthy()).count(),
+        connections,
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &report);
+    }
+    
+    println!("{}", "🔀 Checking Return Routes...".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", report.region.yellow());
+    println!();
+    
+    if report.connections.is_empty() {
+        println!("{}", "No peering connections or shared transit gateways found".yellow());
+        return Ok(());
+    }
+    
+    for connection in &report.connections {
+        let icon = if connection.is_healthy() { "✅" } else { "⚠️ " };
+        println!("{} {} ⇄ {} via {} ({})", 
+            icon,
+            connection.vpc_a.cyan().bold(),
+            connection.vpc_b.cyan().bold(),
+            connection.via.green(),
+            connection.kind.bright_black()
+        );
+        print_route_coverage(&connection.a_to_b, &connection.via);
+        match &connection.b_to_a {
+            Some(back) => print_route_coverage(back, &connection.via),
+            None => println!("   {} {} → {}: {}", 
+                "•".bright_black(),
+                connection.vpc_b,
+                connection.vpc_a,
+                "not checked (peer VPC is in another region or account)".bright_black()
+            ),
+        }
+    }
+    
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+    if report.unhealthy == 0 {
+        println!("{}", format!("✅ All {} connection(s) route both ways", report.connections.len()).green().bold());
+    } else {
+        println!("{} of {} connection(s) have missing routes ({} one-way)", 
+            report.unhealthy.to_string().red().bold(),
+            report.connections.len(),
+            report.one_way.to_string().yellow()
+        );
+    }
+    
+    Ok(())
+}
+
+fn print_route_coverage(coverage: &RouteCoverage, via: &str) {
+    let destinations = coverage.destinations.join(", ");
+    match coverage.status {
+        "routed" => println!("   {} {} → {} ({}): routed", "✅".green(), coverage.from_vpc, coverage.to_vpc, destinations.green()),
+        "partial" => println!("   {} {} → {} ({}): no route via {} in {}", 
+            "⚠️ ".yellow(),
+            coverage.from_vpc,
+            coverage.to_vpc,
+            destinations.green(),
+            via,
+            coverage.missing_in.join(", ").yellow()
+        ),
+        _ => println!("   {} {} → {} ({}): {}", 
+            "❌".red(),
+            coverage.from_vpc,
+            coverage.to_vpc,
+            destinations.green(),
+            format!("no route via {} in any route table", via).red()
+        ),
+    }
+}
+
+/// Which of `from`'s route tables in use send every one of `destinations` through `via`.
+/// A broader route (10.0.0.0/8 → tgw) counts; a blackholed one does not.
+fn route_coverage(from: &str, to: &str, tables: &[&Value], destinations: Vec<String>, via: &str) -> RouteCoverage {
+    let ranges: Vec<(u32, u32)> = destinations.iter()
+        .filter_map(|d| cidr::parse_cidr(d).ok())
+        .map(|(base, prefix)| cidr::range(base, prefix))
+        .collect();
+    
+    // Tables nothing uses can't break connectivity
+    let in_use: Vec<&&Value> = tables.iter()
+        .filter(|rt| rt["Associations"].as_array().is_some_and(|a| a.iter().any(|a| a["SubnetId"].is_string() || a["Main"].as_bool() == Some(true))))
+        .collect();
+    
+    let missing_in: Vec<String> = in_use.iter()
+        .filter(|rt| {
+            let routes: Vec<(u32, u32)> = rt["Routes"].as_array().into_iter().flatten()
+                .filter(|r| route_target(r) == via && r["State"].as_str() != Some("blackhole"))
+                .filter_map(|r| r["DestinationCidrBlock"].as_str().and_then(|d| cidr::parse_cidr(d).ok()))
+                .map(|(base, prefix)| cidr::range(base, prefix))
+                .collect();
+            !ranges.iter().all(|(start, end)| routes.iter().any(|(r_start, r_end)| r_start <= start && end <= r_end))
+        })
+        .map(|rt| rt["RouteTableId"].as_str().unwrap_or("unknown").to_string())
+        .collect();
+    
+    let status = if missing_in.is_empty() && !in_use.is_empty() {
+        "routed"
+    } else if missing_in.len() < in_use.len() {
+        "partial"
+    } else {
+        "missing"
+    };
+    
+    RouteCoverage {
+        from_vpc: from.to_string(),
+        to_vpc: to.to_string(),
+        destinations,
+        status,
+        missing_in,
+    }
+}
+
+fn get_network_interfaces(region: &str, vpc_filter: Option<&str>, status: Option<&str>) -> Result<Vec<Value>> {
+    let mut filters = Vec::new();
+    if let Some(status) = status {
+        filters.push(format!("Name=status,Values={}", status));
+    }
+    if let Some(vpc) = vpc_filter {
+        filters.push(format!("Name=vpc-id,Values={}", vpc));
+    }
+    
+    let mut args = vec!["ec2".to_string(), "describe-network-interfaces".to_string(), "--region".to_string(), region.to_string()];
+    if !filters.is_empty() {
+        args.push("--filters".to_string());
+        args.extend(filters);
+    }
+    
+    let output = awscli::run(&args)?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe network interfaces"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["NetworkInterfaces"].as_array().unwrap_or(&vec![]).clone())
+}
+
+#[derive(Debug, Serialize)]
+struct OrphanedEni {
+    id: String,
+    vpc_id: String,
+    subnet_id: String,
+    private_ip: String,
+    interface_type: String,
+    description: String,
+    /// Service that created the ENI (e.g. amazon-elb), if not the account itself
+    requester_id: Option<String>,
+    requester_managed: bool,
+    /// Elastic IP still associated with the detached ENI
+    public_ip: Option<String>,
+}
+
+pub fn find_orphaned_enis(region: &str, vpc_filter: Option<&str>, format: OutputFormat) -> Result<()> {
+    let orphans: Vec<OrphanedEni> = get_network_interfaces(region, vpc_filter, Some("available"))?
+        .iter()
+        .map(|eni| OrphanedEni {
+            id: eni["NetworkInterfaceId"].as_str().unwrap_or("unknown").to_string(),
+            vpc_id: eni["VpcId"].as_str().unwrap_or("unknown").to_string(),
+            subnet_id: eni["SubnetId"].as_str().unwrap_or("unknown").to_string(),
+            private_ip: eni["PrivateIpAddress"].as_str().unwrap_or("N/A").to_string(),
+            interface_type: eni["InterfaceType"].as_str().unwrap_or("interface").to_string(),
+            description: eni["Description"].as_str().unwrap_or("").to_string(),
+            requester_id: eni["RequesterId"].as_str().map(String::from),
+            requester_managed: eni["RequesterManaged"].as_bool().unwrap_or(false),
+            public_ip: eni["Association"]["PublicIp"].as_str().map(String::from),
+        })
+        .collect();
+    
+    if format.is_structured() {
+        return output::print(format, &serde_json::json!({
+            "region": region,
+            "orphaned_enis": orphans,
+            "count": orphans.len(),
+        }));
+    }
+    
+    println!("{}", "🧹 Orphaned Network Interfaces".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", region.yellow());
+    println!();
+    
+    if orphans.is_empty() {
+        println!("{}", "✅ No unattached network interfaces".green().bold());
+        return Ok(());
+    }
+    
+    for eni in &orphans {
+        println!("{} {} - {} - {} ({})", 
+            "🔌".yellow(),
+            eni.id.cyan().bold(),
+            eni.private_ip.green(),
+            eni.subnet_id.bright_black(),
+            eni.vpc_id.bright_black()
         );
         
-        // Get subnets for this VPC
-        let subnets = get_subnets(region, vpc_id)?;
+        if !eni.description.is_empty() {
+            println!("   Description: {}", eni.description);
+        }
         
-        if !subnets.is_empty() {
-            println!("│ {}", "Subnets:".yellow());
-            for subnet in &subnets {
-                let subnet_id = subnet["SubnetId"].as_str().unwrap_or("unknown");
-                let subnet_cidr = subnet["CidrBlock"].as_str().unwrap_or("unknown");
-                let az = subnet["AvailabilityZone"].as_str().unwrap_or("unknown");
-                let available_ips = subnet["AvailableIpAddressCount"].as_u64().unwrap_or(0);
-                
-                println!("│   • {} ({}) - {} - {} IPs available", 
-                    subnet_id.cyan(),
-                    subnet_cidr.green(),
-                    az.bright_black(),
-                    available_ips.to_string().yellow()
-                );
+        let requester = match (&eni.requester_id, eni.requester_managed) {
+            (Some(id), true) => format!("{} (managed by AWS)", id),
+            (Some(id), false) => id.clone(),
+            (None, _) => "this account".to_string(),
+        };
+        println!("   Type: {} - Requester: {}", eni.interface_type.yellow(), requester.bright_black());
+        
+        if let Some(ip) = &eni.public_ip {
+            println!("   {} Elastic IP {} still associated", "💸".red(), ip.red());
+        }
+    }
+    
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Found {} orphaned ENI(s)", orphans.len().to_string().yellow().bold());
+    
+    Ok(())
+}
+
+/// A port range a security group opens to 0.0.0.0/0 or ::/0
+#[derive(Debug, Clone, Serialize)]
+struct OpenPort {
+    /// "tcp", "udp", "all", or a protocol without ports such as "icmp"
+    protocol: String,
+    /// "22", "8000-8100", or "all"; empty for protocols without ports
+    ports: String,
+    /// Risky-port name when the range covers one (SSH, RDP, ...)
+    services: Vec<String>,
+    group_id: String,
+}
+
+/// One public address and what answers on it
+#[derive(Debug, Serialize)]
+struct ExposedResource {
+    public_ip: String,
+    /// Elastic IP allocation, when the address is an EIP rather than auto-assigned
+    allocation_id: Option<String>,
+    /// instance, load balancer, NAT gateway, or the ENI's interface type
+    resource_type: String,
+    resource_id: String,
+    name: Option<String>,
+    network_interface_id: Option<String>,
+    private_ip: Option<String>,
+    vpc_id: Option<String>,
+    security_groups: Vec<String>,
+    open_ports: Vec<OpenPort>,
+    /// Number of ports reachable from anywhere (65535 for all traffic)
+    exposed_port_count: u32,
+}
+
+impl OpenPort {
+    /// The ports opened, as an inclusive range; `None` for protocols without ports
+    fn range(&self) -> Option<(u32, u32)> {
+        match self.ports.split_once('-') {
+            _ if self.ports == "all" => Some((0, 65535)),
+            _ if self.ports.is_empty() => None,
+            Some((from, to)) => Some((from.parse().ok()?, to.parse().ok()?)),
+            None => self.ports.parse().ok().map(|port| (port, port)),
+        }
+    }
+}
+
+impl ExposedResource {
+    fn risky_services(&self) -> usize {
+        self.open_ports.iter().flat_map(|p| &p.services).collect::<HashSet<_>>().len()
+    }
+    
+    fn icon(&self) -> ColoredString {
+        if self.risky_services() > 0 || self.open_ports.iter().any(|p| p.ports == "all") {
+            "🔴".red()
+        } else if !self.open_ports.is_empty() {
+            "🟡".yellow()
+        } else {
+            "🟢".green()
+        }
+    }
+}
+
+/// Ingress each security group opens to 0.0.0.0/0 or ::/0, keyed by group ID,
+/// naming the risky ports from `rules` it covers
+fn world_open_ports(region: &str, rules: &Ruleset) -> Result<BTreeMap<String, Vec<OpenPort>>> {
+    // Not tag-filtered: a resource's groups need not carry the resource's tags
+    let output = awscli::run(&["ec2", "describe-security-groups", "--region", region])?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe security groups"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let mut open = BTreeMap::new();
+    
+    for sg in json["SecurityGroups"].as_array().unwrap_or(&vec![]) {
+        let group_id = sg["GroupId"].as_str().unwrap_or("unknown");
+        let mut ports: Vec<OpenPort> = Vec::new();
+        
+        for rule in sg["IpPermissions"].as_array().unwrap_or(&vec![]) {
+            let open_to_world = rule["IpRanges"].as_array()
+                .is_some_and(|ranges| ranges.iter().any(|r| r["CidrIp"].as_str() == Some("0.0.0.0/0")))
+                || rule["Ipv6Ranges"].as_array()
+                    .is_some_and(|ranges| ranges.iter().any(|r| r["CidrIpv6"].as_str() == Some("::/0")));
+            if !open_to_world {
+                continue;
             }
             
-            // Show route tables
-            println!("│ {}", "Route Tables:".yellow());
-            let route_tables = get_route_tables(region, vpc_id)?;
+            // Only TCP and UDP rules carry ports; for ICMP the fields are type and code
+            let protocol = match rule["IpProtocol"].as_str().unwrap_or("-1") {
+                "-1" => "all",
+                "6" => "tcp",
+                "17" => "udp",
+                protocol => protocol,
+            };
+            let range = match (rule["FromPort"].as_i64(), rule["ToPort"].as_i64()) {
+                _ if protocol == "all" => Some((0, 65535)),
+                (Some(from), Some(to)) if (protocol == "tcp" || protocol == "udp") && from >= 0 => Some((from, to)),
+                _ if protocol == "tcp" || protocol == "udp" => Some((0, 65535)),
+                _ => None,
+            };
+            let services = rules.risky_ports.iter()
+                .filter(|p| range.is_some_and(|(from, to)| from <= p.port && p.port <= to))
+                .filter(|_| protocol == "all" || protocol == "tcp")
+                .map(|p| p.name.clone())
+                .collect();
             
-            // Build a map of subnet -> route table
-            let mut subnet_routes: HashMap<String, String> = HashMap::new();
-            for rt in &route_tables {
-                let rt_id = rt["RouteTableId"].as_str().unwrap_or("unknown").to_string();
-                
-                // Check associations
-                if let Some(assocs) = rt["Associations"].as_array() {
-                    for assoc in assocs {
-                        if let Some(subnet_id) = assoc["SubnetId"].as_str() {
-                            subnet_routes.insert(subnet_id.to_string(), rt_id.clone());
-                        }
-                    }
-                }
+            let port = OpenPort {
+                protocol: protocol.to_string(),
+                ports: match range {
+                    None => String::new(),
+                    Some((0, 65535)) => "all".to_string(),
+                    Some((from, to)) if from == to => from.to_string(),
+                    Some((from, to)) => format!("{}-{}", from, to),
+                },
+                services,
+                group_id: group_id.to_string(),
+            };
+            // Separate rules for the same ports, e.g. one per address family, are listed once
+            if !ports.iter().any(|p| p.protocol == port.protocol && p.ports == port.ports) {
+                ports.push(port);
             }
+        }
+        
+        if !ports.is_empty() {
+            open.insert(group_id.to_string(), ports);
+        }
+    }
+    
+    Ok(open)
+}
+
+/// Distinct ports reachable through `open_ports`, counting overlapping
+/// ranges once and capped at 65535 (all traffic)
+fn exposed_port_count(open_ports: &[OpenPort]) -> u32 {
+    if open_ports.iter().any(|p| p.protocol == "all") {
+        return 65535;
+    }
+    
+    let count = ["tcp", "udp"].iter()
+        .map(|protocol| {
+            let mut ranges: Vec<(u32, u32)> = open_ports.iter()
+                .filter(|p| p.protocol == *protocol)
+                .filter_map(OpenPort::range)
+                .collect();
+            ranges.sort_unstable();
             
-            // Show routes for each route table
-            for rt in &route_tables {
-                let rt_id = rt["RouteTableId"].as_str().unwrap_or("unknown");
-                
-                // Find which subnets use this route table
-                let using_subnets: Vec<&String> = subnet_routes.iter()
-                    .filter(|(_, v)| v.as_str() == rt_id)
-                    .map(|(k, _)| k)
-                    .collect();
-                
-                if !using_subnets.is_empty() {
-                    println!("│   📋 {} (used by {} subnet(s))", 
-                        rt_id.bright_black(),
-                        using_subnets.len().to_string().yellow()
-                    );
-                    
-                    // Show routes
-                    if let Some(routes) = rt["Routes"].as_array() {
-                        for route in routes {
-                            let dest = route["DestinationCidrBlock"].as_str()
-                                .or(route["DestinationPrefixListId"].as_str())
-                                .unwrap_or("unknown");
-                            
-                            let target = route["GatewayId"].as_str()
-                                .or(route["NatGatewayId"].as_str())
-                                .or(route["TransitGatewayId"].as_str())
-                                .or(route["NetworkInterfaceId"].as_str())
-                                .unwrap_or("local");
-                            
-                            let state = route["State"].as_str().unwrap_or("active");
-                            
-                            let icon = if target.starts_with("igw-") {
-                                "🌐"  // Internet Gateway
-                            } else if target.starts_with("nat-") {
-                                "🔀"  // NAT Gateway
-                            } else if target.starts_with("tgw-") {
-                                "🔗"  // Transit Gateway
-                            } else if target == "local" {
-                                "🏠"  // Local
-                            } else {
-                                "→"
-                            };
-                            
-                            println!("│      {} {} → {} ({})", 
-                                icon,
-                                dest.green(),
-                                target.cyan(),
-                                state.bright_black()
-                            );
-                        }
-                    }
+            let mut count = 0;
+            let mut next = 0;
+            for (from, to) in ranges {
+                let from = from.max(next);
+                if from <= to {
+                    count += to - from + 1;
+                    next = to + 1;
                 }
             }
+            count
+        })
+        .sum::<u32>();
+    count.min(65535)
+}
+
+struct ElasticIp {
+    allocation_id: Option<String>,
+    name: Option<String>,
+}
+
+/// Elastic IPs by public address
+fn get_elastic_ips(region: &str) -> Result<HashMap<String, ElasticIp>> {
+    let output = awscli::run(&["ec2", "describe-addresses", "--region", region])?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe addresses"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["Addresses"].as_array().unwrap_or(&vec![]).iter()
+        .filter_map(|address| Some((
+            address["PublicIp"].as_str()?.to_string(),
+            ElasticIp {
+                allocation_id: address["AllocationId"].as_str().map(String::from),
+                name: tag_value(address, "Name").map(String::from),
+            },
+        )))
+        .collect())
+}
+
+/// What an ENI belongs to: (type, ID, name), from its attachment or the
+/// description AWS gives the interfaces it manages
+fn eni_owner(eni: &Value, instance_names: &HashMap<String, Option<String>>) -> (String, String, Option<String>) {
+    let eni_id = eni["NetworkInterfaceId"].as_str().unwrap_or("unknown").to_string();
+    let description = eni["Description"].as_str().unwrap_or("");
+    
+    if let Some(instance_id) = eni["Attachment"]["InstanceId"].as_str() {
+        let name = instance_names.get(instance_id).cloned().flatten();
+        return ("instance".to_string(), instance_id.to_string(), name);
+    }
+    // "ELB app/my-alb/50dc6c495c0c9188", "ELB net/...", or "ELB my-classic-lb"
+    if let Some(lb) = description.strip_prefix("ELB ") {
+        let name = lb.split('/').nth(1).unwrap_or(lb);
+        return ("load balancer".to_string(), name.to_string(), Some(lb.to_string()));
+    }
+    if eni["InterfaceType"].as_str() == Some("nat_gateway") || description.starts_with("Interface for NAT Gateway") {
+        let nat = description.split_whitespace().find(|w| w.starts_with("nat-")).unwrap_or(&eni_id);
+        return ("NAT gateway".to_string(), nat.to_string(), None);
+    }
+    
+    let interface_type = eni["InterfaceType"].as_str().unwrap_or("interface");
+    let kind = if interface_type == "interface" { "ENI" } else { interface_type };
+    (kind.to_string(), eni_id, (!description.is_empty()).then(|| description.to_string()))
+}
+
+pub fn internet_exposure(region: &str, vpc_filter: Option<&str>, rules: &Ruleset, format: OutputFormat) -> Result<()> {
+    let enis = get_network_interfaces(region, vpc_filter, None)?;
+    let elastic_ips = get_elastic_ips(region)?;
+    let open_ports = world_open_ports(region, rules)?;
+    
+    let vpc_arg = vpc_filter.map(|vpc| format!("Name=vpc-id,Values={}", vpc));
+    let filters: Vec<&str> = vpc_arg.as_deref().into_iter().collect();
+    let output = awscli::run(&tagged(&["ec2", "describe-instances", "--region", region], &filters))?;
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe instances"));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let instances: Vec<&Value> = json["Reservations"].as_array().into_iter().flatten()
+        .flat_map(|r| r["Instances"].as_array().into_iter().flatten())
+        .filter(|i| i["State"]["Name"].as_str() != Some("terminated"))
+        .collect();
+    let instance_names: HashMap<String, Option<String>> = instances.iter()
+        .filter_map(|i| Some((i["InstanceId"].as_str()?.to_string(), tag_value(i, "Name").map(String::from))))
+        .collect();
+    
+    let group_ids = |resource: &Value| -> Vec<String> {
+        resource["Groups"].as_array()
+            .or(resource["SecurityGroups"].as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|g| g["GroupId"].as_str().map(String::from))
+            .collect()
+    };
+    let resource = |public_ip: &str, (resource_type, resource_id, name): (String, String, Option<String>)| ExposedResource {
+        public_ip: public_ip.to_string(),
+        allocation_id: None,
+        resource_type,
+        resource_id,
+        name,
+        network_interface_id: None,
+        private_ip: None,
+        vpc_id: None,
+        security_groups: Vec::new(),
+        open_ports: Vec::new(),
+        exposed_port_count: 0,
+    };
+    
+    let mut resources: Vec<ExposedResource> = Vec::new();
+    for eni in &enis {
+        // Secondary private addresses can carry public addresses of their own
+        let mut addresses: Vec<(&str, Option<&str>)> = eni["PrivateIpAddresses"].as_array().into_iter().flatten()
+            .filter_map(|a| Some((a["Association"]["PublicIp"].as_str()?, a["PrivateIpAddress"].as_str())))
+            .collect();
+        if let Some(public_ip) = eni["Association"]["PublicIp"].as_str() {
+            if !addresses.iter().any(|(ip, _)| *ip == public_ip) {
+                addresses.push((public_ip, eni["PrivateIpAddress"].as_str()));
+            }
         }
         
-        // Get instances in this VPC
-        let instances = get_instances(region, vpc_id)?;
-        
-        if !instances.is_empty() {
-            println!("│ {}", "Instances:".yellow());
-            for instance in &instances {
-                let instance_id = instance["InstanceId"].as_str().unwrap_or("unknown");
-                let state = instance["State"]["Name"].as_str().unwrap_or("unknown");
-                let private_ip = instance["PrivateIpAddress"].as_str().unwrap_or("N/A");
-                let instance_type = instance["InstanceType"].as_str().unwrap_or("unknown");
-                
-                let state_icon = match state {
-                    "running" => "✅",
-                    "stopped" => "⏸️",
-                    "terminated" => "❌",
-                    _ => "⚠️",
-                };
-                
-                let name = instance["Tags"]
-                    .as_array()
-                    .and_then(|tags| {
-                        tags.iter()
-                            .find(|t| t["Key"].as_str() == Some("Name"))
-                            .and_then(|t| t["Value"].as_str())
-                    })
-                    .unwrap_or("unnamed");
-                
-                println!("│   {} {} ({}) - {} - {} - {}", 
-                    state_icon,
-                    name.cyan().bold(),
-                    instance_id.bright_black(),
-                    private_ip.green(),
-                    instance_type.yellow(),
-                    state.bright_black()
-                );
+        for (public_ip, private_ip) in addresses {
+            resources.push(ExposedResource {
+                network_interface_id: eni["NetworkInterfaceId"].as_str().map(String::from),
+                private_ip: private_ip.map(String::from),
+                vpc_id: eni["VpcId"].as_str().map(String::from),
+                security_groups: group_ids(eni),
+                ..resource(public_ip, eni_owner(eni, &instance_names))
+            });
+        }
+    }
+    // Instances whose interface wasn't listed (e.g. tag filters that only the instance matches)
+    for instance in &instances {
+        let Some(public_ip) = instance["PublicIpAddress"].as_str() else { continue };
+        if resources.iter().any(|r| r.public_ip == public_ip) {
+            continue;
+        }
+        let id = instance["InstanceId"].as_str().unwrap_or("unknown").to_string();
+        let name = instance_names.get(&id).cloned().flatten();
+        resources.push(ExposedResource {
+            private_ip: instance["PrivateIpAddress"].as_str().map(String::from),
+            vpc_id: instance["VpcId"].as_str().map(String::from),
+            security_groups: group_ids(instance),
+            ..resource(public_ip, ("instance".to_string(), id, name))
+        });
+    }
+    
+    for resource in &mut resources {
+        if let Some(eip) = elastic_ips.get(&resource.public_ip) {
+            resource.allocation_id = eip.allocation_id.clone();
+            resource.name = resource.name.take().or_else(|| eip.name.clone());
+        }
+        // Several of the resource's groups may open the same port; it's listed once
+        for port in resource.security_groups.iter().filter_map(|g| open_ports.get(g)).flatten() {
+            if !resource.open_ports.iter().any(|p| p.protocol == port.protocol && p.ports == port.ports) {
+                resource.open_ports.push(port.clone());
             }
         }
+        resource.exposed_port_count = exposed_port_count(&resource.open_ports);
+    }
+    
+    // Most exposed first: risky services, then how many ports answer
+    resources.sort_by(|a, b| {
+        b.risky_services().cmp(&a.risky_services())
+            .then(b.exposed_port_count.cmp(&a.exposed_port_count))
+            .then(a.public_ip.cmp(&b.public_ip))
+    });
+    let open_count = resources.iter().filter(|r| !r.open_ports.is_empty()).count();
+    
+    if format.is_structured() {
+        return output::print(format, &serde_json::json!({
+            "region": region,
+            "resources": resources,
+            "count": resources.len(),
+            "open_to_internet": open_count,
+        }));
+    }
+    
+    println!("{}", "🌐 Internet Exposure".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", region.yellow());
+    println!();
+    
+    if resources.is_empty() {
+        println!("{}", "✅ No public IPs or Elastic IPs in use".green().bold());
+        return Ok(());
+    }
+    
+    for resource in &resources {
+        let eip = match &resource.allocation_id {
+            Some(allocation) => format!(" (EIP {})", allocation).bright_black().to_string(),
+            None => String::new(),
+        };
+        let name = resource.name.as_deref().map(|n| format!(" ({})", n)).unwrap_or_default();
+        println!("{} {}{} → {} {}{}",
+            resource.icon(),
+            resource.public_ip.cyan().bold(),
+            eip,
+            resource.resource_type,
+            resource.resource_id.yellow(),
+            name
+        );
+        
+        let via: Vec<String> = [resource.network_interface_id.clone(), resource.private_ip.clone(), resource.vpc_id.clone()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !via.is_empty() {
+            println!("   {}", via.join(" - ").bright_black());
+        }
         
-        println!("{}", "└─────────────────────────────────────────────────────────────────┘".bright_black());
-        println!();
+        if resource.open_ports.is_empty() {
+            println!("   {}", "No ingress open to the internet".green());
+            continue;
+        }
+        for port in &resource.open_ports {
+            let label = match port.protocol.as_str() {
+                "all" => "all traffic".to_string(),
+                protocol if port.ports.is_empty() => protocol.to_string(),
+                protocol => format!("{}/{}", port.ports, protocol),
+            };
+            let label = if port.services.is_empty() && port.protocol != "all" {
+                label.yellow()
+            } else {
+                label.red().bold()
+            };
+            let services = if port.services.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", port.services.join(", "))
+            };
+            println!("   Open to the internet: {}{} {}", label, services, format!("via {}", port.group_id).bright_black());
+        }
     }
     
+    println!();
     println!("{}", "═".repeat(70).bright_black());
+    println!("Found {} public address(es), {} with ingress open to the internet",
+        resources.len().to_string().yellow().bold(),
+        open_count.to_string().red().bold());
     
     Ok(())
 }
 
-fn get_vpcs(region: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&["ec2", "describe-vpcs", "--region", region])
-        .output()?;
-    
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("Failed to describe VPCs"));
+#[derive(Debug, Serialize, Deserialize)]
+struct ComplianceIssue {
+    severity: &'static str,
+    /// "security-group", "route-table", "vpc", "instance", or a custom check's own kind
+    resource_type: String,
+    resource_id: String,
+    /// Name tag (or group name), falling back to the ID
+    resource_name: String,
+    /// Set for security group findings only; empty for routes, VPCs, and instances
+    sg_id: String,
+    sg_name: String,
+    rule_type: String,
+    protocol: String,
+    port: String,
+    source: String,
+    description: String,
+    /// WHOIS owner of a public source, with `--whois`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_owner: Option<String>,
+    /// How to fix it, often with the aws command that does
+    #[serde(default)]
+    remediation: String,
+}
+
+/// `--ip-permissions` for exactly one rule's protocol and ports, with `peer`
+/// the `IpRanges=[...]`-style part naming its source or destination
+fn ip_permissions(protocol: &str, from_port: Option<i64>, to_port: Option<i64>, peer: &str) -> String {
+    match (from_port, to_port) {
+        (Some(from), Some(to)) if protocol != "-1" => format!("'IpProtocol={},FromPort={},ToPort={},{}'", protocol, from, to, peer),
+        _ => format!("'IpProtocol={},{}'", protocol, peer),
     }
-    
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    Ok(json["Vpcs"].as_array().unwrap_or(&vec![]).clone())
 }
 
-fn get_subnets(region: &str, vpc_id: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&[
-            "ec2", "describe-subnets",
-            "--region", region,
-            "--filters", &format!("Name=vpc-id,Values={}", vpc_id)
-        ])
-        .output()?;
-    
-    if !output.status.success() {
-        return Ok(vec![]);
+fn cidr_peer(cidr: &str) -> String {
+    if cidr.contains(':') {
+        format!("Ipv6Ranges=[{{CidrIpv6={}}}]", cidr)
+    } else {
+        format!("IpRanges=[{{CidrIp={}}}]", cidr)
     }
-    
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    Ok(json["Subnets"].as_array().unwrap_or(&vec![]).clone())
 }
 
-fn get_instances(region: &str, vpc_id: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&[
-            "ec2", "describe-instances",
-            "--region", region,
-            "--filters", &format!("Name=vpc-id,Values={}", vpc_id)
-        ])
-        .output()?;
+/// The command that deletes one CIDR from an ingress or egress rule
+fn revoke_command(direction: Direction, sg_id: &str, protocol: &str, from_port: Option<i64>, to_port: Option<i64>, cidr: &str) -> String {
+    let operation = match direction {
+        Direction::Ingress => "revoke-security-group-ingress",
+        Direction::Egress => "revoke-security-group-egress",
+    };
+    format!("`aws ec2 {} --group-id {} --ip-permissions {}`", operation, sg_id, ip_permissions(protocol, from_port, to_port, &cidr_peer(cidr)))
+}
+
+/// Findings for one group's ingress and egress rules
+fn check_security_group(issues: &mut Vec<ComplianceIssue>, sg: &Value, rules: &Ruleset) {
+    let sg_id = sg["GroupId"].as_str().unwrap_or("unknown");
+    let sg_name = sg["GroupName"].as_str().unwrap_or("unnamed");
     
-    if !output.status.success() {
-        return Ok(vec![]);
+    for rule in sg["IpPermissions"].as_array().into_iter().flatten() {
+        check_rule_compliance(issues, sg_id, sg_name, rule, rules);
+        check_peered_references(issues, sg, sg_id, sg_name, rule);
     }
     
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    
-    let mut instances = vec![];
-    if let Some(reservations) = json["Reservations"].as_array() {
-        for reservation in reservations {
-            if let Some(insts) = reservation["Instances"].as_array() {
-                instances.extend(insts.clone());
-            }
-        }
+    for rule in sg["IpPermissionsEgress"].as_array().into_iter().flatten() {
+        check_egress_compliance(issues, sg_id, sg_name, rule, rules);
     }
     
-    Ok(instances)
+    check_naming(issues, sg, sg_id, sg_name, &rules.naming);
 }
 
-fn get_transit_gateways(region: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&["ec2", "describe-transit-gateways", "--region", region])
-        .output()?;
-    
-    if !output.status.success() {
-        return Ok(vec![]);
+/// MEDIUM findings for ingress that trusts a group in another VPC (over
+/// peering) on every protocol or every port. Narrow cross-VPC references are
+/// left to `sec-groups`, which labels them.
+fn check_peered_references(issues: &mut Vec<ComplianceIssue>, sg: &Value, sg_id: &str, sg_name: &str, rule: &Value) {
+    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
+    let from_port = rule["FromPort"].as_i64();
+    let to_port = rule["ToPort"].as_i64();
+    let every_port = protocol == "-1"
+        || (matches!(protocol, "tcp" | "udp" | "6" | "17")
+            && from_port.is_none_or(|from| from <= 0)
+            && to_port.is_none_or(|to| to >= 65535));
+    if !every_port {
+        return;
     }
     
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    Ok(json["TransitGateways"].as_array().unwrap_or(&vec![]).clone())
-}
-
-fn get_tgw_attachments(region: &str, tgw_id: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&[
-            "ec2", "describe-transit-gateway-attachments",
-            "--region", region,
-            "--filters", &format!("Name=transit-gateway-id,Values={}", tgw_id)
-        ])
-        .output()?;
-    
-    if !output.status.success() {
-        return Ok(vec![]);
+    for pair in rule["UserIdGroupPairs"].as_array().into_iter().flatten() {
+        let peer_vpc = pair["VpcId"].as_str().filter(|vpc| sg["VpcId"].as_str() != Some(*vpc));
+        let pcx = pair["VpcPeeringConnectionId"].as_str();
+        if peer_vpc.is_none() && pcx.is_none() {
+            continue;
+        }
+        
+        let group = pair["GroupId"].as_str().unwrap_or("unknown");
+        let what = if protocol == "-1" { "All traffic" } else { "Every port" };
+        issues.push(ComplianceIssue {
+            severity: Severity::Medium.as_str(),
+            resource_type: "security-group".to_string(),
+            resource_id: sg_id.to_string(),
+            resource_name: sg_name.to_string(),
+            sg_id: sg_id.to_string(),
+            sg_name: sg_name.to_string(),
+            rule_type: "Peering".to_string(),
+            protocol: compliance_protocol_label(protocol),
+            port: compliance_port_label(protocol, from_port, to_port),
+            source: group.to_string(),
+            description: format!("{} allowed from group {} in {} via {}",
+                what, group, peer_vpc.unwrap_or("a peered VPC"), pcx.unwrap_or("VPC peering")),
+            source_owner: None,
+            remediation: format!("Allow only the ports the peer uses, then revoke the broad rule: `aws ec2 revoke-security-group-ingress --group-id {} --ip-permissions {}`",
+                sg_id, ip_permissions(protocol, from_port, to_port, &format!("UserIdGroupPairs=[{{GroupId={}}}]", group))),
+        });
     }
-    
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    Ok(json["TransitGatewayAttachments"].as_array().unwrap_or(&vec![]).clone())
 }
 
-fn get_route_tables(region: &str, vpc_id: &str) -> Result<Vec<Value>> {
-    let output = Command::new("aws")
-        .args(&[
-            "ec2", "describe-route-tables",
-            "--region", region,
-            "--filters", &format!("Name=vpc-id,Values={}", vpc_id)
-        ])
-        .output()?;
+/// LOW findings for a group name outside the naming convention and for each
+/// required tag the group lacks; `source` carries the offending name
+fn check_naming(issues: &mut Vec<ComplianceIssue>, sg: &Value, sg_id: &str, sg_name: &str, naming: &rules::Naming) {
+    let finding = |description: String, remediation: String| ComplianceIssue {
+        severity: Severity::Low.as_str(),
+        resource_type: "security-group".to_string(),
+        resource_id: sg_id.to_string(),
+        resource_name: sg_name.to_string(),
+        sg_id: sg_id.to_string(),
+        sg_name: sg_name.to_string(),
+        rule_type: "Governance".to_string(),
+        protocol: String::new(),
+        port: String::new(),
+        source: sg_name.to_string(),
+        description,
+        source_owner: None,
+        remediation,
+    };
     
-    if !output.status.success() {
-        return Ok(vec![]);
+    // Validated when the ruleset was loaded; every VPC's `default` group is named by AWS
+    let pattern = naming.group_name_pattern.as_deref()
+        .and_then(|source| Regex::new(source).ok().map(|pattern| (source, pattern)));
+    if let Some((source, pattern)) = pattern {
+        if sg_name != "default" && !pattern.is_match(sg_name) {
+            issues.push(finding(
+                format!("Group name doesn't match the naming convention {}", source),
+                "Group names can't be changed: create a group with a conforming name and the same rules, move its network interfaces over, then delete this one".to_string(),
+            ));
+        }
     }
     
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    Ok(json["RouteTables"].as_array().unwrap_or(&vec![]).clone())
+    for key in &naming.required_tags {
+        if tag_value(sg, key).is_none() {
+            issues.push(finding(
+                format!("Missing required tag '{}'", key),
+                format!("`aws ec2 create-tags --resources {} --tags Key={},Value=<value>`", sg_id, key),
+            ));
+        }
+    }
 }
 
-pub fn export_dot(region: &str) -> Result<()> {
-    println!("{}", "📊 Exporting to DOT format...".cyan().bold());
+fn check_rule_compliance(
+    issues: &mut Vec<ComplianceIssue>,
+    sg_id: &str,
+    sg_name: &str,
+    rule: &Value,
+    rules: &Ruleset,
+) {
+    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
+    let from_port = rule["FromPort"].as_i64();
+    let to_port = rule["ToPort"].as_i64();
     
-    let mut dot = String::from("digraph AWS {\n");
-    dot.push_str("  rankdir=LR;\n");
-    dot.push_str("  node [shape=box, style=rounded];\n\n");
+    for ip_range in rule["Ipv6Ranges"].as_array().into_iter().flatten() {
+        let cidr = ip_range["CidrIpv6"].as_str().unwrap_or("unknown");
+        let Ok((base, prefix)) = cidr::parse_cidr_v6(cidr) else { continue };
+        
+        if prefix == 0 {
+            check_internet_exposure(issues, sg_id, sg_name, protocol, from_port, to_port, cidr, rules);
+        } else if prefix <= rules.broad_prefix_v6 {
+            let internal = cidr::is_unique_local_v6(base, prefix);
+            check_broad_cidr(issues, sg_id, sg_name, protocol, from_port, to_port, (cidr, internal), &rules.risky_ports);
+        }
+    }
     
-    let vpcs = get_vpcs(region)?;
+    let Some(ip_ranges) = rule["IpRanges"].as_array() else { return };
     
-    for vpc in &vpcs {
-        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
-        let cidr = vpc["CidrBlock"].as_str().unwrap_or("unknown");
-        
-        // VPC node
-        dot.push_str(&format!("  \"{}\" [label=\"VPC\\n{}\", color=blue, penwidth=2];\n", 
-            vpc_id, cidr));
+    for ip_range in ip_ranges {
+        let cidr = ip_range["CidrIp"].as_str().unwrap_or("unknown");
+        let Ok((base, prefix)) = cidr::parse_cidr(cidr) else { continue };
         
-        // Subnets
-        let subnets = get_subnets(region, vpc_id)?;
-        for subnet in &subnets {
-            let subnet_id = subnet["SubnetId"].as_str().unwrap_or("unknown");
-            let subnet_cidr = subnet["CidrBlock"].as_str().unwrap_or("unknown");
-            
-            dot.push_str(&format!("  \"{}\" [label=\"Subnet\\n{}\", color=green];\n", 
-                subnet_id, subnet_cidr));
-            dot.push_str(&format!("  \"{}\" -> \"{}\";\n", vpc_id, subnet_id));
+        if prefix == 0 {
+            check_internet_exposure(issues, sg_id, sg_name, protocol, from_port, to_port, cidr, rules);
+            continue;
         }
         
-        // Instances
-        let instances = get_instances(region, vpc_id)?;
-        for instance in &instances {
-            let instance_id = instance["InstanceId"].as_str().unwrap_or("unknown");
-            let private_ip = instance["PrivateIpAddress"].as_str().unwrap_or("N/A");
-            let state = instance["State"]["Name"].as_str().unwrap_or("unknown");
-            
-            let name = instance["Tags"]
-                .as_array()
-                .and_then(|tags| {
-                    tags.iter()
-                        .find(|t| t["Key"].as_str() == Some("Name"))
-                        .and_then(|t| t["Value"].as_str())
-                })
-                .unwrap_or("unnamed");
-            
-            let color = if state == "running" { "green" } else { "red" };
-            
-            dot.push_str(&format!("  \"{}\" [label=\"{}\\n{}\\n{}\", color={}, shape=ellipse];\n", 
-                instance_id, name, instance_id, private_ip, color));
-            
-            // Connect to subnet
-            if let Some(subnet_id) = instance["SubnetId"].as_str() {
-                dot.push_str(&format!("  \"{}\" -> \"{}\";\n", subnet_id, instance_id));
-            }
-        }
+        let port = compliance_port_label(protocol, from_port, to_port);
         
-        // Route tables
-        let route_tables = get_route_tables(region, vpc_id)?;
-        for rt in &route_tables {
-            if let Some(routes) = rt["Routes"].as_array() {
-                for route in routes {
-                    let dest = route["DestinationCidrBlock"].as_str().unwrap_or("unknown");
-                    
-                    if let Some(tgw_id) = route["TransitGatewayId"].as_str() {
-                        dot.push_str(&format!("  \"{}\" [label=\"TGW\\n{}\", color=purple, shape=diamond];\n", 
-                            tgw_id, tgw_id));
-                        dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"{}\"];\n", 
-                            vpc_id, tgw_id, dest));
-                    } else if let Some(igw_id) = route["GatewayId"].as_str() {
-                        if igw_id.starts_with("igw-") {
-                            dot.push_str(&format!("  \"{}\" [label=\"IGW\\n{}\", color=orange, shape=diamond];\n", 
-                                igw_id, igw_id));
-                            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"{}\"];\n", 
-                                vpc_id, igw_id, dest));
-                        }
-                    }
-                }
-            }
+        // A very broad public block already gets a HIGH finding for the whole
+        // rule, so the per-port broad-CIDR findings would only repeat it
+        if prefix <= rules.broad_public_prefix && !cidr::is_private(base, prefix) {
+            issues.push(ComplianceIssue {
+                severity: "HIGH",
+                resource_type: "security-group".to_string(),
+                resource_id: sg_id.to_string(),
+                resource_name: sg_name.to_string(),
+                sg_id: sg_id.to_string(),
+                sg_name: sg_name.to_string(),
+                rule_type: "Ingress".to_string(),
+                protocol: compliance_protocol_label(protocol),
+                port: port.clone(),
+                source: cidr.to_string(),
+                description: format!("Ingress from a very broad public block ({} addresses)", 1u64 << (32 - u32::from(prefix))),
+                source_owner: None,
+                remediation: format!("Replace the source with the specific addresses that need access, then revoke: {}",
+                    revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
+            });
+        } else if prefix <= rules.broad_prefix {
+            let internal = cidr::is_private(base, prefix);
+            check_broad_cidr(issues, sg_id, sg_name, protocol, from_port, to_port, (cidr, internal), &rules.risky_ports);
         }
         
-        dot.push_str("\n");
+        check_watched_sources(issues, sg_id, sg_name, Direction::Ingress, (protocol, from_port, to_port), &port, (base, prefix), cidr, rules);
     }
+}
+
+fn check_egress_compliance(
+    issues: &mut Vec<ComplianceIssue>,
+    sg_id: &str,
+    sg_name: &str,
+    rule: &Value,
+    rules: &Ruleset,
+) {
+    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
+    let (from_port, to_port) = (rule["FromPort"].as_i64(), rule["ToPort"].as_i64());
+    let port = compliance_port_label(protocol, from_port, to_port);
     
-    dot.push_str("}\n");
-    
-    // Write to file
-    let filename = format!("aws-topology-{}.dot", region);
-    let mut file = File::create(&filename)?;
-    file.write_all(dot.as_bytes())?;
-    
-    println!("{} Exported to: {}", "✅".green(), filename.cyan().bold());
-    println!("View at: {}", "https://dreampuf.github.io/GraphvizOnline/".yellow());
+    let Some(ip_ranges) = rule["IpRanges"].as_array() else { return };
     
-    Ok(())
+    for ip_range in ip_ranges {
+        let cidr = ip_range["CidrIp"].as_str().unwrap_or("unknown");
+        
+        // Every security group starts with allow-all egress, so only rules that
+        // single out a watched range are worth reporting
+        if cidr == "0.0.0.0/0" {
+            continue;
+        }
+        
+        let Ok(network) = cidr::parse_cidr(cidr) else { continue };
+        check_watched_sources(issues, sg_id, sg_name, Direction::Egress, (protocol, from_port, to_port), &port, network, cidr, rules);
+    }
 }
 
-pub fn analyze_security_groups(region: &str, vpc_filter: Option<&str>) -> Result<()> {
-    println!("{}", "🔒 Analyzing Security Groups...".cyan().bold());
-    println!("{}", "═".repeat(70).bright_black());
-    println!("Region: {}", region.yellow());
-    if let Some(vpc) = vpc_filter {
-        println!("VPC Filter: {}", vpc.yellow());
+/// One finding per watched range in `direction` that holds the rule's CIDR.
+/// Rules broader than the range (egress to 128.0.0.0/1, which covers it in
+/// passing) don't single it out, so they are left to the broad-CIDR checks.
+#[allow(clippy::too_many_arguments)]
+fn check_watched_sources(
+    issues: &mut Vec<ComplianceIssue>,
+    sg_id: &str,
+    sg_name: &str,
+    direction: Direction,
+    (protocol, from_port, to_port): (&str, Option<i64>, Option<i64>),
+    port: &str,
+    (base, prefix): (u32, u8),
+    cidr: &str,
+    rules: &Ruleset,
+) {
+    for watched in rules.watched_sources.iter().filter(|w| w.direction == direction) {
+        let Ok((watched_base, watched_prefix)) = cidr::parse_cidr(&watched.cidr) else { continue };
+        
+        if prefix >= watched_prefix && cidr::network(base, watched_prefix) == cidr::network(watched_base, watched_prefix) {
+            issues.push(ComplianceIssue {
+                severity: watched.severity.as_str(),
+                resource_type: "security-group".to_string(),
+                resource_id: sg_id.to_string(),
+                resource_name: sg_name.to_string(),
+                sg_id: sg_id.to_string(),
+                sg_name: sg_name.to_string(),
+                rule_type: match direction {
+                    Direction::Ingress => "Ingress".to_string(),
+                    Direction::Egress => "Egress".to_string(),
+                },
+                protocol: compliance_protocol_label(protocol),
+                port: port.to_string(),
+                source: cidr.to_string(),
+                description: format!("{} ({})", watched.description, watched.cidr),
+                source_owner: None,
+                remediation: format!("If traffic with {} isn't intended, revoke the rule: {}",
+                    watched.cidr, revoke_command(direction, sg_id, protocol, from_port, to_port, cidr)),
+            });
+        }
     }
-    println!();
+}
+
+/// Routes whose target no longer exists, reported against their route table
+fn check_blackhole_routes(issues: &mut Vec<ComplianceIssue>, rt: &Value) {
+    let Some(routes) = rt["Routes"].as_array() else { return };
     
-    let mut args = vec!["ec2", "describe-security-groups", "--region", region];
-    let filter_arg;
-    if let Some(vpc) = vpc_filter {
-        filter_arg = format!("Name=vpc-id,Values={}", vpc);
-        args.extend(&["--filters", &filter_arg]);
+    for route in routes.iter().filter(|r| r["State"].as_str() == Some("blackhole")) {
+        let destination = route_destination(route);
+        let destination_flag = if destination.starts_with("pl-") {
+            "--destination-prefix-list-id"
+        } else if destination.contains(':') {
+            "--destination-ipv6-cidr-block"
+        } else {
+            "--destination-cidr-block"
+        };
+        let route_table = rt["RouteTableId"].as_str().unwrap_or("unknown");
+        issues.push(ComplianceIssue {
+            severity: "MEDIUM",
+            resource_type: "route-table".to_string(),
+            resource_id: route_table.to_string(),
+            resource_name: tag_value(rt, "Name").unwrap_or(route_table).to_string(),
+            sg_id: String::new(),
+            sg_name: String::new(),
+            rule_type: "Route".to_string(),
+            protocol: String::new(),
+            port: String::new(),
+            source: destination.to_string(),
+            description: format!("Blackhole route via {}: traffic to this destination is dropped", route_target(route)),
+            source_owner: None,
+            remediation: format!("Point the route at a live target with `aws ec2 replace-route`, or remove it: `aws ec2 delete-route --route-table-id {} {} {}`",
+                route_table, destination_flag, destination),
+        });
     }
-    
-    let output = Command::new("aws").args(&args).output()?;
-    
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("Failed to describe security groups"));
+}
+
+/// VPCs without an active flow log, and flow logs that are failing to deliver.
+/// A VPC-level log covers every subnet and ENI in it, so only VPCs are checked.
+fn check_flow_logs(issues: &mut Vec<ComplianceIssue>, region: &str, vpc_filter: Option<&str>, vpcs: &[Value]) -> Result<()> {
+    let output = awscli::run(&["ec2", "describe-flow-logs", "--region", region])?;
+    if !output.success {
+        return Err(anyhow::anyhow!("Failed to describe flow logs"));
     }
-    
     let json: Value = serde_json::from_slice(&output.stdout)?;
-    let empty_vec = vec![];
-    let sgs = json["SecurityGroups"].as_array().unwrap_or(&empty_vec);
+    let flow_logs = json["FlowLogs"].as_array().cloned().unwrap_or_default();
     
-    for sg in sgs {
-        let sg_id = sg["GroupId"].as_str().unwrap_or("unknown");
-        let sg_name = sg["GroupName"].as_str().unwrap_or("unnamed");
-        let vpc_id = sg["VpcId"].as_str().unwrap_or("N/A");
-        
-        println!("{}", "┌─────────────────────────────────────────────────────────────────┐".bright_black());
-        println!("│ 🛡️  {} ({})", sg_name.cyan().bold(), sg_id.bright_black());
-        println!("│ VPC: {}", vpc_id.green());
+    for vpc in vpcs.iter().filter(|v| vpc_selected(vpc_filter, v["VpcId"].as_str().unwrap_or_default())) {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        let finding = |description: String, remediation: String| ComplianceIssue {
+            severity: Severity::Medium.as_str(),
+            resource_type: "vpc".to_string(),
+            resource_id: vpc_id.to_string(),
+            resource_name: tag_value(vpc, "Name").unwrap_or(vpc_id).to_string(),
+            sg_id: String::new(),
+            sg_name: String::new(),
+            rule_type: "Logging".to_string(),
+            protocol: String::new(),
+            port: String::new(),
+            source: vpc["CidrBlock"].as_str().unwrap_or_default().to_string(),
+            description,
+            source_owner: None,
+            remediation,
+        };
         
-        // Ingress rules
-        if let Some(ingress) = sg["IpPermissions"].as_array() {
-            if !ingress.is_empty() {
-                println!("│");
-                println!("│ {} Inbound Rules:", "⬇️".green());
-                
-                for rule in ingress {
-                    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
-                    let from_port = rule["FromPort"].as_i64();
-                    let to_port = rule["ToPort"].as_i64();
-                    
-                    let port_str = match (from_port, to_port) {
-                        (Some(f), Some(t)) if f == t => format!(":{}", f),
-                        (Some(f), Some(t)) => format!(":{}−{}", f, t),
-                        _ => String::from(":ALL"),
-                    };
-                    
-                    let proto = if protocol == "-1" { "ALL" } else { protocol };
-                    
-                    // IP ranges
-                    if let Some(ip_ranges) = rule["IpRanges"].as_array() {
-                        for ip_range in ip_ranges {
-                            let cidr = ip_range["CidrIp"].as_str().unwrap_or("unknown");
-                            let desc = ip_range["Description"].as_str().unwrap_or("");
-                            
-                            println!("│   • {} {} from {} {}", 
-                                proto.yellow(),
-                                port_str.cyan(),
-                                cidr.green(),
-                                if !desc.is_empty() { format!("({})", desc) } else { String::new() }.bright_black()
-                            );
-                        }
-                    }
-                    
-                    // Security group sources
-                    if let Some(sg_pairs) = rule["UserIdGroupPairs"].as_array() {
-                        for pair in sg_pairs {
-                            let source_sg = pair["GroupId"].as_str().unwrap_or("unknown");
-                            println!("│   • {} {} from SG {}", 
-                                proto.yellow(),
-                                port_str.cyan(),
-                                source_sg.green()
-                            );
-                        }
-                    }
-                }
-            }
+        let logs: Vec<&Value> = flow_logs.iter()
+            .filter(|log| log["ResourceId"].as_str() == Some(vpc_id))
+            .filter(|log| log["FlowLogStatus"].as_str().is_none_or(|status| status == "ACTIVE"))
+            .collect();
+        if logs.is_empty() {
+            issues.push(finding(
+                "Flow logs not enabled".to_string(),
+                format!("`aws ec2 create-flow-logs --resource-type VPC --resource-ids {} --traffic-type ALL` with a CloudWatch Logs or S3 destination", vpc_id),
+            ));
+            continue;
         }
         
-        // Egress rules
-        if let Some(egress) = sg["IpPermissionsEgress"].as_array() {
-            if !egress.is_empty() {
-                println!("│");
-                println!("│ {} Outbound Rules:", "⬆️".red());
-                
-                for rule in egress {
-                    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
-                    let from_port = rule["FromPort"].as_i64();
-                    let to_port = rule["ToPort"].as_i64();
-                    
-                    let port_str = match (from_port, to_port) {
-                        (Some(f), Some(t)) if f == t => format!(":{}", f),
-                        (Some(f), Some(t)) => format!(":{}−{}", f, t),
-                        _ => String::from(":ALL"),
-                    };
-                    
-                    let proto = if protocol == "-1" { "ALL" } else { protocol };
-                    
-                    if let Some(ip_ranges) = rule["IpRanges"].as_array() {
-                        for ip_range in ip_ranges {
-                            let cidr = ip_range["CidrIp"].as_str().unwrap_or("unknown");
-                            println!("│   • {} {} to {}", 
-                                proto.yellow(),
-                                port_str.cyan(),
-                                cidr.green()
-                            );
-                        }
-                    }
-                }
-            }
+        // Logs still count as enabled, but nothing is being recorded
+        for log in logs.iter().filter(|log| log["DeliverLogsStatus"].as_str() == Some("FAILED")) {
+            let reason = log["DeliverLogsErrorMessage"].as_str().unwrap_or("delivery failed");
+            issues.push(finding(
+                format!("Flow log {} is not delivering ({})", log["FlowLogId"].as_str().unwrap_or("unknown"), reason),
+                "Check that the log's IAM role can write to CloudWatch Logs, or that the S3 bucket policy allows delivery.logs.amazonaws.com".to_string(),
+            ));
         }
-        
-        println!("{}", "└─────────────────────────────────────────────────────────────────┘".bright_black());
-        println!();
     }
     
-    println!("{}", "═".repeat(70).bright_black());
-    println!("Total: {} security group(s)", sgs.len().to_string().green().bold());
-    
     Ok(())
 }
 
-pub fn diff_vpcs(region: &str, vpc1_id: &str, vpc2_id: &str) -> Result<()> {
-    println!("{}", "🔍 Comparing VPCs...".cyan().bold());
-    println!("{}", "═".repeat(70).bright_black());
-    
-    // Get VPC info
-    let vpcs = get_vpcs(region)?;
-    let vpc1 = vpcs.iter().find(|v| v["VpcId"].as_str() == Some(vpc1_id));
-    let vpc2 = vpcs.iter().find(|v| v["VpcId"].as_str() == Some(vpc2_id));
-    
-    if vpc1.is_none() || vpc2.is_none() {
-        return Err(anyhow::anyhow!("One or both VPCs not found"));
-    }
-    
-    let vpc1 = vpc1.unwrap();
-    let vpc2 = vpc2.unwrap();
-    
-    println!("VPC 1: {} ({})", 
-        vpc1_id.cyan().bold(), 
-        vpc1["CidrBlock"].as_str().unwrap_or("unknown").green()
-    );
-    println!("VPC 2: {} ({})", 
-        vpc2_id.cyan().bold(), 
-        vpc2["CidrBlock"].as_str().unwrap_or("unknown").green()
-    );
-    println!();
-    
-    // Compare subnets
-    let subnets1 = get_subnets(region, vpc1_id)?;
-    let subnets2 = get_subnets(region, vpc2_id)?;
-    
-    let cidrs1: HashSet<String> = subnets1.iter()
-        .filter_map(|s| s["CidrBlock"].as_str().map(String::from))
-        .collect();
-    let cidrs2: HashSet<String> = subnets2.iter()
-        .filter_map(|s| s["CidrBlock"].as_str().map(String::from))
-        .collect();
-    
-    println!("{}", "📊 Subnets:".yellow().bold());
-    println!("  VPC 1: {} subnet(s)", subnets1.len().to_string().cyan());
-    println!("  VPC 2: {} subnet(s)", subnets2.len().to_string().cyan());
+/// A finding printed by a custom check: a `ComplianceIssue` in which only
+/// `severity`, `description`, and `sg_id` or `resource_id` are required
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomFinding {
+    severity: String,
+    #[serde(default)]
+    resource_type: Option<String>,
+    #[serde(default)]
+    resource_id: Option<String>,
+    #[serde(default)]
+    resource_name: Option<String>,
+    #[serde(default)]
+    sg_id: String,
+    #[serde(default)]
+    sg_name: String,
+    #[serde(default)]
+    rule_type: Option<String>,
+    #[serde(default)]
+    protocol: String,
+    #[serde(default)]
+    port: String,
+    #[serde(default)]
+    source: String,
+    description: String,
+    #[serde(default)]
+    source_owner: Option<String>,
+    #[serde(default)]
+    remediation: String,
+}
+
+/// Pipe `{"region", "vpc_filter", "security_groups"}` to each of the ruleset's
+/// custom check commands and merge the findings they print as a JSON array
+fn check_custom(issues: &mut Vec<ComplianceIssue>, commands: &[String], region: &str, vpc_filter: Option<&str>, sgs: &[Value]) -> Result<()> {
+    if commands.is_empty() {
+        return Ok(());
+    }
     
-    let only_vpc1: Vec<_> = cidrs1.difference(&cidrs2).collect();
-    let only_vpc2: Vec<_> = cidrs2.difference(&cidrs1).collect();
+    let input = serde_json::to_vec(&serde_json::json!({
+        "region": region,
+        "vpc_filter": vpc_filter,
+        "security_groups": sgs,
+    }))?;
     
-    if !only_vpc1.is_empty() {
-        println!("  {} Only in VPC 1: {}", "−".red(), only_vpc1.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").red());
-    }
-    if !only_vpc2.is_empty() {
-        println!("  {} Only in VPC 2: {}", "+".green(), only_vpc2.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").green());
+    for command in commands {
+        for finding in run_custom_check(command, &input)? {
+            let severity = Severity::parse(&finding.severity.to_ascii_uppercase()).ok_or_else(|| anyhow::anyhow!(
+                "Custom check {} reported severity {:?}; expected CRITICAL, HIGH, MEDIUM, or LOW", command, finding.severity))?;
+            let sg_name = if finding.sg_name.is_empty() { finding.sg_id.clone() } else { finding.sg_name };
+            let resource_id = match finding.resource_id {
+                Some(id) => id,
+                None if !finding.sg_id.is_empty() => finding.sg_id.clone(),
+                None => return Err(anyhow::anyhow!("Custom check {} reported a finding without sg_id or resource_id", command)),
+            };
+            let resource_type = finding.resource_type.unwrap_or_else(|| {
+                if finding.sg_id.is_empty() { "resource".to_string() } else { "security-group".to_string() }
+            });
+            issues.push(ComplianceIssue {
+                severity: severity.as_str(),
+                resource_name: finding.resource_name
+                    .or_else(|| (!sg_name.is_empty()).then(|| sg_name.clone()))
+                    .unwrap_or_else(|| resource_id.clone()),
+                resource_type,
+                resource_id,
+                sg_name,
+                sg_id: finding.sg_id,
+                rule_type: finding.rule_type.unwrap_or_else(|| "Custom".to_string()),
+                protocol: finding.protocol,
+                port: finding.port,
+                source: finding.source,
+                description: finding.description,
+                source_owner: finding.source_owner,
+                remediation: finding.remediation,
+            });
+        }
     }
-    println!();
     
-    // Compare instances
-    let instances1 = get_instances(region, vpc1_id)?;
-    let instances2 = get_instances(region, vpc2_id)?;
-    
-    let running1 = instances1.iter().filter(|i| i["State"]["Name"].as_str() == Some("running")).count();
-    let running2 = instances2.iter().filter(|i| i["State"]["Name"].as_str() == Some("running")).count();
+    Ok(())
+}
+
+/// Run one check command (split on whitespace into program and arguments).
+/// Its stderr passes through; a non-zero exit fails the compliance run rather
+/// than quietly reporting fewer findings.
+fn run_custom_check(command: &str, input: &[u8]) -> Result<Vec<CustomFinding>> {
+    use std::process::{Command, Stdio};
     
-    println!("{}", "💻 Instances:".yellow().bold());
-    println!("  VPC 1: {} total, {} running", 
-        instances1.len().to_string().cyan(),
-        running1.to_string().green()
-    );
-    println!("  VPC 2: {} total, {} running", 
-        instances2.len().to_string().cyan(),
-        running2.to_string().green()
-    );
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or_else(|| anyhow::anyhow!("Custom check command is empty"))?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Cannot run custom check {}: {}", command, e))?;
     
-    let types1: HashSet<String> = instances1.iter()
-        .filter_map(|i| i["InstanceType"].as_str().map(String::from))
-        .collect();
-    let types2: HashSet<String> = instances2.iter()
-        .filter_map(|i| i["InstanceType"].as_str().map(String::from))
-        .collect();
+    // Feed stdin from another thread so a check that prints before reading everything can't deadlock
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || {
+        // A check is free to ignore its input
+        let _ = stdin.write_all(&input);
+    });
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
     
-    if !types1.is_empty() || !types2.is_empty() {
-        println!("  Instance types:");
-        println!("    VPC 1: {}", types1.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").bright_black());
-        println!("    VPC 2: {}", types2.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").bright_black());
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Custom check {} failed ({})", command, output.status));
     }
-    println!();
     
-    // Compare route tables
-    let rts1 = get_route_tables(region, vpc1_id)?;
-    let rts2 = get_route_tables(region, vpc2_id)?;
-    
-    println!("{}", "🗺️  Route Tables:".yellow().bold());
-    println!("  VPC 1: {} route table(s)", rts1.len().to_string().cyan());
-    println!("  VPC 2: {} route table(s)", rts2.len().to_string().cyan());
-    
-    // Extract unique route destinations
-    let mut dests1 = HashSet::new();
-    let mut dests2 = HashSet::new();
-    
-    for rt in &rts1 {
-        if let Some(routes) = rt["Routes"].as_array() {
-            for route in routes {
-                if let Some(dest) = route["DestinationCidrBlock"].as_str() {
-                    if dest != "local" {
-                        dests1.insert(dest.to_string());
-                    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&stdout)
+        .map_err(|e| anyhow::anyhow!("Custom check {} printed invalid findings: {}", command, e))
+}
+
+/// Ports that mark an instance as a database when its security groups accept them
+const DATABASE_PORTS: &[(i64, &str)] = &[
+    (3306, "MySQL"),
+    (5432, "PostgreSQL"),
+    (1433, "MSSQL"),
+    (1521, "Oracle"),
+    (27017, "MongoDB"),
+    (6379, "Redis"),
+    (9200, "Elasticsearch"),
+];
+
+/// Name-tag words that mark an instance as a database
+const DATABASE_NAME_HINTS: &[&str] = &["db", "database", "mysql", "postgres", "postgresql", "mongo", "mongodb", "redis"];
+
+/// Database-like instances (by the ports their groups accept or their Name tag)
+/// placed in a subnet that routes to an internet gateway
+fn check_public_databases(
+    issues: &mut Vec<ComplianceIssue>,
+    region: &str,
+    vpc_filter: Option<&str>,
+    sgs: &[Value],
+    vpcs: &[Value],
+) -> Result<()> {
+    let mut database_services: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for sg in sgs {
+        let group_id = sg["GroupId"].as_str().unwrap_or("unknown");
+        for rule in sg["IpPermissions"].as_array().unwrap_or(&vec![]) {
+            let (Some(from), Some(to)) = (rule["FromPort"].as_i64(), rule["ToPort"].as_i64()) else { continue };
+            // A whole-range rule says nothing about what the instance runs
+            if from == 0 && to == 65535 {
+                continue;
+            }
+            for (_, service) in DATABASE_PORTS.iter().filter(|(port, _)| from <= *port && *port <= to) {
+                let services = database_services.entry(group_id).or_default();
+                if !services.contains(service) {
+                    services.push(service);
                 }
             }
         }
     }
     
-    for rt in &rts2 {
-        if let Some(routes) = rt["Routes"].as_array() {
-            for route in routes {
-                if let Some(dest) = route["DestinationCidrBlock"].as_str() {
-                    if dest != "local" {
-                        dests2.insert(dest.to_string());
-                    }
+    for vpc in vpcs {
+        let vpc_id = vpc["VpcId"].as_str().unwrap_or("unknown");
+        if !vpc_selected(vpc_filter, vpc_id) {
+            continue;
+        }
+        
+        let info = gather_vpc(region, vpc, vpc_id)?;
+        let public_subnets: HashSet<&str> = info.subnets.iter()
+            .filter(|s| s.reachability == Reachability::Public)
+            .map(|s| s.id.as_str())
+            .collect();
+        
+        for instance in info.instances.iter().filter(|i| i.state != "terminated") {
+            let Some(subnet_id) = instance.subnet_id.as_deref().filter(|s| public_subnets.contains(s)) else { continue };
+            
+            let mut services: Vec<&str> = Vec::new();
+            for service in instance.security_groups.iter().filter_map(|g| database_services.get(g.as_str())).flatten() {
+                if !services.contains(service) {
+                    services.push(service);
                 }
             }
+            
+            let named_like_db = instance.name.to_lowercase()
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|word| DATABASE_NAME_HINTS.contains(&word));
+            
+            let evidence = if !services.is_empty() {
+                format!("accepts {}", services.join(", "))
+            } else if named_like_db {
+                format!("named '{}'", instance.name)
+            } else {
+                continue;
+            };
+            
+            issues.push(ComplianceIssue {
+                severity: "HIGH",
+                resource_type: "instance".to_string(),
+                resource_id: instance.id.clone(),
+                resource_name: instance.name.clone(),
+                sg_id: String::new(),
+                sg_name: String::new(),
+                rule_type: "Placement".to_string(),
+                protocol: String::new(),
+                port: String::new(),
+                source: subnet_id.to_string(),
+                description: format!("Database instance in public subnet ({})", evidence),
+                source_owner: None,
+                remediation: "Move it to a private subnet (no internet gateway route), e.g. from an AMI of it, and reach it through SSM Session Manager, a bastion, or a VPN".to_string(),
+            });
         }
     }
     
-    let only_rt1: Vec<_> = dests1.difference(&dests2).collect();
-    let only_rt2: Vec<_> = dests2.difference(&dests1).collect();
-    
-    if !only_rt1.is_empty() {
-        println!("  {} Routes only in VPC 1: {}", "−".red(), only_rt1.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").red());
-    }
-    if !only_rt2.is_empty() {
-        println!("  {} Routes only in VPC 2: {}", "+".green(), only_rt2.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").green());
-    }
-    
-    let common: Vec<_> = dests1.intersection(&dests2).collect();
-    if !common.is_empty() {
-        println!("  {} Common routes: {}", "=".yellow(), common.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ").bright_black());
-    }
-    
-    println!();
-    println!("{}", "═".repeat(70).bright_black());
-    
     Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ComplianceIssue {
-    severity: &'static str,
-    sg_id: String,
-    sg_name: String,
-    rule_type: String,
-    protocol: String,
-    port: String,
-    source: String,
-    description: String,
+fn compliance_protocol_label(protocol: &str) -> String {
+    if protocol == "-1" { "ALL".to_string() } else { protocol.to_string() }
 }
 
-fn check_rule_compliance(
-    issues: &mut Vec<ComplianceIssue>,
-    sg_id: &str,
-    sg_name: &str,
-    rule: &Value,
-    risky_ports: &[(i64, &str)],
-) {
-    let protocol = rule["IpProtocol"].as_str().unwrap_or("-1");
-    let from_port = rule["FromPort"].as_i64();
-    let to_port = rule["ToPort"].as_i64();
-    
-    let Some(ip_ranges) = rule["IpRanges"].as_array() else { return };
-    
-    for ip_range in ip_ranges {
-        let cidr = ip_range["CidrIp"].as_str().unwrap_or("unknown");
-        
-        if cidr == "0.0.0.0/0" {
-            check_internet_exposure(issues, sg_id, sg_name, protocol, from_port, to_port, cidr, risky_ports);
-        } else if cidr.ends_with("/8") || cidr.ends_with("/16") {
-            check_broad_cidr(issues, sg_id, sg_name, protocol, from_port, to_port, cidr, risky_ports);
-        }
+fn compliance_port_label(protocol: &str, from_port: Option<i64>, to_port: Option<i64>) -> String {
+    match (from_port, to_port) {
+        _ if protocol == "-1" => "ALL".to_string(),
+        (Some(f), Some(t)) if f == t => f.to_string(),
+        (Some(f), Some(t)) => format!("{}-{}", f, t),
+        _ => "ALL".to_string(),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn check_internet_exposure(
     issues: &mut Vec<ComplianceIssue>,
     sg_id: &str,
@@ -714,11 +4650,14 @@ fn check_internet_exposure(
     from_port: Option<i64>,
     to_port: Option<i64>,
     cidr: &str,
-    risky_ports: &[(i64, &str)],
+    rules: &Ruleset,
 ) {
     if protocol == "-1" {
         issues.push(ComplianceIssue {
             severity: "CRITICAL",
+            resource_type: "security-group".to_string(),
+            resource_id: sg_id.to_string(),
+            resource_name: sg_name.to_string(),
             sg_id: sg_id.to_string(),
             sg_name: sg_name.to_string(),
             rule_type: "Ingress".to_string(),
@@ -726,16 +4665,52 @@ fn check_internet_exposure(
             port: "ALL".to_string(),
             source: cidr.to_string(),
             description: "All traffic allowed from internet".to_string(),
+            source_owner: None,
+            remediation: format!("Allow only the ports the service needs from known sources, then revoke: {}",
+                revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
         });
         return;
     }
     
     let (Some(from), Some(to)) = (from_port, to_port) else { return };
     
-    for (port, service) in risky_ports {
+    // Wide ranges are usually "open the ephemeral ports" shortcuts, and expose
+    // whatever listens there whether or not it's on the risky-ports list. One
+    // finding covers the rule, naming the risky ports inside rather than
+    // repeating them as findings of their own.
+    let span = to - from + 1;
+    if span > i64::from(rules.wide_port_range) {
+        let services: Vec<&str> = rules.risky_ports.iter()
+            .filter(|p| from <= p.port && p.port <= to)
+            .map(|p| p.name.as_str())
+            .collect();
+        let including = if services.is_empty() { String::new() } else { format!(", including {}", services.join(", ")) };
+        issues.push(ComplianceIssue {
+            severity: "HIGH",
+            resource_type: "security-group".to_string(),
+            resource_id: sg_id.to_string(),
+            resource_name: sg_name.to_string(),
+            sg_id: sg_id.to_string(),
+            sg_name: sg_name.to_string(),
+            rule_type: "Ingress".to_string(),
+            protocol: compliance_protocol_label(protocol),
+            port: format!("{}-{}", from, to),
+            source: cidr.to_string(),
+            description: format!("{} ports ({}-{}) open to internet{}", span, from, to, including),
+            source_owner: None,
+            remediation: format!("Open only the ports the service listens on; return traffic needs no inbound rule since security groups are stateful. Then revoke: {}",
+                revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
+        });
+        return;
+    }
+    
+    for RiskyPort { port, name: service } in &rules.risky_ports {
         if from <= *port && *port <= to {
             issues.push(ComplianceIssue {
                 severity: "HIGH",
+                resource_type: "security-group".to_string(),
+                resource_id: sg_id.to_string(),
+                resource_name: sg_name.to_string(),
                 sg_id: sg_id.to_string(),
                 sg_name: sg_name.to_string(),
                 rule_type: "Ingress".to_string(),
@@ -743,11 +4718,16 @@ fn check_internet_exposure(
                 port: format!("{} ({})", port, service),
                 source: cidr.to_string(),
                 description: format!("{} exposed to internet", service),
+                source_owner: None,
+                remediation: format!("Restrict the source to your office or VPN CIDR (or use SSM Session Manager or a bastion), then revoke: {}",
+                    revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
             });
         }
     }
 }
 
+/// `(cidr, internal)`: the source, and whether it is private (RFC 1918 or IPv6 unique local) space
+#[allow(clippy::too_many_arguments)]
 fn check_broad_cidr(
     issues: &mut Vec<ComplianceIssue>,
     sg_id: &str,
@@ -755,15 +4735,43 @@ fn check_broad_cidr(
     protocol: &str,
     from_port: Option<i64>,
     to_port: Option<i64>,
-    cidr: &str,
-    risky_ports: &[(i64, &str)],
+    (cidr, internal): (&str, bool),
+    risky_ports: &[RiskyPort],
 ) {
+    // All traffic from a broad range is a lateral-movement path on every port at once
+    if protocol == "-1" {
+        issues.push(ComplianceIssue {
+            severity: "HIGH",
+            resource_type: "security-group".to_string(),
+            resource_id: sg_id.to_string(),
+            resource_name: sg_name.to_string(),
+            sg_id: sg_id.to_string(),
+            sg_name: sg_name.to_string(),
+            rule_type: "Ingress".to_string(),
+            protocol: "ALL".to_string(),
+            port: "ALL".to_string(),
+            source: cidr.to_string(),
+            description: if internal {
+                "All traffic allowed from large internal CIDR".to_string()
+            } else {
+                "All traffic allowed from large CIDR block".to_string()
+            },
+            source_owner: None,
+            remediation: format!("Narrow the source to the subnets that need access and allow only their ports, then revoke: {}",
+                revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
+        });
+        return;
+    }
+    
     let (Some(from), Some(to)) = (from_port, to_port) else { return };
     
-    for (port, service) in risky_ports {
+    for RiskyPort { port, name: service } in risky_ports {
         if from <= *port && *port <= to {
             issues.push(ComplianceIssue {
                 severity: "MEDIUM",
+                resource_type: "security-group".to_string(),
+                resource_id: sg_id.to_string(),
+                resource_name: sg_name.to_string(),
                 sg_id: sg_id.to_string(),
                 sg_name: sg_name.to_string(),
                 rule_type: "Ingress".to_string(),
@@ -771,216 +4779,802 @@ fn check_broad_cidr(
                 port: format!("{} ({})", port, service),
                 source: cidr.to_string(),
                 description: format!("{} exposed to large CIDR block", service),
+                source_owner: None,
+                remediation: format!("Narrow the source to the hosts that need {}, then revoke: {}",
+                    service, revoke_command(Direction::Ingress, sg_id, protocol, from_port, to_port, cidr)),
             });
         }
     }
 }
 
-pub fn check_compliance(region: &str, vpc_filter: Option<&str>, json_output: bool, strict: bool) -> Result<i32> {
-    if !json_output {
-        println!("{}", "Security Compliance Check".cyan().bold());
-        println!("{}", "═".repeat(70).bright_black());
-        println!("Region: {}", region.yellow());
-        if let Some(vpc) = vpc_filter {
-            println!("VPC Filter: {}", vpc.yellow());
+#[derive(Debug, Serialize)]
+struct ComplianceReport {
+    region: String,
+    vpc_filter: Option<String>,
+    total_issues: usize,
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+    /// 100 minus the ruleset's per-severity weights for each finding
+    score: u32,
+    grade: &'static str,
+    issues: Vec<ComplianceIssue>,
+}
+
+impl ComplianceReport {
+    fn worst_severity(&self) -> Option<Severity> {
+        self.issues.iter().filter_map(|issue| Severity::parse(issue.severity)).min()
+    }
+}
+
+fn run_compliance(region: &str, vpc_filter: Option<&str>, rules: &Ruleset) -> Result<ComplianceReport> {
+    let started = Instant::now();
+    let sgs = get_security_groups(region, vpc_filter)?;
+    timing::record(started, || format!("fetched {} security group(s) in {}", sgs.len(), region));
+    
+    let started = Instant::now();
+    let mut issues: Vec<ComplianceIssue> = Vec::new();
+    
+    for sg in &sgs {
+        check_security_group(&mut issues, sg, rules);
+    }
+    
+    timing::record(started, || format!("compliance analysis in {}", region));
+    
+    let started = Instant::now();
+    for rt in get_route_tables(region, vpc_filter)? {
+        check_blackhole_routes(&mut issues, &rt);
+    }
+    timing::record(started, || format!("route table checks in {}", region));
+    
+    let vpcs = get_vpcs(region)?;
+    report_vpcs(region, vpcs.iter().filter(|v| vpc_selected(vpc_filter, v["VpcId"].as_str().unwrap_or_default())));
+    
+    let started = Instant::now();
+    check_public_databases(&mut issues, region, vpc_filter, &sgs, &vpcs)?;
+    timing::record(started, || format!("database placement checks in {}", region));
+    
+    let started = Instant::now();
+    check_flow_logs(&mut issues, region, vpc_filter, &vpcs)?;
+    timing::record(started, || format!("flow log checks in {}", region));
+    
+    let started = Instant::now();
+    check_custom(&mut issues, &rules.custom_checks, region, vpc_filter, &sgs)?;
+    timing::record(started, || format!("custom checks in {}", region));
+    
+    // Sort by severity
+    issues.sort_by(|a, b| {
+        let severity_order = |s: &str| match s {
+            "CRITICAL" => 0,
+            "HIGH" => 1,
+            "MEDIUM" => 2,
+            "LOW" => 3,
+            _ => 4,
+        };
+        severity_order(a.severity).cmp(&severity_order(b.severity))
+    });
+    
+    for issue in &issues {
+        let mut data = serde_json::to_value(issue)?;
+        data["region"] = Value::from(region);
+        events::emit("compliance_issue", &data);
+    }
+    
+    let critical = issues.iter().filter(|i| i.severity == "CRITICAL").count();
+    let high = issues.iter().filter(|i| i.severity == "HIGH").count();
+    let medium = issues.iter().filter(|i| i.severity == "MEDIUM").count();
+    let low = issues.iter().filter(|i| i.severity == "LOW").count();
+    let score = rules.score_weights.score(critical, high, medium, low);
+    
+    Ok(ComplianceReport {
+        region: region.to_string(),
+        vpc_filter: vpc_filter.map(String::from),
+        total_issues: issues.len(),
+        critical,
+        high,
+        medium,
+        low,
+        score,
+        grade: rules::grade(score),
+        issues,
+    })
+}
+
+/// Returns the worst severity found, for the `--strict` exit code
+#[allow(clippy::too_many_arguments)]
+pub fn check_compliance(region: &str, vpc_filter: Option<&str>, rules: &Ruleset, whois: bool, group_by: Option<GroupBy>, upload: Option<&str>, format: OutputFormat, textfile: Option<&Path>) -> Result<Option<Severity>> {
+    let mut report = run_compliance(region, vpc_filter, rules)?;
+    if whois {
+        annotate_source_owners(&mut report.issues);
+    }
+    
+    if format == OutputFormat::Prometheus {
+        compliance_metrics(std::slice::from_ref(&report)).write(textfile)?;
+    } else if format.is_structured() {
+        output::print(format, &report)?;
+    } else {
+        print_compliance_report(&report, group_by);
+    }
+    
+    if let Some(uri) = upload {
+        upload_report(uri, region, &report, format)?;
+    }
+    
+    Ok(report.worst_severity())
+}
+
+/// Finding counts per region and severity, for alerting on and trending posture
+fn compliance_metrics(reports: &[ComplianceReport]) -> output::Metrics {
+    let mut metrics = output::Metrics::default();
+    let scanned_at = Utc::now().timestamp().to_string();
+    
+    for report in reports {
+        let mut labels = vec![("region", report.region.as_str())];
+        if let Some(vpc) = &report.vpc_filter {
+            labels.push(("vpc", vpc));
+        }
+        
+        for (severity, count) in [("critical", report.critical), ("high", report.high), ("medium", report.medium), ("low", report.low)] {
+            let mut labels = labels.clone();
+            labels.insert(0, ("severity", severity));
+            metrics.gauge("netkit_compliance_issues", "Security group and VPC compliance findings", &labels, count);
+        }
+        metrics.gauge("netkit_compliance_score", "Posture score, 100 minus weighted findings", &labels, report.score);
+        metrics.gauge("netkit_compliance_last_run_timestamp_seconds", "Unix time of the last compliance scan", &labels, &scanned_at);
+    }
+    
+    metrics
+}
+
+/// Attach the WHOIS owner to findings whose source is a public address block.
+/// Lookups are best-effort; sources that can't be resolved stay unannotated.
+fn annotate_source_owners(issues: &mut [ComplianceIssue]) {
+    let started = Instant::now();
+    let mut owners: BTreeMap<String, Option<String>> = BTreeMap::new();
+    
+    for issue in issues.iter_mut() {
+        let Ok((base, prefix)) = cidr::parse_cidr(&issue.source) else { continue };
+        // 0.0.0.0/0 belongs to everyone; private and link-local ranges to no one
+        let link_local = cidr::network(base, 16) == 0xA9FE_0000;
+        if prefix == 0 || cidr::is_private(base, prefix) || link_local {
+            continue;
+        }
+        
+        let owner = owners.entry(issue.source.clone()).or_insert_with(|| {
+            whois::lookup_asn(std::net::IpAddr::V4(std::net::Ipv4Addr::from(base)))
+                .ok()
+                .and_then(|info| info.owner_label())
+        });
+        issue.source_owner = owner.clone();
+    }
+    timing::record(started, || format!("whois lookups for {} source(s)", owners.len()));
+}
+
+/// Copy a JSON report to `s3://bucket/prefix/compliance-<region>-<timestamp>.json`
+fn upload_report<T: Serialize>(prefix: &str, region: &str, report: &T, format: OutputFormat) -> Result<()> {
+    if !prefix.starts_with("s3://") {
+        return Err(anyhow::anyhow!("--upload expects an s3:// URI, got {}", prefix));
+    }
+    
+    let name = artifact::file_name("compliance", region, "json");
+    let uri = format!("{}/{}", prefix.trim_end_matches('/'), name);
+    
+    let local = std::env::temp_dir().join(&name);
+    std::fs::write(&local, serde_json::to_string_pretty(report)?)?;
+    
+    let local_arg = local.to_string_lossy();
+    let result = awscli::run(&["s3", "cp", &local_arg, &uri, "--content-type", "application/json"]);
+    let _ = std::fs::remove_file(&local);
+    let output = result?;
+    
+    if !output.success {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("AccessDenied") || stderr.contains("Forbidden") {
+            return Err(anyhow::anyhow!("Access denied uploading to {} (needs s3:PutObject on the bucket)", uri));
+        }
+        if stderr.contains("NoSuchBucket") {
+            return Err(anyhow::anyhow!("Bucket in {} does not exist", uri));
+        }
+        return Err(anyhow::anyhow!("Failed to upload report to {}: {}", uri, stderr.trim()));
+    }
+    
+    if awscli::dry_run() {
+        if format.is_structured() {
+            tracing::info!("Skipped upload to {} (--dry-run)", uri);
+        } else {
+            println!("{} Skipped upload to {} (--dry-run)", "☁️ ".yellow(), uri.cyan());
+        }
+        return Ok(());
+    }
+    
+    // Keep stdout clean for structured output
+    if format.is_structured() {
+        tracing::info!("Uploaded report to {}", uri);
+    } else {
+        println!("{} Uploaded report to {}", "☁️ ".green(), uri.cyan());
+    }
+    
+    Ok(())
+}
+
+fn print_compliance_report(report: &ComplianceReport, group_by: Option<GroupBy>) {
+    println!("{}", "Security Compliance Check".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", report.region.yellow());
+    if let Some(vpc) = &report.vpc_filter {
+        println!("VPC Filter: {}", vpc.yellow());
+    }
+    println!();
+    
+    if report.issues.is_empty() {
+        println!("{}", "No compliance issues found".green().bold());
+    } else if group_by == Some(GroupBy::Source) {
+        println!("Found {} issue(s):\n", report.issues.len().to_string().red().bold());
+        print_issues_by_source(&report.issues);
+    } else {
+        println!("Found {} issue(s):\n", report.issues.len().to_string().red().bold());
+        
+        // PEER is the source for ingress, the destination for egress, the subnet
+        // for placement findings, and the group itself for governance ones
+        let mut table = Table::new(&["SEVERITY", "RESOURCE", "RESOURCE ID", "TYPE", "PROTOCOL", "PORT", "PEER", "ISSUE"]);
+        for issue in &report.issues {
+            let peer = match &issue.source_owner {
+                Some(owner) => format!("{} {}", issue.source.red(), format!("({})", owner).bright_black()),
+                None => issue.source.red().to_string(),
+            };
+            
+            table.row(vec![
+                severity_color(issue.severity).to_string(),
+                issue.resource_name.cyan().to_string(),
+                issue.resource_id.bright_black().to_string(),
+                issue.rule_type.clone(),
+                issue.protocol.clone(),
+                issue.port.yellow().to_string(),
+                peer,
+                issue.description.bright_black().to_string(),
+            ]);
+            note_remediation(&mut table, issue);
+        }
+        table.print();
+        println!();
+    }
+    
+    println!("{}", "═".repeat(70).bright_black());
+    
+    // Summary
+    println!("Summary: {} critical, {} high, {} medium, {} low", 
+        report.critical.to_string().red().bold(),
+        report.high.to_string().yellow().bold(),
+        report.medium.to_string().bright_yellow(),
+        report.low.to_string().bright_blue()
+    );
+    println!("Score: {}/100 (grade {})", report.score.to_string().bold(), grade_color(report.grade));
+}
+
+/// One table per source with the same columns minus PEER. IPv4 and IPv6
+/// sources are keyed by their network address, so `10.0.0.5/16` and
+/// `10.0.0.0/16` land together; security group and subnet peers follow.
+fn print_issues_by_source(issues: &[ComplianceIssue]) {
+    let mut groups: BTreeMap<(bool, SourceKey), Vec<&ComplianceIssue>> = BTreeMap::new();
+    for issue in issues {
+        let key = source_key(&issue.source);
+        groups.entry((key.prefix != Some(0), key)).or_default().push(issue);
+    }
+    
+    for ((_, key), mut issues) in groups {
+        issues.sort_by(|a, b| (&a.resource_name, &a.resource_id, Severity::parse(a.severity))
+            .cmp(&(&b.resource_name, &b.resource_id, Severity::parse(b.severity))));
+        let resource_count = issues.iter().map(|i| &i.resource_id).collect::<BTreeSet<_>>().len();
+        
+        let internet = if key.prefix == Some(0) { " open to the internet".red().bold().to_string() } else { String::new() };
+        let owner = issues.iter()
+            .find_map(|i| i.source_owner.as_deref())
+            .map(|owner| format!(" ({})", owner).bright_black().to_string())
+            .unwrap_or_default();
+        println!("{} {}{}{}: {} issue(s) in {} resource(s)",
+            "▸".cyan(), key.label.red().bold(), owner, internet, issues.len(), resource_count);
+        
+        let mut table = Table::new(&["SEVERITY", "RESOURCE", "RESOURCE ID", "TYPE", "PROTOCOL", "PORT", "ISSUE"]);
+        for issue in issues {
+            table.row(vec![
+                severity_color(issue.severity).to_string(),
+                issue.resource_name.cyan().to_string(),
+                issue.resource_id.bright_black().to_string(),
+                issue.rule_type.clone(),
+                issue.protocol.clone(),
+                issue.port.yellow().to_string(),
+                issue.description.bright_black().to_string(),
+            ]);
+            note_remediation(&mut table, issue);
+        }
+        table.print();
+        println!();
+    }
+}
+
+/// "Fix: ..." under the finding's row
+fn note_remediation(table: &mut Table, issue: &ComplianceIssue) {
+    if !issue.remediation.is_empty() {
+        table.note(format!("{} {}", "Fix:".green().bold(), issue.remediation.green()));
+    }
+}
+
+/// Sort order for `--group-by source`: the internet (`0.0.0.0/0`, `::/0`),
+/// then IPv4 and IPv6 CIDRs from broadest to narrowest, then other peers by name
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SourceKey {
+    is_peer: bool,
+    is_v6: bool,
+    prefix: Option<u8>,
+    address: u128,
+    label: String,
+}
+
+fn source_key(source: &str) -> SourceKey {
+    if let Ok((base, prefix)) = cidr::parse_cidr(source) {
+        let network = cidr::network(base, prefix);
+        return SourceKey {
+            is_peer: false,
+            prefix: Some(prefix),
+            is_v6: false,
+            address: u128::from(network),
+            label: format!("{}/{}", cidr::format_ip(network), prefix),
+        };
+    }
+    if let Ok((base, prefix)) = cidr::parse_cidr_v6(source) {
+        let network = base & u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+        return SourceKey {
+            is_peer: false,
+            prefix: Some(prefix),
+            is_v6: true,
+            address: network,
+            label: format!("{}/{}", std::net::Ipv6Addr::from(network), prefix),
+        };
+    }
+    SourceKey { is_peer: true, prefix: None, is_v6: false, address: 0, label: source.to_string() }
+}
+
+fn severity_color(severity: &str) -> ColoredString {
+    match severity {
+        "CRITICAL" => severity.red().bold(),
+        "HIGH" => severity.yellow().bold(),
+        "MEDIUM" => severity.bright_yellow(),
+        "LOW" => severity.bright_blue(),
+        _ => severity.normal(),
+    }
+}
+
+fn grade_color(grade: &str) -> ColoredString {
+    match grade {
+        "A" => grade.green().bold(),
+        "B" => grade.green(),
+        "C" => grade.yellow(),
+        "D" => grade.yellow().bold(),
+        _ => grade.red().bold(),
+    }
+}
+
+/// Fallback for the `--all-regions` scans when `describe-regions` is unavailable
+const REGIONS: &[&str] = &[
+    "us-east-1", "us-east-2", "us-west-1", "us-west-2",
+    "eu-west-1", "eu-west-2", "eu-central-1",
+    "ap-southeast-1", "ap-southeast-2", "ap-northeast-1",
+];
+
+/// Every public region as of this release, so the common case is checked
+/// without a round-trip; anything else is looked up with `describe-regions`
+const KNOWN_REGIONS: &[&str] = &[
+    "us-east-1", "us-east-2", "us-west-1", "us-west-2", "ca-central-1", "ca-west-1", "mx-central-1", "sa-east-1",
+    "eu-west-1", "eu-west-2", "eu-west-3", "eu-central-1", "eu-central-2", "eu-north-1", "eu-south-1", "eu-south-2",
+    "af-south-1", "il-central-1", "me-south-1", "me-central-1",
+    "ap-east-1", "ap-east-2", "ap-south-1", "ap-south-2", "ap-northeast-1", "ap-northeast-2", "ap-northeast-3",
+    "ap-southeast-1", "ap-southeast-2", "ap-southeast-3", "ap-southeast-4", "ap-southeast-5", "ap-southeast-6",
+    "ap-southeast-7", "us-gov-east-1", "us-gov-west-1", "cn-north-1", "cn-northwest-1",
+];
+
+/// Catch region typos before the first call, which would otherwise fail with
+/// an endpoint error that doesn't mention the region at all
+pub fn validate_region(region: &str) -> Result<()> {
+    if KNOWN_REGIONS.contains(&region) {
+        return Ok(());
+    }
+    
+    // A region launched after this list, or one only this partition has
+    let listed: Vec<String> = awscli::run(&["ec2", "describe-regions", "--all-regions"])
+        .ok()
+        .filter(|output| output.success)
+        .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+        .and_then(|json| json["Regions"].as_array().map(|regions| regions.iter()
+            .filter_map(|r| r["RegionName"].as_str().map(String::from))
+            .collect()))
+        .unwrap_or_default();
+    if listed.iter().any(|r| r == region) {
+        return Ok(());
+    }
+    
+    let suggestion = KNOWN_REGIONS.iter().copied()
+        .chain(listed.iter().map(String::as_str))
+        .map(|known| (edit_distance(region, known), known))
+        .min()
+        .filter(|(distance, _)| *distance <= 3);
+    match suggestion {
+        Some((_, known)) => Err(anyhow::anyhow!("Unknown region '{}', did you mean '{}'?", region, known)),
+        None => Err(anyhow::anyhow!("Unknown region '{}' (expected a name like us-east-1)", region)),
+    }
+}
+
+/// Levenshtein distance: single-character insertions, deletions, and substitutions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != *cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Regions enabled for the account, which includes opted-in regions and skips disabled ones
+fn enabled_regions() -> Vec<String> {
+    let discovered = awscli::run(&["ec2", "describe-regions"])
+        .ok()
+        .filter(|output| output.success)
+        .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+        .and_then(|json| json["Regions"].as_array().map(|regions| regions.iter()
+            .filter_map(|r| r["RegionName"].as_str().map(String::from))
+            .collect::<Vec<_>>()))
+        .filter(|regions| !regions.is_empty());
+    
+    match discovered {
+        Some(mut regions) => {
+            regions.sort();
+            regions
         }
-        println!();
+        None => REGIONS.iter().map(|r| r.to_string()).collect(),
     }
+}
+
+/// Set by the first Ctrl-C during a multi-region compliance scan
+static SCAN_INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// True when a multi-region compliance scan was cut short with Ctrl-C;
+/// its results only cover the regions finished before that
+pub fn scan_interrupted() -> bool {
+    SCAN_INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Ctrl-C stops the scan after the region in flight and reports what was
+/// gathered so far; a second Ctrl-C exits immediately. Returns the worst
+/// severity across regions.
+pub fn check_compliance_all_regions(vpc_filter: Option<&str>, rules: &Ruleset, whois: bool, group_by: Option<GroupBy>, upload: Option<&str>, format: OutputFormat, textfile: Option<&Path>) -> Result<Option<Severity>> {
+    use std::io::IsTerminal;
     
-    let mut args = vec!["ec2", "describe-security-groups", "--region", region];
-    let filter_arg;
-    if let Some(vpc) = vpc_filter {
-        filter_arg = format!("Name=vpc-id,Values={}", vpc);
-        args.extend(&["--filters", &filter_arg]);
-    }
+    let regions = enabled_regions();
+    let human = !format.is_structured();
+    // Progress goes to stderr for structured output, and only when someone is watching
+    let progress = human || std::io::stderr().is_terminal();
     
-    let output = Command::new("aws").args(&args).output()?;
+    ctrlc::set_handler(|| {
+        if SCAN_INTERRUPTED.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        tracing::warn!("\nInterrupted; finishing with the regions scanned so far (Ctrl-C again to quit now)");
+    })?;
     
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("Failed to describe security groups"));
+    if human {
+        println!("{}", "Multi-Region Compliance Scan".cyan().bold());
+        println!("{}", "═".repeat(70).bright_black());
+        println!("Scanning {} regions...\n", regions.len());
     }
     
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    let empty_vec = vec![];
-    let sgs = json["SecurityGroups"].as_array().unwrap_or(&empty_vec);
-    
-    let mut issues: Vec<ComplianceIssue> = Vec::new();
-    
-    // Risky ports to check
-    let risky_ports = vec![
-        (22, "SSH"),
-        (3389, "RDP"),
-        (3306, "MySQL"),
-        (5432, "PostgreSQL"),
-        (1433, "MSSQL"),
-        (27017, "MongoDB"),
-        (6379, "Redis"),
-        (9200, "Elasticsearch"),
-    ];
+    let mut worst: Option<Severity> = None;
+    let mut total_issues = 0;
+    let mut reports: Vec<ComplianceReport> = Vec::new();
+    let mut scanned = 0;
     
-    for sg in sgs {
-        let sg_id = sg["GroupId"].as_str().unwrap_or("unknown").to_string();
-        let sg_name = sg["GroupName"].as_str().unwrap_or("unnamed").to_string();
+    for (done, region) in regions.iter().enumerate() {
+        if scan_interrupted() {
+            break;
+        }
+        if human {
+            println!("{} Scanning {}...", "→".cyan(), region.yellow());
+        }
+        
+        let result = run_compliance(region, vpc_filter, rules);
+        // The aws calls in flight got the signal too, so this region's results are incomplete
+        if scan_interrupted() {
+            break;
+        }
+        scanned += 1;
+        
+        match result {
+            Ok(mut report) => {
+                if whois {
+                    annotate_source_owners(&mut report.issues);
+                }
+                if let Some(severity) = report.worst_severity() {
+                    worst = Some(worst.map_or(severity, |worst| worst.min(severity)));
+                }
+                if report.critical + report.high > 0 {
+                    total_issues += 1;
+                }
+                if human {
+                    print_compliance_report(&report, group_by);
+                } else if format == OutputFormat::Ndjson {
+                    output::print(format, &report)?;
+                }
+                reports.push(report);
+            }
+            Err(e) => {
+                if human {
+                    println!("  {} Error: {}", "✗".red(), e);
+                } else {
+                    tracing::error!("Error scanning {}: {}", region, e);
+                }
+            }
+        }
         
-        if let Some(ingress) = sg["IpPermissions"].as_array() {
-            for rule in ingress {
-                check_rule_compliance(&mut issues, &sg_id, &sg_name, rule, &risky_ports);
+        if progress {
+            let count = |severity: fn(&ComplianceReport) -> usize| reports.iter().map(severity).sum::<usize>();
+            let line = format!("[{}/{}] {} issue(s) so far: {} critical, {} high, {} medium, {} low",
+                done + 1, regions.len(), count(|r| r.total_issues),
+                count(|r| r.critical), count(|r| r.high), count(|r| r.medium), count(|r| r.low));
+            if human {
+                println!("{}", line.bright_black());
+            } else {
+                tracing::info!("{}", line);
             }
         }
+        
+        if human {
+            println!();
+        }
     }
     
-    // Sort by severity
-    issues.sort_by(|a, b| {
-        let severity_order = |s: &str| match s {
-            "CRITICAL" => 0,
-            "HIGH" => 1,
-            "MEDIUM" => 2,
-            _ => 3,
-        };
-        severity_order(a.severity).cmp(&severity_order(b.severity))
+    let interrupted = scan_interrupted() && scanned < regions.len();
+    
+    // Scored over every finding at once, so the account-wide number isn't an average of regions
+    let count = |severity: fn(&ComplianceReport) -> usize| reports.iter().map(severity).sum::<usize>();
+    let score = rules.score_weights.score(count(|r| r.critical), count(|r| r.high), count(|r| r.medium), count(|r| r.low));
+    
+    let combined = serde_json::json!({
+        "regions": reports,
+        "regions_with_issues": total_issues,
+        "total_issues": reports.iter().map(|r| r.total_issues).sum::<usize>(),
+        "score": score,
+        "grade": rules::grade(score),
+        "interrupted": interrupted,
     });
     
-    // Display issues
-    if json_output {
-        let output = serde_json::json!({
-            "region": region,
-            "vpc_filter": vpc_filter,
-            "total_issues": issues.len(),
-            "critical": issues.iter().filter(|i| i.severity == "CRITICAL").count(),
-            "high": issues.iter().filter(|i| i.severity == "HIGH").count(),
-            "medium": issues.iter().filter(|i| i.severity == "MEDIUM").count(),
-            "issues": issues,
-        });
-        println!("{}", serde_json::to_string_pretty(&output)?);
-    } else {
-        if issues.is_empty() {
-            println!("{}", "No compliance issues found".green().bold());
-        } else {
-            println!("Found {} issue(s):\n", issues.len().to_string().red().bold());
-            
-            for issue in &issues {
-                let severity_color = match issue.severity {
-                    "CRITICAL" => issue.severity.red().bold(),
-                    "HIGH" => issue.severity.yellow().bold(),
-                    "MEDIUM" => issue.severity.bright_yellow(),
-                    _ => issue.severity.normal(),
-                };
-                
-                println!("[{}] {} ({})", severity_color, issue.sg_name.cyan(), issue.sg_id.bright_black());
-                println!("  Type: {}", issue.rule_type);
-                println!("  Protocol: {} Port: {}", issue.protocol, issue.port.yellow());
-                println!("  Source: {}", issue.source.red());
-                println!("  Issue: {}", issue.description.bright_black());
-                println!();
-            }
-        }
-        
+    if human {
         println!("{}", "═".repeat(70).bright_black());
-        
-        // Summary
-        let critical = issues.iter().filter(|i| i.severity == "CRITICAL").count();
-        let high = issues.iter().filter(|i| i.severity == "HIGH").count();
-        let medium = issues.iter().filter(|i| i.severity == "MEDIUM").count();
-        
-        println!("Summary: {} critical, {} high, {} medium", 
-            critical.to_string().red().bold(),
-            high.to_string().yellow().bold(),
-            medium.to_string().bright_yellow()
-        );
-        
-        // Return exit code
-        let exit_code = if critical > 0 {
-            2
-        } else if high > 0 {
-            1
-        } else {
-            0
-        };
-        
-        return Ok(exit_code);
+        if interrupted {
+            println!("{} Interrupted: results cover {} of {} region(s)", "⚠️ ".yellow(),
+                scanned.to_string().yellow().bold(), regions.len());
+        }
+        println!("Scan {}: {} region(s) with issues", if interrupted { "stopped" } else { "complete" },
+            total_issues.to_string().red().bold());
+        println!("Overall score: {}/100 (grade {})", score.to_string().bold(), grade_color(rules::grade(score)));
+    } else if format == OutputFormat::Prometheus {
+        compliance_metrics(&reports).write(textfile)?;
+    } else if format != OutputFormat::Ndjson {
+        output::print(format, &combined)?;
     }
     
-    // For JSON output, calculate exit code
-    let critical = issues.iter().filter(|i| i.severity == "CRITICAL").count();
-    let high = issues.iter().filter(|i| i.severity == "HIGH").count();
+    match upload {
+        // A partial report would pass for the whole account once archived
+        Some(_) if interrupted => tracing::warn!("Skipping --upload: the scan was interrupted"),
+        Some(uri) => upload_report(uri, "all-regions", &combined, format)?,
+        None => {}
+    }
     
-    let exit_code = if critical > 0 {
-        2
-    } else if high > 0 {
-        1
-    } else {
-        0
-    };
+    Ok(worst)
+}
+
+const HOURS_PER_MONTH: f64 = 730.0;
+
+#[derive(Debug, Serialize)]
+struct ResourceCounts {
+    nat_gateways: usize,
+    transit_gateways: usize,
+    tgw_attachments: usize,
+    running_instances: usize,
+    /// Counted only when `--instance-state` takes in stopped instances
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stopped_instances: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct CostLineItem {
+    resource_type: String,
+    /// Instance type for EC2 lines, empty otherwise
+    detail: String,
+    /// Resource count for hourly items, GB for data processing items
+    count: f64,
+    unit: &'static str,
+    unit_rate: Option<f64>,
+    rate_source: Option<&'static str>,
+    monthly_cost: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct CostReport {
+    region: String,
+    resources: ResourceCounts,
+    includes_data_transfer: bool,
+    live_pricing_failed: bool,
+    items: Vec<CostLineItem>,
+    total_monthly: f64,
+}
+
+impl CostReport {
+    fn item(&self, resource_type: &str) -> Option<&CostLineItem> {
+        self.items.iter().find(|i| i.resource_type == resource_type)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_costs(
+    region: &str,
+    with_data_transfer: bool,
+    live_pricing: bool,
+    instance_state: InstanceState,
+    format: OutputFormat,
+    textfile: Option<&Path>,
+    append: Option<&Path>,
+) -> Result<()> {
+    let report = build_cost_report(region, with_data_transfer, live_pricing, instance_state)?;
+    
+    match format {
+        OutputFormat::Table => print_cost_report(&report),
+        OutputFormat::Csv => CostCsv::open(append)?.report(&report)?,
+        OutputFormat::Prometheus => cost_metrics(std::slice::from_ref(&report)).write(textfile)?,
+        _ => output::print(format, &report)?,
+    }
     
-    Ok(exit_code)
+    Ok(())
 }
 
-pub fn check_compliance_all_regions(vpc_filter: Option<&str>, json_output: bool, strict: bool) -> Result<i32> {
-    let regions = vec![
-        "us-east-1", "us-east-2", "us-west-1", "us-west-2",
-        "eu-west-1", "eu-west-2", "eu-central-1",
-        "ap-southeast-1", "ap-southeast-2", "ap-northeast-1",
-    ];
+pub fn estimate_costs_all_regions(
+    with_data_transfer: bool,
+    live_pricing: bool,
+    instance_state: InstanceState,
+    format: OutputFormat,
+    textfile: Option<&Path>,
+    append: Option<&Path>,
+) -> Result<()> {
+    let regions = enabled_regions();
+    let human = !format.is_structured();
     
-    if !json_output {
-        println!("{}", "Multi-Region Compliance Scan".cyan().bold());
+    if human {
+        println!("{}", "Multi-Region Cost Estimate".cyan().bold());
         println!("{}", "═".repeat(70).bright_black());
         println!("Scanning {} regions...\n", regions.len());
     }
     
-    let mut max_exit_code = 0;
-    let mut total_issues = 0;
+    let mut reports = Vec::new();
+    let mut csv = if format == OutputFormat::Csv { Some(CostCsv::open(append)?) } else { None };
     
+    // Each region is shown as soon as it's estimated; only the totals wait for the end
     for region in &regions {
-        if !json_output {
-            println!("{} Scanning {}...", "→".cyan(), region.yellow());
+        if human {
+            println!("{} Estimating {}...", "→".cyan(), region.yellow());
         }
         
-        match check_compliance(region, vpc_filter, json_output, false) {
-            Ok(exit_code) => {
-                max_exit_code = max_exit_code.max(exit_code);
-                if exit_code > 0 {
-                    total_issues += 1;
+        match build_cost_report(region, with_data_transfer, live_pricing, instance_state) {
+            Ok(report) => {
+                match format {
+                    OutputFormat::Table => println!("  {} ${}/month", "✓".green(), format!("{:.2}", report.total_monthly).green()),
+                    OutputFormat::Csv => csv.as_mut().map_or(Ok(()), |csv| csv.report(&report))?,
+                    OutputFormat::Ndjson => output::print(format, &report)?,
+                    _ => {}
                 }
+                reports.push(report);
             }
             Err(e) => {
-                if !json_output {
+                if human {
                     println!("  {} Error: {}", "✗".red(), e);
+                } else {
+                    tracing::error!("Error estimating {}: {}", region, e);
                 }
             }
         }
-        
-        if !json_output {
-            println!();
+    }
+    
+    let total: f64 = reports.iter().map(|r| r.total_monthly).sum();
+    
+    if let Some(csv) = csv.as_mut() {
+        return csv.total("all", total);
+    }
+    
+    if format == OutputFormat::Prometheus {
+        return cost_metrics(&reports).write(textfile);
+    }
+    
+    if format == OutputFormat::Ndjson {
+        return Ok(());
+    }
+    
+    if format.is_structured() {
+        let output = serde_json::json!({
+            "regions": reports,
+            "total_monthly": total,
+        });
+        return output::print(format, &output);
+    }
+    
+    println!();
+    println!("{}", "Per-Region Breakdown:".yellow().bold());
+    for report in &reports {
+        println!("  {}: ${} ({} NAT, {} TGW attachment(s), {} instance(s))", 
+            report.region.cyan(),
+            format!("{:.2}", report.total_monthly).green(),
+            report.resources.nat_gateways,
+            report.resources.tgw_attachments,
+            report.resources.running_instances
+        );
+    }
+    
+    let mut ranked: Vec<&CostReport> = reports.iter()
+        .filter(|r| r.total_monthly > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.total_monthly.total_cmp(&a.total_monthly));
+    
+    if !ranked.is_empty() {
+        println!();
+        println!("{}", "Top 5 Most Expensive Regions:".yellow().bold());
+        for (i, report) in ranked.iter().take(5).enumerate() {
+            println!("  {}. {} ${}", 
+                i + 1,
+                report.region.cyan(),
+                format!("{:.2}", report.total_monthly).green().bold()
+            );
         }
     }
     
-    if !json_output {
-        println!("{}", "═".repeat(70).bright_black());
-        println!("Scan complete: {} region(s) with issues", total_issues.to_string().red().bold());
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Global total: ${}/month", format!("{:.2}", total).green().bold());
+    if with_data_transfer {
+        println!("{}", "Note: Data processing is based on the last 30 days of CloudWatch actuals, not a forecast".bright_black());
+    } else {
+        println!("{}", "Note: Excludes data transfer, EBS volumes, and other services".bright_black());
     }
     
-    Ok(max_exit_code)
+    Ok(())
 }
 
-pub fn estimate_costs(region: &str) -> Result<()> {
-    println!("{}", "AWS Cost Estimator".cyan().bold());
-    println!("{}", "═".repeat(70).bright_black());
-    println!("Region: {}", region.yellow());
-    println!();
+/// Monthly totals per region plus the line items behind them
+fn cost_metrics(reports: &[CostReport]) -> output::Metrics {
+    let mut metrics = output::Metrics::default();
+    
+    for report in reports {
+        let region = report.region.as_str();
+        metrics.gauge("netkit_estimated_monthly_cost_usd", "Estimated monthly network cost", &[("region", region)], format!("{:.2}", report.total_monthly));
+    }
     
-    // Pricing (approximate, us-east-1)
-    let nat_gateway_hourly = 0.045;
-    let nat_gateway_per_gb = 0.045;
-    let tgw_attachment_hourly = 0.05;
-    let tgw_per_gb = 0.02;
+    for report in reports {
+        for item in &report.items {
+            let labels = [("region", report.region.as_str()), ("resource", item.resource_type.as_str()), ("detail", item.detail.as_str())];
+            metrics.gauge("netkit_estimated_resource_monthly_cost_usd", "Estimated monthly cost of one cost line item", &labels, format!("{:.2}", item.monthly_cost));
+        }
+    }
     
-    let hours_per_month = 730.0;
+    metrics
+}
+
+fn build_cost_report(region: &str, with_data_transfer: bool, live_pricing: bool, instance_state: InstanceState) -> Result<CostReport> {
+    let started = Instant::now();
+    let mut pricing = Pricing::new(region, live_pricing);
     
     // Get NAT Gateways
-    let nat_output = Command::new("aws")
-        .args(&["ec2", "describe-nat-gateways", "--region", region])
-        .output()?;
+    let nat_output = awscli::run(&["ec2", "describe-nat-gateways", "--region", region])?;
     
     let nat_json: Value = serde_json::from_slice(&nat_output.stdout)?;
     let empty_vec = vec![];
@@ -990,9 +5584,7 @@ pub fn estimate_costs(region: &str) -> Result<()> {
         .count();
     
     // Get Transit Gateways
-    let tgw_output = Command::new("aws")
-        .args(&["ec2", "describe-transit-gateways", "--region", region])
-        .output()?;
+    let tgw_output = awscli::run(&["ec2", "describe-transit-gateways", "--region", region])?;
     
     let tgw_json: Value = serde_json::from_slice(&tgw_output.stdout)?;
     let empty_vec2 = vec![];
@@ -1005,13 +5597,11 @@ pub fn estimate_costs(region: &str) -> Result<()> {
     let mut total_attachments = 0;
     for tgw in tgws {
         if let Some(tgw_id) = tgw["TransitGatewayId"].as_str() {
-            let att_output = Command::new("aws")
-                .args(&[
+            let att_output = awscli::run(&[
                     "ec2", "describe-transit-gateway-attachments",
                     "--region", region,
                     "--filters", &format!("Name=transit-gateway-id,Values={}", tgw_id)
-                ])
-                .output()?;
+                ])?;
             
             let att_json: Value = serde_json::from_slice(&att_output.stdout)?;
             if let Some(atts) = att_json["TransitGatewayAttachments"].as_array() {
@@ -1022,61 +5612,451 @@ pub fn estimate_costs(region: &str) -> Result<()> {
         }
     }
     
-    // Get running instances
-    let inst_output = Command::new("aws")
-        .args(&["ec2", "describe-instances", "--region", region])
-        .output()?;
+    // Instances grouped by type: running ones bill compute, stopped ones only storage
+    let inst_output = awscli::run(&["ec2", "describe-instances", "--region", region])?;
     
     let inst_json: Value = serde_json::from_slice(&inst_output.stdout)?;
     let mut running_instances = 0;
+    let mut stopped_instances = 0;
+    let mut instance_types: BTreeMap<String, usize> = BTreeMap::new();
+    let mut stopped_types: BTreeMap<String, usize> = BTreeMap::new();
     
     if let Some(reservations) = inst_json["Reservations"].as_array() {
         for reservation in reservations {
             if let Some(instances) = reservation["Instances"].as_array() {
-                running_instances += instances.iter()
-                    .filter(|i| i["State"]["Name"].as_str() == Some("running"))
-                    .count();
+                for instance in instances {
+                    let state = instance["State"]["Name"].as_str().unwrap_or("unknown");
+                    if !instance_state.includes(state) {
+                        continue;
+                    }
+                    let instance_type = instance["InstanceType"].as_str().unwrap_or("unknown").to_string();
+                    if InstanceState::Running.includes(state) {
+                        *instance_types.entry(instance_type).or_insert(0) += 1;
+                        running_instances += 1;
+                    } else if InstanceState::Stopped.includes(state) {
+                        *stopped_types.entry(instance_type).or_insert(0) += 1;
+                        stopped_instances += 1;
+                    }
+                }
+            }
+        }
+    }
+    
+    let mut items = Vec::new();
+    
+    if active_nats > 0 {
+        let rate = pricing.nat_gateway_hourly();
+        items.push(hourly_item("NAT Gateway", "", active_nats, rate));
+    }
+    
+    if total_attachments > 0 {
+        let rate = pricing.tgw_attachment_hourly();
+        items.push(hourly_item("TGW Attachment", "", total_attachments, rate));
+    }
+    
+    // Data processed over the last 30 days, from CloudWatch
+    if with_data_transfer {
+        let end = Utc::now();
+        let start = end - chrono::Duration::days(30);
+        
+        let mut nat_gb = 0.0;
+        for nat in nat_gateways.iter().filter(|n| n["State"].as_str() == Some("available")) {
+            let Some(nat_id) = nat["NatGatewayId"].as_str() else { continue };
+            // NAT Gateways bill processing in both directions
+            for metric in ["BytesOutToDestination", "BytesInFromDestination"] {
+                nat_gb += get_metric_sum(region, "AWS/NATGateway", metric, "NatGatewayId", nat_id, start, end)?
+                    / BYTES_PER_GB;
             }
         }
+        
+        let mut tgw_gb = 0.0;
+        for tgw in tgws.iter().filter(|t| t["State"].as_str() == Some("available")) {
+            let Some(tgw_id) = tgw["TransitGatewayId"].as_str() else { continue };
+            // AWS bills each GB once, on the attachment it enters the TGW from.
+            // The TGW-wide BytesIn already sums that over every attachment,
+            // VPN and peering included; adding BytesOut would count each GB twice.
+            tgw_gb += get_metric_sum(region, "AWS/TransitGateway", "BytesIn", "TransitGateway", tgw_id, start, end)?
+                / BYTES_PER_GB;
+        }
+        
+        if active_nats > 0 {
+            let rate = pricing.nat_gateway_per_gb();
+            items.push(per_gb_item("NAT Data Processed", nat_gb, rate));
+        }
+        if active_tgws > 0 {
+            let rate = pricing.tgw_per_gb();
+            items.push(per_gb_item("TGW Data Processed", tgw_gb, rate));
+        }
+    }
+    
+    for (instance_type, count) in &instance_types {
+        match pricing.ec2_hourly(instance_type) {
+            Some(rate) => items.push(hourly_item("EC2 Instance", instance_type, *count, rate)),
+            None => items.push(CostLineItem {
+                resource_type: "EC2 Instance".to_string(),
+                detail: instance_type.clone(),
+                count: *count as f64,
+                unit: "hour",
+                unit_rate: None,
+                rate_source: None,
+                monthly_cost: 0.0,
+            }),
+        }
+    }
+    
+    // Listed so they aren't mistaken for missing, but free apart from their volumes
+    for (instance_type, count) in &stopped_types {
+        items.push(CostLineItem {
+            resource_type: "EC2 Instance (stopped)".to_string(),
+            detail: instance_type.clone(),
+            count: *count as f64,
+            unit: "hour",
+            unit_rate: Some(0.0),
+            rate_source: None,
+            monthly_cost: 0.0,
+        });
+    }
+    
+    if live_pricing {
+        pricing.save()?;
+    }
+    
+    let total_monthly = items.iter().map(|i| i.monthly_cost).sum();
+    timing::record(started, || format!("cost estimate for {}", region));
+    
+    Ok(CostReport {
+        region: region.to_string(),
+        resources: ResourceCounts {
+            nat_gateways: active_nats,
+            transit_gateways: active_tgws,
+            tgw_attachments: total_attachments,
+            running_instances,
+            stopped_instances: (instance_state != InstanceState::Running).then_some(stopped_instances),
+        },
+        includes_data_transfer: with_data_transfer,
+        live_pricing_failed: pricing.live_failed(),
+        items,
+        total_monthly,
+    })
+}
+
+fn hourly_item(resource_type: &str, detail: &str, count: usize, rate: Rate) -> CostLineItem {
+    CostLineItem {
+        resource_type: resource_type.to_string(),
+        detail: detail.to_string(),
+        count: count as f64,
+        unit: "hour",
+        unit_rate: Some(rate.usd),
+        rate_source: Some(rate.source.label()),
+        monthly_cost: count as f64 * rate.usd * HOURS_PER_MONTH,
+    }
+}
+
+fn per_gb_item(resource_type: &str, gb: f64, rate: Rate) -> CostLineItem {
+    CostLineItem {
+        resource_type: resource_type.to_string(),
+        detail: String::new(),
+        count: gb,
+        unit: "GB",
+        unit_rate: Some(rate.usd),
+        rate_source: Some(rate.source.label()),
+        monthly_cost: gb * rate.usd,
+    }
+}
+
+fn rate_tag(item: &CostLineItem) -> ColoredString {
+    match item.rate_source {
+        Some("live") => "live".green(),
+        Some(source) => source.bright_black(),
+        None => "".normal(),
     }
+}
+
+fn print_cost_report(report: &CostReport) {
+    println!("{}", "AWS Cost Estimator".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", report.region.yellow());
+    println!();
     
     println!("{}", "Resource Summary:".yellow().bold());
-    println!("  NAT Gateways: {}", active_nats.to_string().cyan());
-    println!("  Transit Gateways: {}", active_tgws.to_string().cyan());
-    println!("  TGW Attachments: {}", total_attachments.to_string().cyan());
-    println!("  Running Instances: {}", running_instances.to_string().cyan());
+    println!("  NAT Gateways: {}", report.resources.nat_gateways.to_string().cyan());
+    println!("  Transit Gateways: {}", report.resources.transit_gateways.to_string().cyan());
+    println!("  TGW Attachments: {}", report.resources.tgw_attachments.to_string().cyan());
+    println!("  Running Instances: {}", report.resources.running_instances.to_string().cyan());
+    if let Some(stopped) = report.resources.stopped_instances {
+        println!("  Stopped Instances: {}", stopped.to_string().cyan());
+    }
     println!();
     
     println!("{}", "Estimated Monthly Costs:".yellow().bold());
     
-    let nat_cost = active_nats as f64 * nat_gateway_hourly * hours_per_month;
-    if active_nats > 0 {
-        println!("  NAT Gateways: ${:.2} (${:.2}/hr × {} × {} hrs)", 
-            nat_cost,
-            nat_gateway_hourly,
-            active_nats,
-            hours_per_month
+    let hourly_line = |label: &str, item: &CostLineItem| {
+        println!("  {}: ${:.2} (${:.3}/hr × {} × {} hrs) [{}]", 
+            label,
+            item.monthly_cost,
+            item.unit_rate.unwrap_or(0.0),
+            item.count,
+            HOURS_PER_MONTH,
+            rate_tag(item)
         );
-        println!("    {} Data transfer not included (${}/GB)", "+".yellow(), nat_gateway_per_gb);
+    };
+    let data_line = |label: &str, item: &CostLineItem| {
+        println!("  {}: ${:.2} ({:.2} GB × ${}/GB, last 30 days) [{}]", 
+            label,
+            item.monthly_cost,
+            item.count,
+            item.unit_rate.unwrap_or(0.0),
+            rate_tag(item)
+        );
+    };
+    
+    if let Some(item) = report.item("NAT Gateway") {
+        hourly_line("NAT Gateways", item);
+        if !report.includes_data_transfer {
+            println!("    {} Data transfer not included", "+".yellow());
+        }
+    }
+    if let Some(item) = report.item("NAT Data Processed") {
+        data_line("NAT Data Processed", item);
     }
     
-    let tgw_attachment_cost = total_attachments as f64 * tgw_attachment_hourly * hours_per_month;
-    if total_attachments > 0 {
-        println!("  TGW Attachments: ${:.2} (${:.2}/hr × {} × {} hrs)", 
-            tgw_attachment_cost,
-            tgw_attachment_hourly,
-            total_attachments,
-            hours_per_month
-        );
-        println!("    {} Data transfer not included (${}/GB)", "+".yellow(), tgw_per_gb);
+    if let Some(item) = report.item("TGW Attachment") {
+        hourly_line("TGW Attachments", item);
+        if !report.includes_data_transfer {
+            println!("    {} Data transfer not included", "+".yellow());
+        }
+    }
+    if let Some(item) = report.item("TGW Data Processed") {
+        data_line("TGW Data Processed", item);
+    }
+    
+    let instances: Vec<_> = report.items.iter()
+        .filter(|i| i.resource_type == "EC2 Instance")
+        .collect();
+    if !instances.is_empty() {
+        println!("  EC2 Instances (approximate, on-demand Linux):");
+        for item in instances {
+            match item.unit_rate {
+                Some(rate) => {
+                    println!("    {} × {}: ${:.2} (${:.4}/hr × {} × {} hrs) [{}]", 
+                        item.detail.cyan(),
+                        item.count,
+                        item.monthly_cost,
+                        rate,
+                        item.count,
+                        HOURS_PER_MONTH,
+                        rate_tag(item)
+                    );
+                }
+                None => {
+                    println!("    {} × {}: {}", 
+                        item.detail.cyan(),
+                        item.count,
+                        "no known price, not included".bright_black()
+                    );
+                }
+            }
+        }
+        println!("    {} Reservations and savings plans not considered", "!".yellow());
+    }
+    
+    let stopped: Vec<_> = report.items.iter()
+        .filter(|i| i.resource_type == "EC2 Instance (stopped)")
+        .collect();
+    if !stopped.is_empty() {
+        println!("  Stopped EC2 Instances (no compute charge):");
+        for item in stopped {
+            println!("    {} × {}: {}", 
+                item.detail.cyan(),
+                item.count,
+                "$0.00 compute; attached EBS volumes are still billed, not estimated".bright_black()
+            );
+        }
+    }
+    
+    if report.live_pricing_failed {
+        println!();
+        println!("{} Pricing API unavailable or not permitted, using built-in rates", "!".yellow());
+    }
+    
+    println!();
+    println!("{}", "═".repeat(70).bright_black());
+    if report.includes_data_transfer {
+        println!("Total: ${}/month", format!("{:.2}", report.total_monthly).green().bold());
+        println!("{}", "Note: Data processing is based on the last 30 days of CloudWatch actuals, not a forecast".bright_black());
+        println!("{}", "Note: Excludes internet/inter-AZ transfer, EBS volumes, and other services".bright_black());
+    } else {
+        println!("Total (base): ${}/month", format!("{:.2}", report.total_monthly).green().bold());
+        println!("{}", "Note: Excludes data transfer, EBS volumes, and other services".bright_black());
+    }
+}
+
+/// Cost rows for stdout, or appended to a file that builds up into a time
+/// series; the header is only written once, when the file is new or empty
+struct CostCsv {
+    out: Box<dyn Write>,
+    date: String,
+}
+
+impl CostCsv {
+    fn open(append: Option<&Path>) -> Result<Self> {
+        let (mut out, is_new): (Box<dyn Write>, bool) = match append {
+            Some(path) => {
+                let is_new = std::fs::metadata(path).map_or(true, |meta| meta.len() == 0);
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| anyhow::anyhow!("Cannot open cost log {}: {}", path.display(), e))?;
+                (Box::new(file), is_new)
+            }
+            None => (Box::new(std::io::stdout()), true),
+        };
+        
+        if is_new {
+            writeln!(out, "date,region,resource_type,detail,count,unit,unit_rate,rate_source,monthly_cost")?;
+        }
+        Ok(CostCsv { out, date: Utc::now().format("%Y-%m-%d").to_string() })
+    }
+    
+    fn report(&mut self, report: &CostReport) -> Result<()> {
+        for item in &report.items {
+            writeln!(self.out, "{},{},{},{},{},{},{},{},{:.2}",
+                self.date,
+                report.region,
+                item.resource_type,
+                item.detail,
+                item.count,
+                item.unit,
+                item.unit_rate.map(|r| r.to_string()).unwrap_or_default(),
+                item.rate_source.unwrap_or(""),
+                item.monthly_cost
+            )?;
+        }
+        self.total(&report.region, report.total_monthly)
+    }
+    
+    fn total(&mut self, region: &str, total: f64) -> Result<()> {
+        writeln!(self.out, "{},{},Total,,,,,,{:.2}", self.date, region, total)?;
+        Ok(())
+    }
+}
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Sum a CloudWatch metric over the given window. Missing data counts as zero.
+fn get_metric_sum(
+    region: &str,
+    namespace: &str,
+    metric: &str,
+    dimension: &str,
+    value: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<f64> {
+    let output = awscli::run(&[
+            "cloudwatch", "get-metric-statistics",
+            "--region", region,
+            "--namespace", namespace,
+            "--metric-name", metric,
+            "--dimensions", &format!("Name={},Value={}", dimension, value),
+            "--start-time", &start.to_rfc3339(),
+            "--end-time", &end.to_rfc3339(),
+            "--period", "86400",
+            "--statistics", "Sum",
+        ])?;
+    
+    if !output.success {
+        return Ok(0.0);
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let sum = json["Datapoints"].as_array()
+        .map(|points| points.iter().filter_map(|p| p["Sum"].as_f64()).sum())
+        .unwrap_or(0.0);
+    
+    Ok(sum)
+}
+
+#[derive(Debug, Serialize)]
+struct ComplianceCounts {
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountSummary {
+    region: String,
+    vpcs: usize,
+    subnets: usize,
+    instances: usize,
+    nat_gateways: usize,
+    transit_gateways: usize,
+    /// NAT and TGW hourly charges, without EC2
+    monthly_network_cost: f64,
+    monthly_total_cost: f64,
+    compliance: ComplianceCounts,
+}
+
+/// One-screen overview built from the topology, cost, and compliance checks
+pub fn account_summary(region: &str, rules: &Ruleset, format: OutputFormat) -> Result<()> {
+    let topology = gather_topology(region)?;
+    let costs = build_cost_report(region, false, false, InstanceState::Running)?;
+    let compliance = run_compliance(region, None, rules)?;
+    
+    let summary = AccountSummary {
+        region: region.to_string(),
+        vpcs: topology.vpcs.len(),
+        subnets: topology.vpcs.iter().map(|v| v.subnets.len()).sum(),
+        instances: topology.vpcs.iter().map(|v| v.instances.len()).sum(),
+        nat_gateways: costs.resources.nat_gateways,
+        transit_gateways: costs.resources.transit_gateways,
+        monthly_network_cost: costs.items.iter()
+            .filter(|i| i.resource_type != "EC2 Instance")
+            .fold(0.0, |total, i| total + i.monthly_cost),
+        monthly_total_cost: costs.total_monthly,
+        compliance: ComplianceCounts {
+            critical: compliance.critical,
+            high: compliance.high,
+            medium: compliance.medium,
+            low: compliance.low,
+        },
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &summary);
     }
     
-    let total_base = nat_cost + tgw_attachment_cost;
+    println!("{}", "📊 Network Summary".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("Region: {}", summary.region.yellow());
+    println!();
+    
+    println!("{}", "Inventory:".yellow().bold());
+    println!("  🏢 VPCs:              {}", summary.vpcs.to_string().cyan());
+    println!("  📊 Subnets:           {}", summary.subnets.to_string().cyan());
+    println!("  💻 Instances:         {}", summary.instances.to_string().cyan());
+    println!("  🔀 NAT Gateways:      {}", summary.nat_gateways.to_string().cyan());
+    println!("  🔗 Transit Gateways:  {}", summary.transit_gateways.to_string().cyan());
+    println!();
     
+    println!("{}", "Estimated Monthly Cost:".yellow().bold());
+    println!("  Network:  {}", format!("${:.2}", summary.monthly_network_cost).green());
+    println!("  Total:    {}", format!("${:.2}", summary.monthly_total_cost).green().bold());
     println!();
+    
+    println!("{}", "Compliance:".yellow().bold());
+    println!("  {} critical, {} high, {} medium, {} low", 
+        summary.compliance.critical.to_string().red().bold(),
+        summary.compliance.high.to_string().yellow().bold(),
+        summary.compliance.medium.to_string().bright_yellow(),
+        summary.compliance.low.to_string().bright_blue()
+    );
+    
     println!("{}", "═".repeat(70).bright_black());
-    println!("Total (base): ${:.2}/month", total_base.to_string().green().bold());
-    println!("{}", "Note: Excludes data transfer, EC2 instances, and other services".bright_black());
+    println!("{}", "Run `netkit aws-map`, `netkit cost`, or `netkit compliance` for details".bright_black());
     
     Ok(())
 }
@@ -0,0 +1,528 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config;
+use crate::log;
+use crate::redact;
+use crate::timing;
+
+/// Process-wide options for every `aws` invocation, set once from the CLI in `main`
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// Reuse cached describe responses younger than this
+    pub cache_ttl: Option<Duration>,
+    /// Ignore cached responses but still write fresh ones
+    pub refresh: bool,
+    /// `--tag KEY=VALUE` selectors every tag-aware describe call must match
+    pub tags: Vec<(String, String)>,
+    /// Log each command and answer it with an empty JSON object instead of running it
+    pub dry_run: bool,
+    /// Kill any `aws` call still running after this long
+    pub timeout: Option<Duration>,
+    /// Answer calls from captured `<operation>.json` files in this directory instead of AWS
+    pub input_dir: Option<PathBuf>,
+    /// Run every call as this role, assumed with the profile's own credentials
+    pub assume_role: Option<AssumeRole>,
+}
+
+#[derive(Debug)]
+pub struct AssumeRole {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    pub session_name: String,
+}
+
+/// Temporary credentials from `sts assume-role`
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Credentials this close to expiring are replaced before the next call
+const CREDENTIAL_REFRESH_MARGIN: chrono::Duration = chrono::Duration::minutes(5);
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static CREDENTIALS: Mutex<Option<Credentials>> = Mutex::new(None);
+
+pub fn configure(settings: Settings) {
+    let _ = SETTINGS.set(settings);
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+/// True with `--dry-run`, when calls are only logged and answer with `{}`
+pub fn dry_run() -> bool {
+    settings().dry_run
+}
+
+/// The region `aws configure` saved for the active profile (`AWS_PROFILE`
+/// is already set from `--profile` by now), read under `--aws-timeout`.
+/// `None` when unset, without the CLI, or with `--dry-run` or `--input-dir`,
+/// which must not run it.
+pub fn configured_region() -> Option<String> {
+    let settings = settings();
+    if settings.dry_run || settings.input_dir.is_some() {
+        return None;
+    }
+    
+    let mut command = Command::new("aws");
+    command.args(["configure", "get", "region"]);
+    let output = run_command(command).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|region| !region.is_empty())
+}
+
+/// `Name=tag:KEY,Values=VALUE` filters for the configured `--tag` selectors
+pub fn tag_filters() -> Vec<String> {
+    settings().tags.iter()
+        .map(|(key, value)| format!("Name=tag:{},Values={}", key, value))
+        .collect()
+}
+
+/// Captured result of one `aws` call
+pub struct AwsOutput {
+    pub success: bool,
+    pub stdout: Vec<u8>,
+    /// Empty for responses served from the cache
+    pub stderr: Vec<u8>,
+}
+
+/// Run `aws <args>`, serving read-only describe calls from the disk cache when enabled.
+/// Output is always requested as JSON, whatever `output` the user's AWS config sets.
+/// With `--redact`, pseudonyms in `args` are mapped back before the call and
+/// identifiers in the response are replaced on the way out.
+pub fn run<S: AsRef<str>>(args: &[S]) -> Result<AwsOutput> {
+    if !redact::enabled() {
+        let args: Vec<&str> = args.iter().map(|a| a.as_ref()).collect();
+        return execute(&args);
+    }
+    
+    let restored: Vec<String> = args.iter().map(|a| redact::restore(a.as_ref())).collect();
+    let restored: Vec<&str> = restored.iter().map(String::as_str).collect();
+    let output = execute(&restored).map_err(|e| anyhow::anyhow!("{}", redact::text(&e.to_string())))?;
+    
+    Ok(AwsOutput {
+        success: output.success,
+        stdout: redact::json(&output.stdout),
+        stderr: redact::text(&String::from_utf8_lossy(&output.stderr)).into_bytes(),
+    })
+}
+
+fn execute(args: &[&str]) -> Result<AwsOutput> {
+    let args = args.to_vec();
+    let settings = settings();
+    
+    if settings.dry_run {
+        eprintln!("[dry-run] {}", command_line(&args));
+        return Ok(AwsOutput { success: true, stdout: b"{}".to_vec(), stderr: Vec::new() });
+    }
+    
+    if let Some(dir) = &settings.input_dir {
+        return replay(dir, &args);
+    }
+    
+    let cache_file = settings.cache_ttl
+        .filter(|_| is_cacheable(&args) && !is_live(&args))
+        .and_then(|_| cache_path(&args));
+    
+    if let (Some(path), Some(ttl)) = (&cache_file, settings.cache_ttl) {
+        if !settings.refresh && is_fresh(path, ttl) {
+            if let Ok(stdout) = fs::read(path) {
+                tracing::debug!(target: log::AWS, "{} (cached)", command_line(&args));
+                return Ok(AwsOutput { success: true, stdout, stderr: Vec::new() });
+            }
+        }
+    }
+    
+    tracing::debug!(target: log::AWS, "{}", command_line(&args));
+    
+    let mut output = call(&args, false)?;
+    // Long multi-region runs can outlive a session; renew once and retry
+    if settings.assume_role.is_some() && String::from_utf8_lossy(&output.stderr).contains("ExpiredToken") {
+        tracing::debug!(target: log::AWS, "session expired; renewing the role and retrying");
+        output = call(&args, true)?;
+    }
+    
+    // Only successful responses are cached; failures should be retried next time
+    if output.status.success() {
+        if let Some(path) = &cache_file {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let _ = fs::write(path, &output.stdout);
+        }
+    }
+    
+    Ok(AwsOutput {
+        success: output.status.success(),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+/// One real `aws` invocation, as the assumed role when `--assume-role` is set
+fn call(args: &[&str], renew_credentials: bool) -> Result<Output> {
+    let settings = settings();
+    let mut command = Command::new("aws");
+    command.args(args).args(["--output", "json"]);
+    
+    if let Some(role) = &settings.assume_role {
+        let mut credentials = CREDENTIALS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stale = credentials.as_ref().is_none_or(|c| c.expires_at - Utc::now() < CREDENTIAL_REFRESH_MARGIN);
+        if renew_credentials || stale {
+            *credentials = Some(assume_role(role)?);
+        }
+        
+        let credentials = credentials.as_ref().expect("credentials were just assumed");
+        // Explicit keys win over any profile, so the whole run stays in the role's account
+        command.env_remove("AWS_PROFILE")
+            .env("AWS_ACCESS_KEY_ID", &credentials.access_key_id)
+            .env("AWS_SECRET_ACCESS_KEY", &credentials.secret_access_key)
+            .env("AWS_SESSION_TOKEN", &credentials.session_token);
+    }
+    
+    let started = Instant::now();
+    let output = run_command(command)
+        .map_err(|e| anyhow::anyhow!("`{}` {}", command_line(args), e))?;
+    timing::record_call(args, started.elapsed());
+    tracing::trace!("{} finished in {:.2}s ({})",
+        args.iter().take(2).copied().collect::<Vec<_>>().join(" "), started.elapsed().as_secs_f64(), output.status);
+    Ok(output)
+}
+
+/// `sts assume-role` with the caller's own credentials (profile or environment)
+fn assume_role(role: &AssumeRole) -> Result<Credentials> {
+    let mut args = vec!["sts", "assume-role", "--role-arn", &role.role_arn, "--role-session-name", &role.session_name];
+    if let Some(external_id) = &role.external_id {
+        args.extend(["--external-id", external_id]);
+    }
+    
+    tracing::debug!(target: log::AWS, "{}", command_line(&args));
+    
+    let mut command = Command::new("aws");
+    command.args(&args).args(["--output", "json"]);
+    let output = run_command(command)
+        .map_err(|e| anyhow::anyhow!("`{}` {}", command_line(&args), e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("AccessDenied") {
+            return Err(anyhow::anyhow!("Not allowed to assume {} (check the role's trust policy and --external-id)", role.role_arn));
+        }
+        return Err(anyhow::anyhow!("Failed to assume {}: {}", role.role_arn, redact::text(stderr.trim())));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let field = |key: &str| json["Credentials"][key].as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("sts assume-role returned no {} for {}", key, role.role_arn));
+    let expires_at = DateTime::parse_from_rfc3339(&field("Expiration")?)
+        .map_err(|e| anyhow::anyhow!("sts assume-role returned an invalid expiration: {}", e))?
+        .with_timezone(&Utc);
+    
+    tracing::debug!(target: log::AWS, "assumed {} until {}", redact::text(&role.role_arn), expires_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    
+    Ok(Credentials {
+        access_key_id: field("AccessKeyId")?,
+        secret_access_key: field("SecretAccessKey")?,
+        session_token: field("SessionToken")?,
+        expires_at,
+    })
+}
+
+fn run_command(mut command: Command) -> Result<Output> {
+    match settings().timeout {
+        Some(limit) => output_within(command, limit),
+        None => Ok(command.output()?),
+    }
+}
+
+/// `Command::output` with a deadline; the child is killed if it overruns
+fn output_within(mut command: Command, limit: Duration) -> Result<Output> {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    
+    // Drain both pipes while waiting so a chatty child can't block on a full buffer
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        buf
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+    });
+    
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!("timed out after {}s (--aws-timeout)", limit.as_secs_f64()));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    
+    Ok(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+/// Serve a call from `<dir>/<operation>.json`, applying its `--filters` the way
+/// the EC2 API would so one account-wide capture answers per-VPC lookups.
+/// Captures are taken as-is for whichever region was requested.
+fn replay(dir: &Path, args: &[&str]) -> Result<AwsOutput> {
+    let operation = args.get(1).copied().unwrap_or_default();
+    let path = dir.join(format!("{}.json", operation));
+    
+    tracing::debug!(target: log::AWS, "{} (from {})", command_line(args), path.display());
+    
+    let raw = match fs::read(&path) {
+        Ok(raw) => raw,
+        // A missing describe capture usually means the account has none of that resource
+        Err(_) if is_cacheable(args) => {
+            tracing::info!("No capture {}, treating it as empty", path.display());
+            b"{}".to_vec()
+        }
+        Err(_) => return Err(anyhow::anyhow!(
+            "`aws {}` is not available with --input-dir (no capture at {})",
+            args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+            path.display()
+        )),
+    };
+    let mut json: Value = serde_json::from_slice(&raw)
+        .map_err(|e| anyhow::anyhow!("Invalid capture {}: {}", path.display(), e))?;
+    
+    let mut filters: Vec<(Selector, Vec<&str>)> = args.iter()
+        .skip_while(|a| **a != "--filters")
+        .skip(1)
+        .take_while(|a| !a.starts_with("--"))
+        .filter_map(|f| parse_filter(f))
+        .map(|(name, values)| Ok((Selector::for_filter(name)?, values)))
+        .collect::<Result<_>>()?;
+    filters.extend(id_selectors(args));
+    
+    if let Some(collections) = json.as_object_mut() {
+        for items in collections.values_mut().filter_map(Value::as_array_mut) {
+            for reservation in items.iter_mut() {
+                // describe-instances nests instances one level down
+                if let Some(instances) = reservation["Instances"].as_array_mut() {
+                    instances.retain(|i| matches_filters(i, &filters));
+                }
+            }
+            items.retain(|item| item["Instances"].as_array().map_or_else(
+                || matches_filters(item, &filters),
+                |instances| !instances.is_empty(),
+            ));
+        }
+    }
+    
+    Ok(AwsOutput { success: true, stdout: serde_json::to_vec(&json)?, stderr: Vec::new() })
+}
+
+/// Response fields behind each `--filters` name netkit sends, as paths where
+/// `[]` steps into every element of an array
+const FILTER_FIELDS: &[(&str, &[&str])] = &[
+    ("vpc-id", &["VpcId"]),
+    // Internet gateways list `Attachments`, VPN gateways `VpcAttachments`
+    ("attachment.vpc-id", &["Attachments[].VpcId", "VpcAttachments[].VpcId"]),
+    ("resource-id", &["ResourceId"]),
+    ("transit-gateway-id", &["TransitGatewayId"]),
+    ("prefix-list-id", &["PrefixListId"]),
+    ("type", &["Type"]),
+    ("status", &["Status"]),
+];
+
+/// What a replayed filter compares its values against
+enum Selector {
+    Tag(String),
+    Fields(Vec<String>),
+}
+
+impl Selector {
+    fn for_filter(name: &str) -> Result<Self> {
+        if let Some(key) = name.strip_prefix("tag:") {
+            return Ok(Selector::Tag(key.to_string()));
+        }
+        FILTER_FIELDS.iter()
+            .find(|(filter, _)| *filter == name)
+            .map(|(_, paths)| Selector::Fields(paths.iter().map(|p| p.to_string()).collect()))
+            .ok_or_else(|| anyhow::anyhow!("Filter '{}' is not supported with --input-dir", name))
+    }
+    
+    fn matches(&self, item: &Value, values: &[&str]) -> bool {
+        match self {
+            Selector::Tag(key) => item["Tags"].as_array().is_some_and(|tags| tags.iter().any(|t| {
+                t["Key"].as_str() == Some(key) && t["Value"].as_str().is_some_and(|v| values.contains(&v))
+            })),
+            Selector::Fields(paths) => paths.iter()
+                .flat_map(|path| field_values(item, path))
+                .any(|v| values.contains(&v)),
+        }
+    }
+}
+
+/// `Name=vpc-id,Values=a,b` as `("vpc-id", ["a", "b"])`
+fn parse_filter(filter: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, values) = filter.strip_prefix("Name=")?.split_once(",Values=")?;
+    Some((name, values.split(',').collect()))
+}
+
+/// `--instance-ids i-1 i-2` style arguments, matched against the resource's
+/// own ID field (`InstanceId`, `NatGatewayId`, ...)
+fn id_selectors<'a>(args: &[&'a str]) -> Vec<(Selector, Vec<&'a str>)> {
+    args.iter()
+        .enumerate()
+        .filter_map(|(i, arg)| {
+            let resource = arg.strip_prefix("--")?.strip_suffix("-ids")?;
+            let field: String = resource.split('-')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+                })
+                .chain(["Id".to_string()])
+                .collect();
+            let values = args[i + 1..].iter()
+                .take_while(|a| !a.starts_with("--"))
+                .flat_map(|a| a.split(','))
+                .collect();
+            Some((Selector::Fields(vec![field]), values))
+        })
+        .collect()
+}
+
+/// String values at a dotted path such as `Attachments[].VpcId`
+fn field_values<'a>(item: &'a Value, path: &str) -> Vec<&'a str> {
+    let mut current = vec![item];
+    for segment in path.split('.') {
+        current = match segment.strip_suffix("[]") {
+            Some(key) => current.iter()
+                .filter_map(|v| v[key].as_array())
+                .flatten()
+                .collect(),
+            None => current.iter().map(|v| &v[segment]).collect(),
+        };
+    }
+    current.iter().filter_map(|v| v.as_str()).collect()
+}
+
+fn matches_filters(item: &Value, filters: &[(Selector, Vec<&str>)]) -> bool {
+    filters.iter().all(|(selector, values)| selector.matches(item, values))
+}
+
+/// Shell-style rendering of an invocation for `--verbose` and `--dry-run`
+fn command_line(args: &[&str]) -> String {
+    let mut line = String::from("aws");
+    for arg in args.iter().chain(&["--output", "json"]) {
+        line.push(' ');
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "'\"$`\\*?[]{}|&;<>()".contains(c)) {
+            line.push_str(&format!("'{}'", arg.replace('\'', "'\\''")));
+        } else {
+            line.push_str(arg);
+        }
+    }
+    redact::text(&line)
+}
+
+fn is_cacheable(args: &[&str]) -> bool {
+    args.get(1).is_some_and(|op| op.starts_with("describe-"))
+}
+
+/// Describe calls reporting live status (an SSM agent's last ping) rather
+/// than configuration, which a cached answer would misstate
+fn is_live(args: &[&str]) -> bool {
+    args.get(1).is_some_and(|op| *op == "describe-instance-information")
+}
+
+/// Cache entries are keyed by the full argument list (service, operation,
+/// region, filters) plus the active profile and assumed role.
+fn cache_path(args: &[&str]) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    args.hash(&mut hasher);
+    std::env::var("AWS_PROFILE").unwrap_or_default().hash(&mut hasher);
+    settings().assume_role.as_ref().map(|role| &role.role_arn).hash(&mut hasher);
+    
+    let name = format!("{}-{:016x}.json", args.get(1).unwrap_or(&"aws"), hasher.finish());
+    config::cache_dir().map(|dir| dir.join("aws").join(name))
+}
+
+fn is_fresh(path: &PathBuf, ttl: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    fn selectors<'a>(args: &[&'a str]) -> Vec<(Selector, Vec<&'a str>)> {
+        let mut filters: Vec<_> = args.iter()
+            .filter_map(|f| parse_filter(f))
+            .map(|(name, values)| (Selector::for_filter(name).unwrap(), values))
+            .collect();
+        filters.extend(id_selectors(args));
+        filters
+    }
+    
+    #[test]
+    fn dotted_filter_matches_nested_attachments() {
+        let igw = json!({"InternetGatewayId": "igw-1", "Attachments": [{"VpcId": "vpc-1"}]});
+        let vgw = json!({"VpnGatewayId": "vgw-1", "VpcAttachments": [{"VpcId": "vpc-2"}]});
+        
+        let filters = selectors(&["Name=attachment.vpc-id,Values=vpc-1"]);
+        assert!(matches_filters(&igw, &filters));
+        assert!(!matches_filters(&vgw, &filters));
+        assert!(matches_filters(&vgw, &selectors(&["Name=attachment.vpc-id,Values=vpc-1,vpc-2"])));
+    }
+    
+    #[test]
+    fn id_arguments_select_by_resource_id() {
+        let nat = json!({"NatGatewayId": "nat-1", "VpcId": "vpc-1"});
+        
+        assert!(matches_filters(&nat, &selectors(&["ec2", "describe-nat-gateways", "--nat-gateway-ids", "nat-1"])));
+        assert!(!matches_filters(&nat, &selectors(&["ec2", "describe-nat-gateways", "--nat-gateway-ids", "nat-2", "--region", "x"])));
+        assert!(matches_filters(&nat, &selectors(&["--nat-gateway-ids", "nat-2", "nat-1", "Name=vpc-id,Values=vpc-1"])));
+    }
+    
+    #[test]
+    fn tag_filters_match_key_and_value() {
+        let vpc = json!({"VpcId": "vpc-1", "Tags": [{"Key": "Env", "Value": "prod"}]});
+        
+        assert!(matches_filters(&vpc, &selectors(&["Name=tag:Env,Values=dev,prod"])));
+        assert!(!matches_filters(&vpc, &selectors(&["Name=tag:Env,Values=dev"])));
+    }
+    
+    #[test]
+    fn unknown_filters_are_rejected() {
+        assert!(Selector::for_filter("availability-zone").is_err());
+        assert!(Selector::for_filter("vpc-id").is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+
+/// Split `a.b.c.d/n` into its base address and prefix length, rejecting
+/// anything that isn't four 0-255 octets and a 0-32 prefix.
+pub fn parse_cidr(cidr: &str) -> Result<(u32, u8)> {
+    let invalid = |reason: String| anyhow::anyhow!("Invalid CIDR '{}': {}", cidr, reason);
+    
+    let (ip, prefix) = cidr.trim().split_once('/')
+        .ok_or_else(|| invalid("expected a.b.c.d/prefix".to_string()))?;
+    
+    let prefix: u8 = prefix.parse()
+        .ok()
+        .filter(|p| *p <= 32)
+        .ok_or_else(|| invalid(format!("prefix /{} is out of range (0-32)", prefix)))?;
+    
+    let octets: Vec<&str> = ip.split('.').collect();
+    if octets.len() != 4 {
+        return Err(invalid(format!("expected 4 octets, got {}", octets.len())));
+    }
+    
+    let mut base = 0u32;
+    for octet in octets {
+        let value: u8 = octet.parse()
+            .map_err(|_| invalid(format!("octet '{}' is not in 0-255", octet)))?;
+        base = (base << 8) | u32::from(value);
+    }
+    
+    Ok((base, prefix))
+}
+
+/// Split an IPv6 `addr/n` into its base address and a 0-128 prefix length
+pub fn parse_cidr_v6(cidr: &str) -> Result<(u128, u8)> {
+    let invalid = |reason: String| anyhow::anyhow!("Invalid IPv6 CIDR '{}': {}", cidr, reason);
+    
+    let (ip, prefix) = cidr.trim().split_once('/')
+        .ok_or_else(|| invalid("expected addr/prefix".to_string()))?;
+    
+    let prefix: u8 = prefix.parse()
+        .ok()
+        .filter(|p| *p <= 128)
+        .ok_or_else(|| invalid(format!("prefix /{} is out of range (0-128)", prefix)))?;
+    let ip: std::net::Ipv6Addr = ip.parse()
+        .map_err(|_| invalid(format!("'{}' is not an IPv6 address", ip)))?;
+    
+    Ok((u128::from(ip), prefix))
+}
+
+/// True when the whole network sits inside unique local space, fc00::/7 (RFC 4193)
+pub fn is_unique_local_v6(base: u128, prefix: u8) -> bool {
+    prefix >= 7 && base >> 121 == 0xfc >> 1
+}
+
+/// Like `parse_cidr`, but a bare address is accepted as its /32
+pub fn parse_network(raw: &str) -> Result<(u32, u8)> {
+    if raw.contains('/') {
+        parse_cidr(raw)
+    } else {
+        parse_cidr(&format!("{}/32", raw.trim()))
+            .map_err(|_| anyhow::anyhow!("Invalid IP or CIDR '{}'", raw))
+    }
+}
+
+/// Base address with the host bits cleared
+pub fn network(base: u32, prefix: u8) -> u32 {
+    range(base, prefix).0
+}
+
+/// First and last address covered by a network
+pub fn range(base: u32, prefix: u8) -> (u32, u32) {
+    let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - u32::from(prefix.min(32))) };
+    let first = base & mask;
+    (first, first | !mask)
+}
+
+/// Intersection of two inclusive address ranges
+pub fn overlap(a: (u32, u32), b: (u32, u32)) -> Option<(u32, u32)> {
+    let start = a.0.max(b.0);
+    let end = a.1.min(b.1);
+    (start <= end).then_some((start, end))
+}
+
+/// True when the whole network sits inside 10/8, 172.16/12, or 192.168/16 (RFC 1918)
+pub fn is_private(base: u32, prefix: u8) -> bool {
+    const PRIVATE: [(u32, u8); 3] = [(0x0A00_0000, 8), (0xAC10_0000, 12), (0xC0A8_0000, 16)];
+    
+    PRIVATE.iter().any(|(net, len)| prefix >= *len && network(base, *len) == *net)
+}
+
+/// Assignable hosts in a network: everything but the network and broadcast
+/// addresses, except point-to-point /31s (RFC 3021) and single-host /32s.
+pub fn usable_hosts(prefix: u8) -> u64 {
+    match prefix {
+        32 => 1,
+        31 => 2,
+        _ => (1u64 << (32 - u32::from(prefix.min(32)))) - 2,
+    }
+}
+
+/// Addresses AWS reserves in every subnet (network, router, DNS, future use, broadcast)
+pub const AWS_RESERVED_IPS: u64 = 5;
+
+/// Usable hosts as EC2 reports them, after the AWS reservation
+pub fn aws_usable_hosts(prefix: u8) -> u64 {
+    (1u64 << (32 - u32::from(prefix.min(32)))).saturating_sub(AWS_RESERVED_IPS)
+}
+
+pub fn format_ip(ip: u32) -> String {
+    format!("{}.{}.{}.{}", 
+        (ip >> 24) & 0xFF,
+        (ip >> 16) & 0xFF,
+        (ip >> 8) & 0xFF,
+        ip & 0xFF
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn usable_hosts_edge_prefixes() {
+        assert_eq!(usable_hosts(32), 1);
+        assert_eq!(usable_hosts(31), 2);
+        assert_eq!(usable_hosts(30), 2);
+        assert_eq!(usable_hosts(24), 254);
+        assert_eq!(usable_hosts(0), (1u64 << 32) - 2);
+    }
+    
+    #[test]
+    fn aws_usable_hosts_never_underflows() {
+        assert_eq!(aws_usable_hosts(28), 11);
+        assert_eq!(aws_usable_hosts(30), 0);
+        assert_eq!(aws_usable_hosts(31), 0);
+        assert_eq!(aws_usable_hosts(32), 0);
+        assert_eq!(aws_usable_hosts(0), (1u64 << 32) - 5);
+    }
+}
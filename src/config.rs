@@ -0,0 +1,112 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::awscli;
+use crate::output::OutputFormat;
+
+const DEFAULT_REGION: &str = "us-east-1";
+const DEFAULT_SCAN_TIMEOUT_MS: u64 = 3000;
+const DEFAULT_SCAN_CONCURRENCY: usize = 32;
+
+/// Defaults loaded from `~/.config/netkit/config.toml` and `./netkit.toml`.
+/// Every value here is overridden by the matching CLI flag.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub output: Option<OutputFormat>,
+    /// Seconds to reuse cached AWS describe responses
+    pub cache_ttl: Option<u64>,
+    /// Directory for generated files, like `--output-dir`
+    pub output_dir: Option<PathBuf>,
+    pub scan: ScanConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Connect timeout for `scan`, in milliseconds
+    pub timeout_ms: Option<u64>,
+    /// Parallel probes for `discover`
+    pub concurrency: Option<usize>,
+}
+
+impl Config {
+    /// Load the user config, then let the project-local `netkit.toml` override it field by field.
+    pub fn load() -> Result<Self> {
+        let mut config = Config::default();
+        
+        if let Some(path) = user_config_path() {
+            if path.exists() {
+                config.merge(read_config(&path)?);
+            }
+        }
+        
+        let local = Path::new("netkit.toml");
+        if local.exists() {
+            config.merge(read_config(local)?);
+        }
+        
+        Ok(config)
+    }
+    
+    fn merge(&mut self, other: Config) {
+        self.region = other.region.or(self.region.take());
+        self.profile = other.profile.or(self.profile.take());
+        self.output = other.output.or(self.output.take());
+        self.cache_ttl = other.cache_ttl.or(self.cache_ttl);
+        self.output_dir = other.output_dir.or(self.output_dir.take());
+        self.scan.timeout_ms = other.scan.timeout_ms.or(self.scan.timeout_ms);
+        self.scan.concurrency = other.scan.concurrency.or(self.scan.concurrency);
+    }
+    
+    /// `--region`, then the config file, then the same places the aws CLI
+    /// looks: `AWS_REGION`, `AWS_DEFAULT_REGION`, and the profile's `region`
+    pub fn region(&self, flag: Option<String>) -> String {
+        flag.or_else(|| self.region.clone())
+            .or_else(|| env_region("AWS_REGION"))
+            .or_else(|| env_region("AWS_DEFAULT_REGION"))
+            .or_else(awscli::configured_region)
+            .unwrap_or_else(|| DEFAULT_REGION.to_string())
+    }
+    
+    pub fn scan_timeout_ms(&self, flag: Option<u64>) -> u64 {
+        flag.or(self.scan.timeout_ms).unwrap_or(DEFAULT_SCAN_TIMEOUT_MS).max(1)
+    }
+    
+    pub fn scan_concurrency(&self, flag: Option<usize>) -> usize {
+        flag.or(self.scan.concurrency).unwrap_or(DEFAULT_SCAN_CONCURRENCY).max(1)
+    }
+}
+
+fn env_region(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+        .map(|region| region.trim().to_string())
+        .filter(|region| !region.is_empty())
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    
+    Some(base.join("netkit").join("config.toml"))
+}
+
+/// Base directory for netkit's on-disk caches
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    
+    Some(base.join("netkit"))
+}
+
+fn read_config(path: &Path) -> Result<Config> {
+    let raw = fs::read_to_string(path)?;
+    toml::from_str(&raw)
+        .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+}
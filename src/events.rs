@@ -0,0 +1,110 @@
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+
+/// One line of the `--events` stream
+#[derive(Serialize)]
+struct Event<'a, T: Serialize> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    timestamp: String,
+    data: &'a T,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SINK: Mutex<Option<File>> = Mutex::new(None);
+
+/// Start the event stream. `-` takes over stdout and moves the human output
+/// to stderr; any other path is appended to, so a shipper can tail it.
+pub fn enable(target: &Path) -> Result<()> {
+    let sink = if target == Path::new("-") {
+        take_stdout()?
+    } else {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(target)
+            .map_err(|e| anyhow::anyhow!("Cannot open events file {}: {}", target.display(), e))?
+    };
+    
+    *lock() = Some(sink);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Write one `{"type", "timestamp", "data"}` line; a no-op unless `--events` is set.
+/// Each line is flushed as it is written so events arrive while the command runs.
+pub fn emit<T: Serialize>(kind: &str, data: &T) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    
+    let event = Event { kind, timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), data };
+    let Ok(line) = serde_json::to_string(&event) else { return };
+    
+    if let Some(sink) = lock().as_mut() {
+        if let Err(e) = writeln!(sink, "{}", line).and_then(|_| sink.flush()) {
+            tracing::error!("Error: Cannot write event: {}", e);
+            ENABLED.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A handle on the original stdout for events, with fd 1 pointed at stderr
+/// so everything else printed from here on lands there
+#[cfg(unix)]
+fn take_stdout() -> Result<File> {
+    // Nothing has been written to stdout yet, so no buffered output is misdirected
+    let events = redirect_stdout(std::io::stderr())
+        .map_err(|e| anyhow::anyhow!("Cannot move output to stderr: {}", e))?;
+    Ok(File::from(events))
+}
+
+/// Point fd 1 at `to`, returning a handle on whatever it pointed at before
+#[cfg(unix)]
+fn redirect_stdout(to: impl std::os::fd::AsFd) -> std::io::Result<std::os::fd::OwnedFd> {
+    use std::os::fd::AsFd;
+    
+    let previous = std::io::stdout().as_fd().try_clone_to_owned()?;
+    rustix::stdio::dup2_stdout(to.as_fd())?;
+    Ok(previous)
+}
+
+/// Moving stdout aside needs Unix file descriptors, so elsewhere events need a file
+#[cfg(not(unix))]
+fn take_stdout() -> Result<File> {
+    Err(anyhow::anyhow!("--events - is only supported on Unix; give a file path instead"))
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<File>> {
+    SINK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    
+    #[test]
+    fn redirect_stdout_moves_fd_1_and_hands_back_the_original() {
+        let path = std::env::temp_dir().join(format!("netkit-events-{}.txt", std::process::id()));
+        let target = File::create(&path).unwrap();
+        
+        let original = redirect_stdout(&target).unwrap();
+        let written = std::io::stdout().write_all(b"moved\n").and_then(|_| std::io::stdout().flush());
+        redirect_stdout(&original).unwrap();
+        written.unwrap();
+        
+        let mut captured = String::new();
+        File::open(&path).unwrap().read_to_string(&mut captured).unwrap();
+        std::fs::remove_file(&path).ok();
+        // The test harness may report on other tests in the meantime
+        assert!(captured.contains("moved\n"));
+    }
+}
@@ -0,0 +1,3 @@
+//! The parts of netkit that stand on their own, shared by the binary and its integration tests
+
+pub mod scan;
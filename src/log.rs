@@ -0,0 +1,73 @@
+use std::fmt::{self, Write as _};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Target of the `aws` command lines, cache hits, and credential renewals that
+/// `--debug` logs on its own
+pub const AWS: &str = "aws";
+
+/// Send diagnostics to stderr at the level the flags ask for. Command results
+/// never pass through here; they stay on stdout.
+///
+/// `--quiet` keeps errors only, the default adds warnings and notes, `-v` every
+/// `aws` command, and `-vv` per-call and per-phase timings. `--debug` logs the
+/// `aws` commands whatever the level.
+pub fn init(quiet: bool, verbose: u8, debug: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let mut filter = Targets::new().with_default(level);
+    if debug {
+        filter = filter.with_target(AWS, level.max(LevelFilter::DEBUG));
+    }
+    
+    let layer = tracing_subscriber::fmt::layer()
+        .event_format(Plain)
+        .with_writer(std::io::stderr)
+        .with_filter(filter);
+    tracing_subscriber::registry().with(layer).init();
+}
+
+/// Lines as netkit always printed them: notes and warnings bare, debug and
+/// trace lines tagged so they stand apart from a default run's output
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::DEBUG => writer.write_str("[debug] ")?,
+            Level::TRACE => writer.write_str("[trace] ")?,
+            _ => {}
+        }
+        
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        writeln!(writer, "{}", message.0)
+    }
+}
+
+/// The event's message, written as given (colors included) rather than escaped
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        }
+    }
+}
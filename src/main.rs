@@ -1,14 +1,117 @@
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use clap::{Parser, Subcommand};
 use colored::*;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 
+mod arp;
+mod artifact;
 mod aws;
+mod awscli;
+mod cidr;
+mod config;
+mod events;
+mod log;
+mod output;
+mod pricing;
+mod redact;
+mod rules;
+mod sysnet;
+mod timing;
+mod whois;
+
+use config::Config;
+use netkit::scan;
+use output::OutputFormat;
 
 #[derive(Parser)]
 #[command(name = "netkit")]
 #[command(about = "AWS Network Analysis Toolkit", long_about = None)]
 struct Cli {
+    /// Output format [default: table]
+    #[arg(short, long, visible_alias = "format", global = true, value_enum)]
+    output: Option<OutputFormat>,
+    
+    /// AWS CLI profile to use
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    
+    /// Cache AWS describe responses on disk for this many seconds
+    #[arg(long, global = true, value_name = "SECS")]
+    cache_ttl: Option<u64>,
+    
+    /// Bypass cached AWS responses and overwrite them
+    #[arg(long, global = true)]
+    refresh: bool,
+    
+    /// Only include AWS resources with this tag (repeatable, all must match)
+    #[arg(long, global = true, value_name = "KEY=VALUE", value_parser = parse_tag)]
+    tag: Vec<(String, String)>,
+    
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+    
+    /// Give up on any single aws call after this many seconds
+    #[arg(long, global = true, value_name = "SECS")]
+    aws_timeout: Option<u64>,
+    
+    /// Print only errors and final results
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    
+    /// Log each aws command, cache hit, and credential renewal to stderr (-vv: also per-call and per-phase timings)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    
+    /// Log every aws command to stderr before running it
+    #[arg(long, global = true)]
+    debug: bool,
+    
+    /// Print the aws commands without running them (AWS lookups return nothing)
+    #[arg(long, global = true)]
+    dry_run: bool,
+    
+    /// Read captured describe output (e.g. describe-vpcs.json) from this directory instead of calling AWS
+    #[arg(long, global = true, value_name = "DIR")]
+    input_dir: Option<PathBuf>,
+    
+    /// Print how long each phase and aws call took to stderr when the command finishes
+    #[arg(long, global = true)]
+    timing: bool,
+    
+    /// Replace account IDs, resource IDs, and public IPs with stable pseudonyms (vpc-0001, 203.0.113.1)
+    #[arg(long, global = true)]
+    redact: bool,
+    
+    /// With --redact, keep the pseudonym-to-real legend in this JSON file (reused on later runs)
+    #[arg(long, global = true, value_name = "PATH", requires = "redact")]
+    redact_map: Option<PathBuf>,
+    
+    /// Assume this IAM role (arn:aws:iam::ACCOUNT:role/NAME) and run every AWS call as it
+    #[arg(long, global = true, value_name = "ARN", value_parser = parse_role_arn)]
+    assume_role: Option<String>,
+    
+    /// External ID the role's trust policy requires
+    #[arg(long, global = true, requires = "assume_role")]
+    external_id: Option<String>,
+    
+    /// Session name recorded in the target account's CloudTrail [default: netkit]
+    #[arg(long, global = true, requires = "assume_role")]
+    session_name: Option<String>,
+    
+    /// Stream open ports, found hosts, VPCs, and compliance issues as JSON lines to this file (`-` for stdout, moving the rest of the output to stderr)
+    #[arg(long, global = true, value_name = "PATH")]
+    events: Option<PathBuf>,
+    
+    /// Write generated files (DOT graphs, snapshots) here as <command>-<region>-<timestamp>.<ext>, creating it if missing
+    #[arg(long, global = true, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -20,9 +123,51 @@ enum Commands {
         /// Target IP or hostname
         target: String,
         
-        /// Number of packets
-        #[arg(short, long, default_value = "4")]
+        /// Number of packets (1-10000; use --continuous to ping until Ctrl-C)
+        #[arg(short, long, default_value = "4", value_parser = parse_ping_count)]
         count: u32,
+        
+        /// Ping until Ctrl-C, showing rolling loss and RTT
+        #[arg(long)]
+        continuous: bool,
+        
+        /// Append this run's loss and RTT as a row to a CSV file
+        #[arg(long, value_name = "FILE")]
+        log: Option<PathBuf>,
+    },
+    
+    /// Trace the network path to a host
+    Traceroute {
+        /// Target IP or hostname
+        target: String,
+        
+        /// Maximum number of hops
+        #[arg(short, long, default_value = "30", value_parser = clap::value_parser!(u32).range(1..=255))]
+        max_hops: u32,
+    },
+    
+    /// Resolve DNS records for a name
+    Dns {
+        /// Hostname to resolve
+        name: String,
+        
+        /// Record type to query
+        #[arg(value_enum, ignore_case = true, default_value = "a")]
+        record_type: RecordType,
+        
+        /// Resolver to query instead of the system default
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Discover the path MTU to a host with don't-fragment pings
+    Mtu {
+        /// Target IP or hostname
+        target: String,
+        
+        /// Largest MTU to try
+        #[arg(long, default_value = "1500")]
+        max: u32,
     },
     
     /// Show network interfaces
@@ -36,9 +181,37 @@ enum Commands {
         /// Target IP
         target: String,
         
-        /// Port to scan
+        /// Port(s) to scan, comma-separated, with low-high ranges (e.g. 22,3389,8000-8010)
+        #[arg(short, long, visible_alias = "ports", required = true, value_delimiter = ',', value_parser = scan::PortRange::parse)]
+        port: Vec<scan::PortRange>,
+        
+        /// Connect timeout in milliseconds [default: 3000]
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..=60_000))]
+        timeout: Option<u64>,
+        
+        /// Exit with code 1 if any scanned port is open (for CI)
+        #[arg(long)]
+        fail_if_open: bool,
+        
+        /// Relay probes through a SOCKS5 proxy (socks5://[user:pass@]host:port), e.g. an `ssh -D` tunnel to a bastion
+        #[arg(long, value_name = "URI", value_parser = scan::Socks5Proxy::parse)]
+        proxy: Option<scan::Socks5Proxy>,
+        
+        /// Probe only the first address the target resolves to, instead of every one
+        #[arg(long, conflicts_with = "proxy")]
+        first_only: bool,
+        
+        /// List only open ports (also filters the JSON results)
+        #[arg(long)]
+        only_open: bool,
+        
+        /// Probe at most this many address/port pairs
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        limit: Option<u64>,
+        
+        /// Don't ask before probing more than 1024 address/port pairs
         #[arg(short, long)]
-        port: u16,
+        yes: bool,
     },
     
     /// Discover hosts on local network
@@ -46,51 +219,233 @@ enum Commands {
         /// Network to scan (e.g., 172.31.64.0/24)
         #[arg(short, long)]
         network: Option<String>,
+        
+        /// Number of hosts probed in parallel [default: 32]
+        #[arg(long)]
+        concurrency: Option<usize>,
+        
+        /// List MAC/IP pairs from the ARP neighbor table instead of probing TCP ports (Linux)
+        #[arg(long)]
+        arp: bool,
+        
+        /// Probe at most this many hosts
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "arp")]
+        limit: Option<u64>,
+        
+        /// Don't ask before probing more than 1024 hosts
+        #[arg(short, long)]
+        yes: bool,
     },
     
     /// Map AWS VPC topology
     AwsMap {
-        /// AWS Region
-        #[arg(short, long, default_value = "us-east-1")]
-        region: String,
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
         
         /// Export to DOT format (Graphviz)
         #[arg(long)]
         dot: bool,
+        
+        /// Write an HTML report with the diagram rendered by Graphviz's `dot` and a summary of each VPC (named aws-map-<region>-<timestamp>.html without FILE)
+        #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with_all = ["dot", "save", "compact", "watch"])]
+        report: Option<Option<PathBuf>>,
+        
+        /// Also write the gathered topology to a JSON snapshot (named aws-map-<region>-<timestamp>.json without FILE)
+        #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with = "dot")]
+        save: Option<Option<PathBuf>>,
+        
+        /// Only list instances launched within this window (e.g. 7d, 12h) or since a date (2024-06-01)
+        #[arg(long, value_parser = parse_since, conflicts_with = "dot")]
+        since: Option<DateTime<Utc>>,
+        
+        /// Only list instances in this state
+        #[arg(long, value_enum, default_value = "all", conflicts_with = "dot")]
+        instance_state: aws::InstanceState,
+        
+        /// Also list terminated instances (hidden by default)
+        #[arg(long, conflicts_with = "dot")]
+        include_terminated: bool,
+        
+        /// Group subnets under their Availability Zone
+        #[arg(long, conflicts_with = "dot")]
+        by_az: bool,
+        
+        /// One tab-separated line per resource (VPC, SUBNET, ROUTE-TABLE, INSTANCE, ...) for grep and awk
+        #[arg(long, conflicts_with_all = ["dot", "by_az"])]
+        compact: bool,
+        
+        /// Cut lines to this many columns [default: terminal width, at most 70]
+        #[arg(long, value_parser = clap::value_parser!(u16).range(40..), conflicts_with_all = ["dot", "compact"])]
+        width: Option<u16>,
+        
+        /// Draw with plain ASCII (+-|) instead of Unicode box characters and emoji
+        #[arg(long, conflicts_with_all = ["dot", "compact"])]
+        ascii: bool,
+        
+        /// Refetch and redraw every SECS seconds, listing what changed since the last poll
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["dot", "compact", "save"])]
+        watch: Option<u64>,
+    },
+    
+    /// Everything about one VPC: subnets, routes, gateways, security groups, endpoints
+    DescribeVpc {
+        /// VPC ID (e.g., vpc-0123456789abcdef0)
+        vpc_id: String,
+        
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// Subnet utilization percentage highlighted as near exhaustion
+        #[arg(long, default_value = "90")]
+        threshold: f64,
+    },
+    
+    /// Explain how an instance reaches the internet (subnet → route table → gateway)
+    InstancePath {
+        /// Instance ID (e.g., i-0123456789abcdef0)
+        instance_id: String,
+        
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+    },
+    
+    /// Test a TCP connection from inside an instance, run through SSM (exits 1 unless it connects)
+    CheckFrom {
+        /// Instance to test from (needs a running SSM agent)
+        instance_id: String,
+        
+        /// Host name or IP to connect to
+        target: String,
+        
+        /// TCP port
+        #[arg(value_parser = clap::value_parser!(u16).range(1..))]
+        port: u16,
+        
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// Seconds the instance waits for the connection
+        #[arg(long, default_value = "5", value_parser = clap::value_parser!(u64).range(1..=60))]
+        timeout: u64,
+    },
+    
+    /// Compare two saved topology snapshots
+    TopologyDiff {
+        /// Older snapshot (from aws-map --save)
+        old: PathBuf,
+        
+        /// Newer snapshot
+        new: PathBuf,
     },
     
     /// Analyze security groups
     SecGroups {
-        /// AWS Region
-        #[arg(short, long, default_value = "us-east-1")]
-        region: String,
-        
-        /// VPC ID to filter
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
         #[arg(short, long)]
-        vpc: Option<String>,
+        region: Option<String>,
+        
+        /// VPC ID to filter (repeatable, or comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        vpc: Vec<String>,
+        
+        /// Inbound/outbound rules allowed per group (raise if your quota was increased)
+        #[arg(long, default_value = "60")]
+        rule_quota: usize,
+        
+        /// Show only inbound rules
+        #[arg(long, conflicts_with = "egress_only")]
+        ingress_only: bool,
+        
+        /// Show only outbound rules
+        #[arg(long)]
+        egress_only: bool,
+        
+        /// One line per group with rule counts instead of full rule lists
+        #[arg(long)]
+        summary: bool,
+        
+        /// Score each group by its compliance findings and list the riskiest first
+        #[arg(long)]
+        sort_by_risk: bool,
+        
+        /// With --sort-by-risk, score against this ruleset TOML (risky ports, weights)
+        #[arg(long, value_name = "FILE", requires = "sort_by_risk")]
+        rules: Option<PathBuf>,
     },
     
     /// Check security group compliance
     Compliance {
-        /// AWS Region
-        #[arg(short, long, default_value = "us-east-1")]
-        region: String,
-        
-        /// VPC ID to filter
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
         #[arg(short, long)]
-        vpc: Option<String>,
+        region: Option<String>,
         
-        /// Output as JSON
-        #[arg(long)]
-        json: bool,
+        /// VPC ID to filter (repeatable, or comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        vpc: Vec<String>,
         
-        /// Scan all regions
+        /// Scan every region enabled for the account (same as --region all)
         #[arg(long)]
         all_regions: bool,
         
         /// Exit with error code if issues found
         #[arg(long)]
         strict: bool,
+        
+        /// Least severe finding that fails the run [default: high] (implies --strict)
+        #[arg(long, value_enum, ignore_case = true, value_name = "SEVERITY")]
+        fail_on: Option<rules::Severity>,
+        
+        /// Exit code when the worst finding is CRITICAL [default: 2] (implies --strict)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..=255))]
+        exit_code_critical: Option<i32>,
+        
+        /// Exit code when the worst finding is HIGH [default: 1] (implies --strict)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..=255))]
+        exit_code_high: Option<i32>,
+        
+        /// Exit code when the worst finding is MEDIUM and --fail-on includes it [default: 1] (implies --strict)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..=255))]
+        exit_code_medium: Option<i32>,
+        
+        /// Exit code when the worst finding is LOW and --fail-on includes it [default: 1] (implies --strict)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..=255))]
+        exit_code_low: Option<i32>,
+        
+        /// Also upload the JSON report to this S3 prefix (e.g. s3://bucket/reports/)
+        #[arg(long, value_name = "S3_URI")]
+        upload: Option<String>,
+        
+        /// TOML ruleset overriding the built-in risky ports and watched sources
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+        
+        /// Also treat this port as risky (repeatable, e.g. 8080:HTTP-alt)
+        #[arg(long, value_name = "PORT[:NAME]", value_parser = parse_risky_port)]
+        risky_port: Vec<rules::RiskyPort>,
+        
+        /// Check only the --risky-port ports instead of adding them to the ruleset's list
+        #[arg(long, requires = "risky_port")]
+        only_ports: bool,
+        
+        /// Annotate public sources with their WHOIS owner, ASN, and country
+        #[arg(long)]
+        whois: bool,
+        
+        /// Run this command as an extra check: security groups as JSON on stdin, findings as JSON on stdout (repeatable)
+        #[arg(long, value_name = "COMMAND")]
+        custom_check: Vec<String>,
+        
+        /// With --output prometheus, write metrics to this file for node_exporter's textfile collector
+        #[arg(long, value_name = "PATH")]
+        textfile: Option<PathBuf>,
+        
+        /// Arrange the findings table by source CIDR, then by group (table output only)
+        #[arg(long, value_enum, value_name = "KEY")]
+        group_by: Option<aws::GroupBy>,
     },
     
     /// Compare two VPCs
@@ -101,16 +456,127 @@ enum Commands {
         /// Second VPC ID
         vpc2: String,
         
-        /// AWS Region
-        #[arg(short, long, default_value = "us-east-1")]
-        region: String,
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// AWS Region of the second VPC (defaults to --region)
+        #[arg(long)]
+        region2: Option<String>,
+    },
+    
+    /// Report subnet IP utilization
+    SubnetUsage {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// VPC ID to filter (repeatable, or comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        vpc: Vec<String>,
+        
+        /// Utilization percentage flagged as near exhaustion
+        #[arg(long, default_value = "90")]
+        threshold: f64,
+    },
+    
+    /// Find unattached network interfaces
+    Orphans {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// VPC ID to filter (repeatable, or comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        vpc: Vec<String>,
+    },
+    
+    /// List public IPs and Elastic IPs with the ports they expose to 0.0.0.0/0, most exposed first
+    Exposure {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// VPC ID to filter (repeatable, or comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        vpc: Vec<String>,
+        
+        /// TOML ruleset whose risky ports name the services open to the internet
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+    },
+    
+    /// Look up who owns a public IP or CIDR (org, ASN, country)
+    Whois {
+        /// IP address or CIDR (its base address is looked up)
+        ip_or_cidr: String,
+    },
+    
+    /// Find VPCs whose CIDR blocks overlap
+    Overlaps {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+    },
+    
+    /// Check that peered and transit-gateway-connected VPCs route back to each other
+    RoutingCheck {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
     },
     
     /// Estimate monthly AWS costs
     Cost {
-        /// AWS Region
-        #[arg(short, long, default_value = "us-east-1")]
-        region: String,
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// Include NAT/TGW data processing from the last 30 days of CloudWatch metrics
+        #[arg(long)]
+        with_data_transfer: bool,
+        
+        /// Fetch region-accurate rates from the AWS Pricing API (cached locally)
+        #[arg(long)]
+        live_pricing: bool,
+        
+        /// Which instances to count; stopped ones are listed without compute cost
+        #[arg(long, value_enum, default_value = "running")]
+        instance_state: aws::InstanceState,
+        
+        /// Sum costs across every region enabled for the account (same as --region all)
+        #[arg(long)]
+        all_regions: bool,
+        
+        /// With --output prometheus, write metrics to this file for node_exporter's textfile collector
+        #[arg(long, value_name = "PATH")]
+        textfile: Option<PathBuf>,
+        
+        /// With --output csv, append rows to this file, writing the header only when it is new
+        #[arg(long, value_name = "FILE")]
+        append: Option<PathBuf>,
+    },
+    
+    /// Flat list of every VPC, subnet, route table, instance, security group, gateway, and endpoint
+    Inventory {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// Same as --output json
+        #[arg(long, conflicts_with = "output")]
+        json: bool,
+    },
+    
+    /// One-screen overview of inventory, cost, and compliance
+    Summary {
+        /// AWS Region [default: AWS_REGION, AWS_DEFAULT_REGION, the aws CLI's region, or us-east-1]
+        #[arg(short, long)]
+        region: Option<String>,
+        
+        /// TOML ruleset to count compliance findings against, as with `compliance --rules`
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
     },
     
     /// Calculate subnet splits
@@ -121,36 +587,124 @@ enum Commands {
         /// Number of subnets to create
         #[arg(short, long)]
         count: usize,
+        
+        /// Longest prefix the split may produce (AWS subnets stop at /28)
+        #[arg(long, default_value = "28", value_parser = clap::value_parser!(u32).range(0..=32))]
+        max_prefix: u32,
+        
+        /// Count usable hosts the way EC2 does (5 reserved per subnet instead of 2)
+        #[arg(long)]
+        aws: bool,
+    },
+    
+    /// Check whether two CIDRs (or a CIDR and an IP) nest, overlap, or are disjoint
+    Cidr {
+        /// First CIDR or IP (e.g., 10.0.0.0/16)
+        a: String,
+        
+        /// Second CIDR or IP (e.g., 10.0.4.0/22 or 10.0.4.17)
+        b: String,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    if cli.quiet && cli.verbose > 0 {
+        return Err(anyhow::anyhow!("--quiet and --verbose can't be used together"));
+    }
+    log::init(cli.quiet, cli.verbose, cli.debug);
+    let config = Config::load()?;
+    let format = if matches!(cli.command, Commands::Inventory { json: true, .. }) {
+        OutputFormat::Json
+    } else {
+        cli.output.or(config.output).unwrap_or_default()
+    };
+    require_supported(&cli.command, format)?;
+    
+    // Child `aws` processes pick the profile up from the environment
+    if let Some(profile) = cli.profile.as_ref().or(config.profile.as_ref()) {
+        std::env::set_var("AWS_PROFILE", profile);
+    }
+    
+    if let Some(dir) = cli.input_dir.as_ref().filter(|dir| !dir.is_dir()) {
+        return Err(anyhow::anyhow!("--input-dir {} is not a directory", dir.display()));
+    }
+    
+    awscli::configure(awscli::Settings {
+        cache_ttl: cli.cache_ttl.or(config.cache_ttl).map(Duration::from_secs),
+        // A watch has to see changes, so it never reads cached responses
+        refresh: cli.refresh || matches!(cli.command, Commands::AwsMap { watch: Some(_), .. }),
+        tags: cli.tag,
+        dry_run: cli.dry_run,
+        timeout: cli.aws_timeout.map(Duration::from_secs),
+        input_dir: cli.input_dir,
+        assume_role: cli.assume_role.map(|role_arn| awscli::AssumeRole {
+            role_arn,
+            external_id: cli.external_id,
+            session_name: cli.session_name.unwrap_or_else(|| "netkit".to_string()),
+        }),
+    });
+    artifact::configure(cli.output_dir.or(config.output_dir.clone()));
+    let _timing = cli.timing.then(timing::enable);
+    if let Some(target) = &cli.events {
+        events::enable(target)?;
+    }
+    let _legend = if cli.redact { Some(redact::enable(cli.redact_map)?) } else { None };
+    
+    // Color only when writing to a terminal, unless explicitly forced
+    let is_tty = std::io::stdout().is_terminal();
+    let force_color = std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| v != "0");
+    if cli.no_color || std::env::var_os("NO_COLOR").is_some() || (!is_tty && !force_color) {
+        colored::control::set_override(false);
+    }
     
     match cli.command {
-        Commands::Ping { target, count } => {
-            println!("{}", format!("🏓 Pinging {}...", target).cyan().bold());
-            println!();
+        Commands::Ping { target, count, continuous, log } => {
+            if !format.is_structured() {
+                println!("{}", format!("🏓 Pinging {}...", target).cyan().bold());
+                println!();
+            }
+            
+            if continuous {
+                let stats = ping_continuous(&target, !format.is_structured(), is_tty)?;
+                if format.is_structured() {
+                    output::print(format, &stats)?;
+                }
+                if let Some(path) = &log {
+                    stats.append_csv(path)?;
+                }
+                return Ok(());
+            }
             
             let output = Command::new("ping")
-                .arg("-c")
+                .arg(sysnet::PING_COUNT_FLAG)
                 .arg(count.to_string())
                 .arg(&target)
                 .output()?;
             
             let stdout = String::from_utf8_lossy(&output.stdout);
+            let stats = PingStats::parse(&target, &stdout);
+            
+            if let Some(path) = &log {
+                stats.append_csv(path)?;
+            }
+            
+            if format.is_structured() {
+                output::print(format, &stats)?;
+                return Ok(());
+            }
             
             // Parse and colorize output
             for line in stdout.lines() {
-                if line.contains("bytes from") {
+                if is_ping_reply(line) {
                     println!("{}", line.green());
-                } else if line.contains("packet loss") {
-                    if line.contains("0% packet loss") {
+                } else if line.contains("packet loss") || line.contains("% loss") {
+                    if stats.loss_percent == 0.0 {
                         println!("{}", line.green().bold());
                     } else {
                         println!("{}", line.yellow().bold());
                     }
-                } else if line.contains("rtt") {
+                } else if line.contains("rtt") || line.contains("Minimum =") {
                     println!("{}", line.cyan());
                 } else {
                     println!("{}", line);
@@ -158,246 +712,1185 @@ fn main() -> Result<()> {
             }
         }
         
-        Commands::Interfaces => {
-            println!("{}", "🌐 Network Interfaces".cyan().bold());
-            println!("{}", "═".repeat(60).bright_black());
+        Commands::Traceroute { target, max_hops } => {
+            if !format.is_structured() {
+                println!("{}", format!("🛤️  Tracing route to {} ({} hops max)...", target, max_hops).cyan().bold());
+                println!();
+            }
             
-            let output = Command::new("ip")
-                .arg("addr")
-                .arg("show")
-                .output()?;
+            let (program, hops_flag) = if cfg!(windows) { ("tracert", "-h") } else { ("traceroute", "-m") };
+            let output = Command::new(program)
+                .arg(hops_flag)
+                .arg(max_hops.to_string())
+                .arg(&target)
+                .output()
+                .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", program, e))?;
             
             let stdout = String::from_utf8_lossy(&output.stdout);
+            let hops: Vec<Hop> = stdout.lines().filter_map(Hop::parse).collect();
             
-            for line in stdout.lines() {
-                if line.contains(": <") {
-                    println!("{}", line.yellow().bold());
-                } else if line.contains("inet ") {
-                    println!("{}", line.green());
-                } else if line.contains("link/") {
-                    println!("{}", line.cyan());
-                } else {
-                    println!("{}", line.bright_black());
-                }
+            if format.is_structured() {
+                output::print(format, &serde_json::json!({
+                    "target": target,
+                    "max_hops": max_hops,
+                    "hops": hops,
+                }))?;
+                return Ok(());
             }
-        }
-        
-        Commands::Routes => {
-            println!("{}", "🗺️  Routing Table".cyan().bold());
-            println!("{}", "═".repeat(60).bright_black());
             
-            let output = Command::new("ip")
-                .arg("route")
-                .arg("show")
-                .output()?;
-            
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            
-            for line in stdout.lines() {
-                if line.contains("default") {
-                    println!("{}", line.yellow().bold());
-                } else if line.contains("proto kernel") {
-                    println!("{}", line.green());
-                } else {
-                    println!("{}", line.cyan());
+            for hop in &hops {
+                print!("{:>3}  ", hop.hop.to_string().bright_black());
+                
+                if hop.is_timeout() {
+                    println!("{}", "* * *".red());
+                    continue;
+                }
+                
+                let host = hop.host.as_deref().unwrap_or("unknown");
+                match hop.address.as_deref().filter(|addr| *addr != host) {
+                    Some(addr) => print!("{} {}", host.green(), format!("({})", addr).bright_black()),
+                    None => print!("{}", host.green()),
+                }
+                
+                for rtt in &hop.rtts_ms {
+                    match rtt {
+                        Some(ms) => print!("  {}", format!("{:.3} ms", ms).cyan()),
+                        None => print!("  {}", "*".red()),
+                    }
                 }
+                println!();
             }
         }
         
-        Commands::Scan { target, port } => {
-            println!("{}", format!("🔍 Scanning {}:{}...", target, port).cyan().bold());
+        Commands::Dns { name, record_type, server } => {
+            let records = resolve_dns(&name, record_type, server.as_deref())?;
+            
+            if format.is_structured() {
+                output::print(format, &serde_json::json!({
+                    "name": name,
+                    "record_type": record_type.as_str(),
+                    "server": server,
+                    "records": records,
+                }))?;
+                return Ok(());
+            }
             
-            use std::net::{TcpStream, ToSocketAddrs};
-            use std::time::Duration;
+            let via = server.as_deref().unwrap_or("system resolver");
+            println!("{}", format!("📖 {} records for {} (via {})", record_type.as_str(), name, via).cyan().bold());
+            println!("{}", "═".repeat(60).bright_black());
             
-            let addr = format!("{}:{}", target, port);
-            let socket_addr = addr.to_socket_addrs()?.next()
-                .ok_or_else(|| anyhow::anyhow!("Invalid address"))?;
+            if records.is_empty() {
+                println!("{}", "No records found".yellow());
+                return Ok(());
+            }
             
-            match TcpStream::connect_timeout(&socket_addr, Duration::from_secs(3)) {
-                Ok(_) => {
-                    println!("{} Port {} is {}", "✅".green(), port, "OPEN".green().bold());
-                }
-                Err(_) => {
-                    println!("{} Port {} is {}", "❌".red(), port, "CLOSED".red().bold());
-                }
+            println!("{:<30} {:>7}  {:<6} {}", "NAME".bold(), "TTL".bold(), "TYPE".bold(), "DATA".bold());
+            for record in &records {
+                let ttl = record.ttl.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{:<30} {:>7}  {:<6} {}",
+                    record.name.cyan(),
+                    ttl.bright_black(),
+                    record.record_type.yellow(),
+                    record.data.green());
             }
         }
         
-        Commands::Discover { network } => {
-            println!("{}", "🔍 Discovering network hosts...".cyan().bold());
-            println!("{}", "═".repeat(60).bright_black());
+        Commands::Mtu { target, max } => {
+            let human = !format.is_structured();
+            if human {
+                println!("{}", format!("📏 Discovering path MTU to {} (max {})...", target, max).cyan().bold());
+                println!();
+            }
             
-            // Get local network if not specified
-            let net = if let Some(n) = network {
-                n
-            } else {
-                // Auto-detect from ip addr
-                let output = Command::new("ip")
-                    .arg("addr")
-                    .arg("show")
-                    .output()?;
-                
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let mut local_net = String::new();
-                
-                for line in stdout.lines() {
-                    if line.contains("inet ") && !line.contains("127.0.0.1") {
-                        if let Some(ip_part) = line.split_whitespace().nth(1) {
-                            local_net = ip_part.to_string();
-                            break;
-                        }
-                    }
-                }
-                
-                if local_net.is_empty() {
-                    return Err(anyhow::anyhow!("Could not detect local network"));
+            let max_payload = max.checked_sub(ICMP_OVERHEAD)
+                .ok_or_else(|| anyhow::anyhow!("--max must be at least {}", ICMP_OVERHEAD))?;
+            
+            let mut probes = Vec::new();
+            let mut probe = |size: u32| -> Result<bool> {
+                let result = probe_df(&target, size)?;
+                if human {
+                    let status = match result {
+                        Probe::Ok => "✅ passed".green(),
+                        Probe::TooBig => "❌ message too long".red(),
+                        Probe::Lost => "❌ no reply".red(),
+                    };
+                    println!("  {} bytes (MTU {})  {}", size.to_string().cyan(), size + ICMP_OVERHEAD, status);
                 }
-                
-                local_net
+                probes.push(serde_json::json!({ "payload": size, "passed": result == Probe::Ok }));
+                Ok(result == Probe::Ok)
             };
             
-            println!("Network: {}", net.yellow());
-            println!();
-            
-            // Parse CIDR
-            let parts: Vec<&str> = net.split('/').collect();
-            if parts.len() != 2 {
-                return Err(anyhow::anyhow!("Invalid CIDR format"));
+            // Smallest payload first; if that fails the host is unreachable, not an MTU problem
+            if !probe(0)? {
+                return Err(anyhow::anyhow!("{} did not answer ping; cannot measure MTU", target));
             }
             
-            let base_ip = parts[0];
-            let ip_parts: Vec<&str> = base_ip.split('.').collect();
-            if ip_parts.len() != 4 {
-                return Err(anyhow::anyhow!("Invalid IP format"));
+            // Binary search for the largest payload that gets through unfragmented
+            let (mut low, mut high) = (0, max_payload);
+            while low < high {
+                let mid = (low + high).div_ceil(2);
+                if probe(mid)? {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
             }
             
-            let base = format!("{}.{}.{}", ip_parts[0], ip_parts[1], ip_parts[2]);
-            
-            println!("{}", "Scanning hosts (this may take a moment)...".bright_black());
-            println!();
-            
-            use std::net::TcpStream;
-            use std::time::Duration;
-            
-            let mut found = 0;
-            
-            // Quick scan - just check if host responds on common ports
-            for i in 1..255 {
-                let ip = format!("{}.{}", base, i);
-                
-                // Try SSH port (22) as a quick check
-                let addr = format!("{}:22", ip);
-                if let Ok(socket_addr) = addr.parse::<std::net::SocketAddr>() {
-                    if TcpStream::connect_timeout(&socket_addr, Duration::from_millis(100)).is_ok() {
-                        println!("{} {} {}", "✅".green(), ip.cyan(), "(SSH open)".bright_black());
-                        found += 1;
-                    }
-                }
+            let path_mtu = low + ICMP_OVERHEAD;
+            if !human {
+                output::print(format, &serde_json::json!({
+                    "target": target,
+                    "path_mtu": path_mtu,
+                    "largest_payload": low,
+                    "probes": probes,
+                }))?;
+                return Ok(());
             }
             
             println!();
             println!("{}", "═".repeat(60).bright_black());
-            println!("Found {} active host(s)", found.to_string().green().bold());
-            println!("{}", "═".repeat(60).bright_black());
-        }
-        
-        Commands::AwsMap { region, dot } => {
-            if dot {
-                aws::export_dot(&region)?;
+            let verdict = format!("Path MTU: {} bytes (payload {})", path_mtu, low);
+            if path_mtu < max {
+                println!("{}", verdict.yellow().bold());
             } else {
-                aws::map_vpc_topology(&region)?;
+                println!("{}", verdict.green().bold());
             }
         }
         
-        Commands::SecGroups { region, vpc } => {
-            aws::analyze_security_groups(&region, vpc.as_deref())?;
+        Commands::Interfaces => {
+            sysnet::show_interfaces(format)?;
         }
         
-        Commands::Compliance { region, vpc, json, all_regions, strict } => {
-            let exit_code = if all_regions {
-                aws::check_compliance_all_regions(vpc.as_deref(), json, strict)?
-            } else {
-                aws::check_compliance(&region, vpc.as_deref(), json, strict)?
-            };
-            
-            if strict && exit_code != 0 {
-                std::process::exit(exit_code);
+        Commands::Routes => {
+            sysnet::show_routes(format)?;
+        }
+        
+        Commands::Scan { target, port: ranges, timeout, fail_if_open, proxy, first_only, only_open, limit, yes } => {
+            let quiet = cli.quiet;
+            let port_list = ranges.iter()
+                .map(|r| if r.first == r.last { r.first.to_string() } else { format!("{}-{}", r.first, r.last) })
+                .collect::<Vec<_>>()
+                .join(",");
+            let ports = scan::expand_ports(&ranges);
+            if !format.is_structured() && !quiet {
+                println!("{}", format!("🔍 Scanning {}:{}...", target, port_list).cyan().bold());
+                if let Some(proxy) = &proxy {
+                    println!("{}", format!("   via {}", proxy).bright_black());
+                }
+            }
+            
+            use std::net::ToSocketAddrs;
+            
+            let timeout = Duration::from_millis(config.scan_timeout_ms(timeout));
+            
+            let results: Vec<scan::ScanResult> = if let Some(proxy) = &proxy {
+                let ports = &ports[..capped(ports.len(), limit, "ports", format)];
+                confirm_sweep(ports.len(), "ports", yes)?;
+                
+                scan::scan_all_via(proxy, &target, ports, timeout)?
+            } else {
+                // Dual-stack and round-robin names resolve to several hosts; probe each
+                let mut ips: Vec<std::net::IpAddr> = Vec::new();
+                for addr in (target.as_str(), 0).to_socket_addrs()? {
+                    if !ips.contains(&addr.ip()) {
+                        ips.push(addr.ip());
+                    }
+                }
+                if ips.is_empty() {
+                    return Err(anyhow::anyhow!("{} did not resolve to any address", target));
+                }
+                if first_only {
+                    ips.truncate(1);
+                }
+                
+                let mut addrs: Vec<(u16, std::net::SocketAddr)> = ips.iter()
+                    .flat_map(|ip| ports.iter().map(|&port| (port, std::net::SocketAddr::new(*ip, port))))
+                    .collect();
+                addrs.truncate(capped(addrs.len(), limit, "address/port pairs", format));
+                confirm_sweep(addrs.len(), "address/port pairs", yes)?;
+                
+                scan::scan_all(&target, &addrs, timeout)
+            };
+            
+            for result in results.iter().filter(|r| r.status == scan::PortStatus::Open) {
+                events::emit("port_open", result);
+            }
+            
+            // Name the address only when there is more than one to tell apart
+            let per_address = results.len() > ports.len();
+            let count = |status| results.iter().filter(|r| r.status == status).count();
+            let (open_count, closed_count, filtered_count) =
+                (count(scan::PortStatus::Open), count(scan::PortStatus::Closed), count(scan::PortStatus::Filtered));
+            let shown: Vec<&scan::ScanResult> = results.iter()
+                .filter(|r| !only_open || r.status == scan::PortStatus::Open)
+                .collect();
+            
+            if format.is_structured() {
+                // A single port keeps the original one-object shape; --only-open always gives a list
+                match shown.as_slice() {
+                    [result] if !only_open && results.len() == 1 => output::print(format, result)?,
+                    _ => output::print(format, &shown)?,
+                }
+            } else {
+                if !quiet {
+                    for result in &shown {
+                        let on = match result.address.parse::<std::net::SocketAddr>() {
+                            Ok(addr) if per_address => format!(" on {}", addr.ip().to_string().bright_black()),
+                            _ => String::new(),
+                        };
+                        println!("{} Port {}{} is {}", result.status.icon(), result.port, on, result.status.label());
+                    }
+                }
+                if quiet || results.len() > 1 {
+                    println!("Scanned {} port(s) on {}: {} open, {} closed, {} filtered", 
+                        results.len(),
+                        target,
+                        open_count.to_string().green().bold(),
+                        closed_count.to_string().red(),
+                        filtered_count.to_string().yellow()
+                    );
+                }
+            }
+            
+            let open: Vec<String> = results.iter()
+                .filter(|r| r.status == scan::PortStatus::Open)
+                .map(|r| if per_address { r.address.clone() } else { r.port.to_string() })
+                .collect();
+            
+            if fail_if_open && !open.is_empty() {
+                if !format.is_structured() {
+                    let open = open.join(", ");
+                    println!("{} Open port(s) found: {}", "✗".red(), open.red().bold());
+                }
+                exit(1);
+            }
+        }
+        
+        Commands::Discover { network, concurrency, arp, limit, yes } => {
+            let human = !format.is_structured();
+            if human {
+                println!("{}", "🔍 Discovering network hosts...".cyan().bold());
+                println!("{}", "═".repeat(60).bright_black());
+            }
+            
+            // Get local network if not specified
+            let explicit = network.is_some();
+            let net = match network {
+                Some(n) => n,
+                None => sysnet::local_network()?,
+            };
+            
+            if human {
+                println!("Network: {}", net.yellow());
+                println!();
+            }
+            
+            if arp {
+                return discover_arp(&net, format);
+            }
+            
+            // An auto-detected network only covers the host's own /24; a given one is swept whole
+            let (base, prefix) = cidr::parse_cidr(&net)?;
+            let prefix = if explicit { prefix } else { prefix.max(24) };
+            let (first, last) = cidr::range(base, prefix);
+            let (first, last) = if prefix < 31 { (first + 1, last - 1) } else { (first, last) };
+            let total = u64::from(last - first) + 1;
+            let host_count = capped(total as usize, limit, "hosts", format) as u32;
+            confirm_sweep(host_count as usize, "hosts", yes)?;
+            
+            if human {
+                println!("{}", "Scanning hosts (this may take a moment)...".bright_black());
+                println!();
             }
+            
+            use std::net::TcpStream;
+            use std::sync::atomic::{AtomicU32, Ordering};
+            use std::sync::Mutex;
+            
+            let next_host = AtomicU32::new(0);
+            let responders = Mutex::new(Vec::new());
+            
+            // Quick scan - just check if host responds on common ports
+            std::thread::scope(|scope| {
+                for _ in 0..config.scan_concurrency(concurrency) {
+                    scope.spawn(|| loop {
+                        let i = next_host.fetch_add(1, Ordering::Relaxed);
+                        if i >= host_count {
+                            break;
+                        }
+                        
+                        // Try SSH port (22) as a quick check
+                        let host = first + i;
+                        let socket_addr = std::net::SocketAddr::from((std::net::Ipv4Addr::from(host), 22));
+                        if TcpStream::connect_timeout(&socket_addr, Duration::from_millis(100)).is_ok() {
+                            events::emit("host_found", &serde_json::json!({ "ip": cidr::format_ip(host), "port": 22 }));
+                            responders.lock().unwrap().push(host);
+                        }
+                    });
+                }
+            });
+            
+            let mut responders = responders.into_inner().unwrap();
+            responders.sort();
+            
+            let found: Vec<String> = responders.iter().map(|host| cidr::format_ip(*host)).collect();
+            if human {
+                for ip in &found {
+                    println!("{} {} {}", "✅".green(), ip.cyan(), "(SSH open)".bright_black());
+                }
+            }
+            
+            if !human {
+                output::print(format, &serde_json::json!({
+                    "network": net,
+                    "hosts": found,
+                    "found": found.len(),
+                }))?;
+                return Ok(());
+            }
+            
+            println!();
+            println!("{}", "═".repeat(60).bright_black());
+            println!("Found {} active host(s)", found.len().to_string().green().bold());
+            println!("{}", "═".repeat(60).bright_black());
         }
         
-        Commands::Diff { vpc1, vpc2, region } => {
-            aws::diff_vpcs(&region, &vpc1, &vpc2)?;
+        Commands::DescribeVpc { vpc_id, region, threshold } => {
+            let region = aws_region(&config, region)?;
+            aws::describe_vpc(&region, &vpc_id, threshold, format)?;
         }
         
-        Commands::Cost { region } => {
-            aws::estimate_costs(&region)?;
+        Commands::InstancePath { instance_id, region } => {
+            let region = aws_region(&config, region)?;
+            aws::instance_path(&region, &instance_id, format)?;
         }
         
-        Commands::Subnet { cidr, count } => {
-            calculate_subnets(&cidr, count)?;
+        Commands::CheckFrom { instance_id, target, port, region, timeout } => {
+            let region = aws_region(&config, region)?;
+            if !aws::check_from(&region, &instance_id, &target, port, timeout, format)? {
+                exit(1);
+            }
+        }
+        
+        Commands::AwsMap { region, dot, report, save, since, instance_state, include_terminated, by_az, compact, width, ascii, watch } => {
+            let region = aws_region(&config, region)?;
+            let filter = aws::InstanceFilter { since, state: instance_state, include_terminated };
+            if dot {
+                aws::export_dot(&region)?;
+            } else if let Some(report) = report {
+                let path = match report {
+                    Some(path) => path,
+                    None => artifact::path("aws-map", &region, "html")?,
+                };
+                aws::export_report(&region, &path, filter)?;
+            } else if let Some(secs) = watch {
+                if format.is_structured() {
+                    return Err(anyhow::anyhow!("--watch only draws the table view; drop --output"));
+                }
+                let layout = output::Layout::new(width.map(usize::from), ascii);
+                aws::watch_vpc_topology(&region, Duration::from_secs(secs), filter, by_az, layout)?;
+            } else {
+                let save = match save {
+                    Some(Some(path)) => Some(path),
+                    Some(None) => Some(artifact::path("aws-map", &region, "json")?),
+                    None => None,
+                };
+                let layout = output::Layout::new(width.map(usize::from), ascii);
+                aws::map_vpc_topology(&region, save.as_deref(), filter, by_az, compact, layout, format)?;
+            }
+        }
+        
+        Commands::TopologyDiff { old, new } => {
+            aws::diff_topology_snapshots(&old, &new, format)?;
+        }
+        
+        Commands::SecGroups { region, vpc, rule_quota, ingress_only, egress_only, summary, sort_by_risk, rules } => {
+            let region = aws_region(&config, region)?;
+            let rules = if sort_by_risk { Some(rules::Ruleset::load(rules.as_deref())?) } else { None };
+            aws::analyze_security_groups(&region, vpc_filter(&vpc).as_deref(), rule_quota, ingress_only, egress_only, summary, rules.as_ref(), format)?;
+        }
+        
+        Commands::Compliance {
+            region, vpc, all_regions, strict, fail_on, exit_code_critical, exit_code_high, exit_code_medium, exit_code_low,
+            upload, rules, risky_port, only_ports, whois, custom_check, textfile, group_by,
+        } => {
+            require_prometheus(textfile.as_deref(), format)?;
+            let all_regions = all_regions || region.as_deref() == Some("all");
+            let region = if all_regions { config.region(region) } else { aws_region(&config, region)? };
+            let mut rules = rules::Ruleset::load(rules.as_deref())?;
+            if only_ports {
+                rules.risky_ports.clear();
+            }
+            rules.risky_ports.extend(risky_port);
+            rules.custom_checks.extend(custom_check);
+            let defaults = rules::ExitCodes::default();
+            let exit_codes = rules::ExitCodes {
+                fail_on: fail_on.unwrap_or(defaults.fail_on),
+                critical: exit_code_critical.unwrap_or(defaults.critical),
+                high: exit_code_high.unwrap_or(defaults.high),
+                medium: exit_code_medium.unwrap_or(defaults.medium),
+                low: exit_code_low.unwrap_or(defaults.low),
+            };
+            let strict = strict || fail_on.is_some()
+                || [exit_code_critical, exit_code_high, exit_code_medium, exit_code_low].iter().any(Option::is_some);
+            
+            let worst = if all_regions {
+                aws::check_compliance_all_regions(vpc_filter(&vpc).as_deref(), &rules, whois, group_by, upload.as_deref(), format, textfile.as_deref())?
+            } else {
+                aws::check_compliance(&region, vpc_filter(&vpc).as_deref(), &rules, whois, group_by, upload.as_deref(), format, textfile.as_deref())?
+            };
+            
+            // A cut-short scan always reports the worst severity it saw
+            let exit_code = exit_codes.code(worst);
+            if (strict || aws::scan_interrupted()) && exit_code != 0 {
+                exit(exit_code);
+            }
+        }
+        
+        Commands::Diff { vpc1, vpc2, region, region2 } => {
+            let region = aws_region(&config, region)?;
+            let region2 = region2.map(|r| aws_region(&config, Some(r))).transpose()?;
+            aws::diff_vpcs(&region, region2.as_deref().unwrap_or(&region), &vpc1, &vpc2, format)?;
+        }
+        
+        Commands::SubnetUsage { region, vpc, threshold } => {
+            let region = aws_region(&config, region)?;
+            aws::subnet_usage(&region, vpc_filter(&vpc).as_deref(), threshold, format)?;
+        }
+        
+        Commands::Orphans { region, vpc } => {
+            let region = aws_region(&config, region)?;
+            aws::find_orphaned_enis(&region, vpc_filter(&vpc).as_deref(), format)?;
+        }
+        
+        Commands::Exposure { region, vpc, rules } => {
+            let region = aws_region(&config, region)?;
+            let rules = rules::Ruleset::load(rules.as_deref())?;
+            aws::internet_exposure(&region, vpc_filter(&vpc).as_deref(), &rules, format)?;
+        }
+        
+        Commands::Whois { ip_or_cidr } => {
+            let ip = whois::parse_target(&ip_or_cidr)?;
+            
+            let reserved = match ip {
+                std::net::IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local(),
+                std::net::IpAddr::V6(v6) => v6.is_loopback() || (v6.segments()[0] & 0xfe00) == 0xfc00,
+            };
+            if reserved {
+                return Err(anyhow::anyhow!("{} is a private or reserved address with no public owner", ip));
+            }
+            
+            if !format.is_structured() {
+                println!("{}", format!("🔎 WHOIS {}...", ip).cyan().bold());
+                println!("{}", "═".repeat(60).bright_black());
+            }
+            
+            let info = whois::lookup(ip)?;
+            
+            if format.is_structured() {
+                output::print(format, &info)?;
+            } else {
+                let unknown = || "unknown".bright_black();
+                let show = |value: &Option<String>| value.as_deref().map_or_else(unknown, |v| v.normal());
+                
+                println!("Organization: {}", info.org.as_deref().map_or_else(unknown, |v| v.cyan().bold()));
+                println!("Network:      {}", show(&info.network).green());
+                println!("ASN:          {}{}", 
+                    info.asn.as_deref().map_or_else(unknown, |v| format!("AS{}", v.trim_start_matches("AS")).yellow()),
+                    info.as_name.as_deref().map(|n| format!(" ({})", n)).unwrap_or_default().bright_black()
+                );
+                println!("BGP Prefix:   {}", show(&info.bgp_prefix));
+                println!("Country:      {}", show(&info.country));
+                println!("Registry:     {}", show(&info.registry));
+            }
+        }
+        
+        Commands::Overlaps { region } => {
+            let region = aws_region(&config, region)?;
+            aws::find_cidr_overlaps(&region, format)?;
+        }
+        
+        Commands::RoutingCheck { region } => {
+            let region = aws_region(&config, region)?;
+            aws::check_return_routes(&region, format)?;
+        }
+        
+        Commands::Cost { region, with_data_transfer, live_pricing, instance_state, all_regions, textfile, append } => {
+            require_prometheus(textfile.as_deref(), format)?;
+            if append.is_some() && format != OutputFormat::Csv {
+                return Err(anyhow::anyhow!("--append requires --output csv"));
+            }
+            let all_regions = all_regions || region.as_deref() == Some("all");
+            let region = if all_regions { config.region(region) } else { aws_region(&config, region)? };
+            if all_regions {
+                aws::estimate_costs_all_regions(with_data_transfer, live_pricing, instance_state, format, textfile.as_deref(), append.as_deref())?;
+            } else {
+                aws::estimate_costs(&region, with_data_transfer, live_pricing, instance_state, format, textfile.as_deref(), append.as_deref())?;
+            }
+        }
+        
+        Commands::Inventory { region, .. } => {
+            let region = aws_region(&config, region)?;
+            aws::inventory(&region, format)?;
+        }
+        
+        Commands::Summary { region, rules } => {
+            let region = aws_region(&config, region)?;
+            let rules = rules::Ruleset::load(rules.as_deref())?;
+            aws::account_summary(&region, &rules, format)?;
+        }
+        
+        Commands::Subnet { cidr, count, max_prefix, aws } => {
+            calculate_subnets(&cidr, count, max_prefix, aws, format)?;
+        }
+        
+        Commands::Cidr { a, b } => {
+            compare_cidrs(&a, &b, format)?;
         }
     }
     
     Ok(())
 }
 
-fn calculate_subnets(cidr: &str, count: usize) -> Result<()> {
-    println!("{}", "Subnet Calculator".cyan().bold());
-    println!("{}", "═".repeat(70).bright_black());
-    println!("VPC CIDR: {}", cidr.yellow());
-    println!("Subnets: {}", count.to_string().cyan());
+/// `--vpc` values as one comma-separated filter, which the aws CLI reads as
+/// `Values=vpc-1,vpc-2`; duplicates are dropped
+fn vpc_filter(vpcs: &[String]) -> Option<String> {
+    let mut ids: Vec<&str> = Vec::new();
+    for id in vpcs.iter().map(|id| id.trim()).filter(|id| !id.is_empty()) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    (!ids.is_empty()).then(|| ids.join(","))
+}
+
+/// The `--region` flag or configured default, checked for typos before any call
+fn aws_region(config: &Config, flag: Option<String>) -> Result<String> {
+    let region = config.region(flag);
+    aws::validate_region(&region)?;
+    Ok(region)
+}
+
+/// `process::exit` skips destructors, so flush the end-of-run reports first
+fn exit(code: i32) -> ! {
+    timing::finish();
+    if let Err(e) = redact::finish() {
+        eprintln!("Error: {}", e);
+    }
+    std::process::exit(code)
+}
+
+/// CSV and Prometheus are rendered by only a few commands; refuse them up
+/// front rather than after every AWS call has been made
+fn require_supported(command: &Commands, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Csv if !matches!(command, Commands::Cost { .. }) => {
+            Err(anyhow::anyhow!("CSV output is not supported for this command"))
+        }
+        OutputFormat::Prometheus if !matches!(command, Commands::Compliance { .. } | Commands::Cost { .. }) => {
+            Err(anyhow::anyhow!("Prometheus output is only supported by compliance and cost"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn require_prometheus(textfile: Option<&Path>, format: OutputFormat) -> Result<()> {
+    if textfile.is_some() && format != OutputFormat::Prometheus {
+        return Err(anyhow::anyhow!("--textfile requires --output prometheus"));
+    }
+    Ok(())
+}
+
+/// Accept a relative window (`30m`, `12h`, `7d`, `2w`), a date, or an RFC 3339 timestamp
+fn parse_since(raw: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    
+    let usage = || format!("expected e.g. 7d, 12h, or 2024-06-01, got '{}'", raw);
+    let unit = raw.chars().last().ok_or_else(usage)?;
+    let amount: i64 = raw[..raw.len() - unit.len_utf8()].parse().map_err(|_| usage())?;
+    let window = match unit {
+        'm' => TimeDelta::try_minutes(amount),
+        'h' => TimeDelta::try_hours(amount),
+        'd' => TimeDelta::try_days(amount),
+        'w' => TimeDelta::try_weeks(amount),
+        _ => return Err(format!("unknown unit '{}' (use m, h, d, or w)", unit)),
+    }.ok_or_else(usage)?;
+    
+    Ok(Utc::now() - window)
+}
+
+/// Same-segment discovery from the kernel's neighbor table, which also finds
+/// hosts that filter every port
+fn discover_arp(net: &str, format: OutputFormat) -> Result<()> {
+    let human = !format.is_structured();
+    let (base, prefix) = cidr::parse_cidr(net)?;
+    let (first, last) = cidr::range(base, prefix);
+    
+    // Poke every address so hosts not yet in the table get resolved
+    if last - first < arp::MAX_PRIME_HOSTS {
+        if human {
+            println!("{}", "Resolving neighbors...".bright_black());
+            println!();
+        }
+        arp::prime(base, prefix)?;
+        std::thread::sleep(Duration::from_secs(1));
+    } else if human {
+        println!("{}", format!("Network is larger than {} hosts; showing cached neighbors only", arp::MAX_PRIME_HOSTS).yellow());
+        println!();
+    }
+    
+    let neighbors = arp::neighbors(base, prefix)?;
+    for neighbor in &neighbors {
+        events::emit("host_found", neighbor);
+    }
+    
+    if !human {
+        return output::print(format, &serde_json::json!({
+            "network": net,
+            "neighbors": neighbors,
+            "found": neighbors.len(),
+        }));
+    }
+    
+    for n in &neighbors {
+        println!("{} {:<16} {}  {}  {}", 
+            "✅".green(),
+            n.ip.cyan(),
+            n.mac.yellow(),
+            n.vendor.unwrap_or("unknown vendor").bright_black(),
+            format!("({}, {})", n.device, n.state.to_lowercase()).bright_black()
+        );
+    }
+    
     println!();
+    println!("{}", "═".repeat(60).bright_black());
+    println!("Found {} neighbor(s)", neighbors.len().to_string().green().bold());
+    println!("{}", "═".repeat(60).bright_black());
     
-    // Parse CIDR
-    let parts: Vec<&str> = cidr.split('/').collect();
-    if parts.len() != 2 {
-        return Err(anyhow::anyhow!("Invalid CIDR format"));
+    Ok(())
+}
+
+/// Sweeps larger than this need `--yes` or a yes at the prompt
+const CONFIRM_ABOVE: usize = 1024;
+
+/// `count` cut to `--limit`, with a note when that drops targets
+fn capped(count: usize, limit: Option<u64>, what: &str, format: OutputFormat) -> usize {
+    let Some(limit) = limit.map(|l| usize::try_from(l).unwrap_or(usize::MAX)).filter(|l| *l < count) else {
+        return count;
+    };
+    if !format.is_structured() {
+        println!("{}", format!("   --limit: probing the first {} of {} {}", limit, count, what).bright_black());
+    } else {
+        tracing::info!("--limit: probing the first {} of {} {}", limit, count, what);
+    }
+    limit
+}
+
+/// Ask before a large sweep, so a mistyped /8 doesn't hammer a whole network.
+/// Without a terminal to ask on, only `--yes` lets it through.
+fn confirm_sweep(count: usize, what: &str, yes: bool) -> Result<()> {
+    if count <= CONFIRM_ABOVE || yes {
+        return Ok(());
+    }
+    
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("Refusing to probe {} {} without --yes (or cap it with --limit)", count, what));
     }
     
-    let base_ip = parts[0];
-    let prefix: u32 = parts[1].parse()?;
+    eprint!("About to probe {} {}. Continue? [y/N] ", count, what);
+    use std::io::Write;
+    std::io::stderr().flush()?;
     
-    // Calculate new prefix length
-    let bits_needed = (count as f64).log2().ceil() as u32;
-    let new_prefix = prefix + bits_needed;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Aborted"))
+    }
+}
+
+fn parse_role_arn(raw: &str) -> Result<String, String> {
+    let valid = raw.strip_prefix("arn:")
+        .and_then(|rest| rest.split_once(":iam::"))
+        .and_then(|(_, rest)| rest.split_once(":role/"))
+        .is_some_and(|(account, name)| account.len() == 12 && account.chars().all(|c| c.is_ascii_digit()) && !name.is_empty());
     
-    if new_prefix > 28 {
-        return Err(anyhow::anyhow!("Too many subnets - would result in /{} (max /28)", new_prefix));
+    if valid {
+        Ok(raw.to_string())
+    } else {
+        Err(format!("expected a role ARN like arn:aws:iam::123456789012:role/Auditor, got '{}'", raw))
     }
+}
+
+fn parse_tag(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", raw)),
+    }
+}
+
+/// Largest `ping --count`; longer runs belong to `--continuous`
+const MAX_PING_COUNT: u32 = 10_000;
+
+fn parse_ping_count(raw: &str) -> Result<u32, String> {
+    raw.trim().parse::<u32>()
+        .ok()
+        .filter(|count| (1..=MAX_PING_COUNT).contains(count))
+        .ok_or_else(|| format!("count must be 1-{}, got '{}' (use --continuous to ping until Ctrl-C)", MAX_PING_COUNT, raw))
+}
+
+fn parse_risky_port(raw: &str) -> Result<rules::RiskyPort, String> {
+    let (port, name) = match raw.split_once(':') {
+        Some((port, name)) => (port, Some(name.trim())),
+        None => (raw, None),
+    };
     
-    let hosts_per_subnet = 2u32.pow(32 - new_prefix) - 2; // -2 for network and broadcast
+    let port = scan::parse_port(port)?;
     
-    println!("Original: {}", format!("/{} ({} hosts)", prefix, 2u32.pow(32 - prefix) - 2).bright_black());
-    println!("New subnets: {}", format!("/{} ({} hosts each)", new_prefix, hosts_per_subnet).green());
-    println!();
+    Ok(rules::RiskyPort {
+        port: i64::from(port),
+        name: name.filter(|n| !n.is_empty())
+            .map(String::from)
+            .unwrap_or_else(|| format!("port {}", port)),
+    })
+}
+
+#[derive(Debug, Default, Serialize)]
+struct PingStats {
+    target: String,
+    transmitted: u32,
+    received: u32,
+    loss_percent: f64,
+    rtt_min_ms: Option<f64>,
+    rtt_avg_ms: Option<f64>,
+    rtt_max_ms: Option<f64>,
+}
+
+impl PingStats {
+    /// Parse the summary lines printed by the system `ping`
+    fn parse(target: &str, stdout: &str) -> Self {
+        let mut stats = PingStats {
+            target: target.to_string(),
+            ..Default::default()
+        };
+        
+        for line in stdout.lines() {
+            if line.contains("packets transmitted") {
+                // "4 packets transmitted, 4 received, 0% packet loss, time 3004ms"
+                for part in line.split(',') {
+                    let part = part.trim();
+                    let number = part.split_whitespace().next().unwrap_or("");
+                    if part.ends_with("packets transmitted") {
+                        stats.transmitted = number.parse().unwrap_or(0);
+                    } else if part.contains("received") {
+                        stats.received = number.parse().unwrap_or(0);
+                    } else if part.contains("packet loss") {
+                        stats.loss_percent = number.trim_end_matches('%').parse().unwrap_or(0.0);
+                    }
+                }
+            } else if line.contains("Sent =") {
+                // Windows: "    Packets: Sent = 4, Received = 4, Lost = 0 (0% loss),"
+                let count = |key: &str| line.split(',')
+                    .find_map(|part| part.split_once(key))
+                    .and_then(|(_, value)| value.split_whitespace().next()?.parse().ok())
+                    .unwrap_or(0);
+                stats.transmitted = count("Sent =");
+                stats.received = count("Received =");
+                stats.loss_percent = line.split_once('(')
+                    .and_then(|(_, rest)| rest.split('%').next()?.parse().ok())
+                    .unwrap_or(0.0);
+            } else if line.contains("Minimum =") {
+                // Windows: "    Minimum = 11ms, Maximum = 12ms, Average = 11ms"
+                let ms = |key: &str| line.split(',')
+                    .find_map(|part| part.split_once(key))
+                    .and_then(|(_, value)| value.trim().trim_end_matches("ms").parse().ok());
+                stats.rtt_min_ms = ms("Minimum =");
+                stats.rtt_avg_ms = ms("Average =");
+                stats.rtt_max_ms = ms("Maximum =");
+            } else if line.contains("min/avg/max") {
+                // "rtt min/avg/max/mdev = 0.045/0.061/0.079/0.012 ms"
+                if let Some(values) = line.split('=').nth(1) {
+                    let values: Vec<f64> = values
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or("")
+                        .split('/')
+                        .filter_map(|v| v.parse().ok())
+                        .collect();
+                    stats.rtt_min_ms = values.first().copied();
+                    stats.rtt_avg_ms = values.get(1).copied();
+                    stats.rtt_max_ms = values.get(2).copied();
+                }
+            }
+        }
+        
+        stats
+    }
     
-    // Parse base IP
-    let ip_parts: Vec<u32> = base_ip.split('.')
-        .map(|s| s.parse().unwrap_or(0))
-        .collect();
+    /// Append one row per run, writing the header first when the file is new or empty
+    fn append_csv(&self, path: &std::path::Path) -> Result<()> {
+        use std::io::Write;
+        
+        let is_new = std::fs::metadata(path).map_or(true, |meta| meta.len() == 0);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Cannot open ping log {}: {}", path.display(), e))?;
+        
+        if is_new {
+            writeln!(file, "timestamp,target,transmitted,received,loss_percent,rtt_min_ms,rtt_avg_ms,rtt_max_ms")?;
+        }
+        
+        let ms = |value: Option<f64>| value.map(|v| format!("{:.3}", v)).unwrap_or_default();
+        writeln!(file, "{},{},{},{},{:.1},{},{},{}",
+            Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            self.target,
+            self.transmitted,
+            self.received,
+            self.loss_percent,
+            ms(self.rtt_min_ms),
+            ms(self.rtt_avg_ms),
+            ms(self.rtt_max_ms)
+        )?;
+        
+        Ok(())
+    }
     
-    if ip_parts.len() != 4 {
-        return Err(anyhow::anyhow!("Invalid IP format"));
+    fn record_timeout(&mut self) {
+        self.transmitted += 1;
+        self.loss_percent = f64::from(self.transmitted - self.received) / f64::from(self.transmitted) * 100.0;
     }
     
-    let base_ip_num = (ip_parts[0] << 24) | (ip_parts[1] << 16) | (ip_parts[2] << 8) | ip_parts[3];
-    let subnet_size = 2u32.pow(32 - new_prefix);
+    fn record_reply(&mut self, rtt_ms: f64) {
+        self.received += 1;
+        self.transmitted = self.transmitted.max(self.received);
+        
+        let avg = self.rtt_avg_ms.unwrap_or(0.0);
+        self.rtt_avg_ms = Some(avg + (rtt_ms - avg) / f64::from(self.received));
+        self.rtt_min_ms = Some(self.rtt_min_ms.map_or(rtt_ms, |min| min.min(rtt_ms)));
+        self.rtt_max_ms = Some(self.rtt_max_ms.map_or(rtt_ms, |max| max.max(rtt_ms)));
+        self.loss_percent = f64::from(self.transmitted - self.received) / f64::from(self.transmitted) * 100.0;
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Hop {
+    hop: u32,
+    host: Option<String>,
+    address: Option<String>,
+    /// One entry per probe; `None` means the probe timed out
+    rtts_ms: Vec<Option<f64>>,
+}
+
+impl Hop {
+    /// Parse one hop line from `traceroute` or `tracert`
+    fn parse(line: &str) -> Option<Self> {
+        // traceroute: " 1  gateway (192.168.1.1)  0.512 ms  0.456 ms  *"
+        // tracert:    "  1    <1 ms    <1 ms    <1 ms  router [192.168.1.1]"
+        let mut tokens = line.split_whitespace().peekable();
+        let mut hop = Hop {
+            hop: tokens.next()?.parse().ok()?,
+            ..Default::default()
+        };
+        
+        while let Some(token) = tokens.next() {
+            if token == "*" {
+                hop.rtts_ms.push(None);
+            } else if token == "Request" {
+                // tracert's "Request timed out."
+                break;
+            } else if tokens.peek() == Some(&"ms") {
+                tokens.next();
+                hop.rtts_ms.push(token.trim_start_matches('<').parse().ok());
+            } else if let Some(addr) = token.strip_prefix(['(', '[']) {
+                hop.address = Some(addr.trim_end_matches([')', ']']).to_string());
+            } else if hop.host.is_none() && !token.starts_with('!') {
+                hop.host = Some(token.to_string());
+            }
+        }
+        
+        Some(hop)
+    }
     
-    println!("{}", "Subnet Allocations:".yellow().bold());
+    fn is_timeout(&self) -> bool {
+        self.host.is_none() && self.rtts_ms.iter().all(Option::is_none)
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+impl RecordType {
+    fn as_str(self) -> &'static str {
+        match self {
+            RecordType::A => "A",
+            RecordType::Aaaa => "AAAA",
+            RecordType::Cname => "CNAME",
+            RecordType::Mx => "MX",
+            RecordType::Txt => "TXT",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DnsRecord {
+    name: String,
+    ttl: Option<u32>,
+    record_type: String,
+    data: String,
+}
+
+/// Resolve with `dig`, falling back to the system resolver for A/AAAA when `dig` is missing
+fn resolve_dns(name: &str, record_type: RecordType, server: Option<&str>) -> Result<Vec<DnsRecord>> {
+    let mut cmd = Command::new("dig");
+    cmd.args(["+noall", "+answer"]);
+    if let Some(server) = server {
+        cmd.arg(format!("@{}", server));
+    }
+    cmd.arg(name).arg(record_type.as_str());
     
-    for i in 0..count {
-        let subnet_ip_num = base_ip_num + (i as u32 * subnet_size);
-        let octet1 = (subnet_ip_num >> 24) & 0xFF;
-        let octet2 = (subnet_ip_num >> 16) & 0xFF;
-        let octet3 = (subnet_ip_num >> 8) & 0xFF;
-        let octet4 = subnet_ip_num & 0xFF;
+    match cmd.output() {
+        Ok(output) => {
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("dig failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            
+            // "example.com.  300  IN  A  93.184.216.34"
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(stdout.lines()
+                .filter(|line| !line.starts_with(';'))
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    let name = fields.next()?.to_string();
+                    let ttl = fields.next()?.parse().ok();
+                    let _class = fields.next()?;
+                    let record_type = fields.next()?.to_string();
+                    let data = fields.collect::<Vec<_>>().join(" ");
+                    Some(DnsRecord { name, ttl, record_type, data })
+                })
+                .collect())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if server.is_some() || !matches!(record_type, RecordType::A | RecordType::Aaaa) {
+                return Err(anyhow::anyhow!("dig is required for --server and {} lookups", record_type.as_str()));
+            }
+            
+            use std::net::ToSocketAddrs;
+            
+            let mut addrs: Vec<std::net::IpAddr> = (name, 0).to_socket_addrs()?
+                .map(|addr| addr.ip())
+                .filter(|ip| ip.is_ipv4() == matches!(record_type, RecordType::A))
+                .collect();
+            addrs.sort();
+            addrs.dedup();
+            
+            Ok(addrs.into_iter()
+                .map(|ip| DnsRecord {
+                    name: name.to_string(),
+                    ttl: None,
+                    record_type: record_type.as_str().to_string(),
+                    data: ip.to_string(),
+                })
+                .collect())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// An echo reply line: "64 bytes from ..." or, on Windows, "Reply from ...: bytes=32 time=11ms"
+fn is_ping_reply(line: &str) -> bool {
+    line.contains("bytes from") || (line.starts_with("Reply from") && line.contains("time"))
+}
+
+/// Stream `ping` until it exits (Ctrl-C reaches it too), keeping `PingStats` current per reply.
+/// `live` redraws a rolling status line under the replies, which only makes sense on a terminal.
+fn ping_continuous(target: &str, human: bool, live: bool) -> Result<PingStats> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+    
+    // The child gets SIGINT from the terminal as well; we only need to survive it
+    // long enough to print the summary.
+    ctrlc::set_handler(|| {})?;
+    
+    let mut cmd = Command::new("ping");
+    // Windows ping stops after four probes unless told to keep going
+    if cfg!(windows) {
+        cmd.arg("-t");
+    }
+    let mut child = cmd.arg(target).stdout(Stdio::piped()).spawn()?;
+    
+    let mut stats = PingStats {
+        target: target.to_string(),
+        ..Default::default()
+    };
+    let mut tail = String::new();
+    let mut seq_offset = None;
+    
+    let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("Failed to capture ping output"))?;
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
         
-        println!("  Subnet {}: {}.{}.{}.{}/{} ({} usable hosts)", 
+        if is_ping_reply(&line) {
+            let field = |name: &str| line.split_whitespace()
+                .find_map(|part| part.strip_prefix(name))
+                .map(|v| v.to_string());
+            
+            // Windows numbers nothing, so there transmitted just follows the replies and timeouts
+            if let Some(seq) = field("icmp_seq=").and_then(|v| v.parse::<u32>().ok()) {
+                let offset = *seq_offset.get_or_insert(u32::from(seq == 0));
+                stats.transmitted = stats.transmitted.max(seq + offset);
+            }
+            // "time=0.045 ms" here, "time=11ms" or "time<1ms" on Windows
+            if let Some(rtt) = field("time=").or_else(|| field("time<")).and_then(|v| v.trim_end_matches("ms").parse::<f64>().ok()) {
+                stats.record_reply(rtt);
+            }
+            
+            if live {
+                println!("\r\x1b[K{}", line.green());
+                print!("{}", format!("   ↳ {} received, {:.1}% loss, avg {:.3} ms",
+                    stats.received,
+                    stats.loss_percent,
+                    stats.rtt_avg_ms.unwrap_or(0.0)).bright_black());
+                std::io::stdout().flush()?;
+            } else if human {
+                println!("{}", line.green());
+            }
+        } else if line.starts_with("Request timed out") {
+            stats.record_timeout();
+            if human {
+                println!("\r\x1b[K{}", line.red());
+            }
+        } else if ["packets transmitted", "min/avg/max", "Sent =", "Minimum ="].iter().any(|s| line.contains(s)) {
+            tail.push_str(&line);
+            tail.push('\n');
+        }
+    }
+    child.wait()?;
+    
+    // Prefer ping's own totals, which also count the final unanswered probe
+    let summary = PingStats::parse(target, &tail);
+    if summary.transmitted > 0 {
+        stats.transmitted = summary.transmitted;
+        stats.received = summary.received;
+        stats.loss_percent = summary.loss_percent;
+    }
+    
+    if live {
+        print!("\r\x1b[K");
+    }
+    if human {
+        println!();
+        println!("{}", "═".repeat(60).bright_black());
+        let loss = format!("{:.1}% packet loss", stats.loss_percent);
+        println!("{} transmitted, {} received, {}", 
+            stats.transmitted,
+            stats.received,
+            if stats.loss_percent == 0.0 { loss.green().bold() } else { loss.yellow().bold() }
+        );
+        if let (Some(min), Some(avg), Some(max)) = (stats.rtt_min_ms, stats.rtt_avg_ms, stats.rtt_max_ms) {
+            println!("{}", format!("rtt min/avg/max = {:.3}/{:.3}/{:.3} ms", min, avg, max).cyan());
+        }
+    }
+    
+    Ok(stats)
+}
+
+/// IPv4 header (20) plus ICMP echo header (8)
+const ICMP_OVERHEAD: u32 = 28;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Probe {
+    Ok,
+    /// Rejected locally or by a router since it exceeds the MTU
+    TooBig,
+    Lost,
+}
+
+/// Send one echo of `size` payload bytes with the don't-fragment bit set
+fn probe_df(target: &str, size: u32) -> Result<Probe> {
+    let mut cmd = Command::new("ping");
+    let size_flag = if cfg!(windows) {
+        cmd.args(["-f", "-n", "1", "-w", "1000"]);
+        "-l"
+    } else if cfg!(target_os = "macos") {
+        cmd.args(["-D", "-c", "1", "-W", "1000"]);
+        "-s"
+    } else {
+        cmd.args(["-M", "do", "-c", "1", "-W", "1"]);
+        "-s"
+    };
+    
+    let output = cmd.arg(size_flag).arg(size.to_string()).arg(target).output()?;
+    if output.status.success() {
+        return Ok(Probe::Ok);
+    }
+    
+    let text = format!("{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)).to_lowercase();
+    // Windows: "Packet needs to be fragmented but DF set."
+    if text.contains("message too long") || text.contains("frag needed") || text.contains("needs to be fragmented") {
+        Ok(Probe::TooBig)
+    } else {
+        Ok(Probe::Lost)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SubnetPlan {
+    cidr: String,
+    count: usize,
+    original_prefix: u32,
+    original_hosts: u64,
+    /// "aws" (5 reserved per subnet) or "generic" (network + broadcast)
+    reservation: &'static str,
+    new_prefix: u32,
+    hosts_per_subnet: u64,
+    subnets: Vec<String>,
+}
+
+fn calculate_subnets(cidr: &str, count: usize, max_prefix: u32, aws: bool, format: OutputFormat) -> Result<()> {
+    let plan = plan_subnets(cidr, count, max_prefix, aws)?;
+    
+    if format.is_structured() {
+        return output::print(format, &plan);
+    }
+    
+    println!("{}", "Subnet Calculator".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    println!("VPC CIDR: {}", plan.cidr.yellow());
+    println!("Subnets: {}", plan.count.to_string().cyan());
+    if aws {
+        println!("Reserved: {}", format!("{} per subnet (AWS: network, router, DNS, future use, broadcast)", cidr::AWS_RESERVED_IPS).bright_black());
+    } else {
+        println!("Reserved: {}", "network + broadcast (use --aws for EC2 counts)".bright_black());
+    }
+    println!();
+    
+    println!("Original: {}", format!("/{} ({} hosts)", plan.original_prefix, plan.original_hosts).bright_black());
+    println!("New subnets: {}", format!("/{} ({} hosts each)", plan.new_prefix, plan.hosts_per_subnet).green());
+    println!();
+    
+    println!("{}", "Subnet Allocations:".yellow().bold());
+    
+    for (i, subnet) in plan.subnets.iter().enumerate() {
+        println!("  Subnet {}: {} ({} usable hosts)", 
             i + 1,
-            octet1, octet2, octet3, octet4,
-            new_prefix,
-            hosts_per_subnet.to_string().cyan()
+            subnet,
+            plan.hosts_per_subnet.to_string().cyan()
         );
     }
     
@@ -406,3 +1899,210 @@ fn calculate_subnets(cidr: &str, count: usize) -> Result<()> {
     
     Ok(())
 }
+
+
+/// Split `cidr` into `count` equal subnets no longer than `/max_prefix`
+fn plan_subnets(cidr: &str, count: usize, max_prefix: u32, aws: bool) -> Result<SubnetPlan> {
+    let hosts = if aws { cidr::aws_usable_hosts } else { cidr::usable_hosts };
+    
+    let (base_ip_num, prefix) = cidr::parse_cidr(cidr)?;
+    
+    // Normalize e.g. 10.0.0.5/16 to 10.0.0.0/16 rather than splitting from a host address
+    let network = cidr::network(base_ip_num, prefix);
+    if network != base_ip_num {
+        tracing::warn!("{} is not on a /{} boundary; using {}/{}", cidr, prefix, cidr::format_ip(network), prefix);
+    }
+    let base_ip_num = network;
+    let cidr = &format!("{}/{}", cidr::format_ip(network), prefix);
+    let prefix = u32::from(prefix);
+    
+    if count == 0 {
+        return Err(anyhow::anyhow!("--count must be at least 1"));
+    }
+    if max_prefix > 32 {
+        return Err(anyhow::anyhow!("--max-prefix must be at most 32, not {}", max_prefix));
+    }
+    
+    // Calculate new prefix length: enough bits to number `count` subnets
+    let bits_needed = count.checked_next_power_of_two().map_or(usize::BITS, |n| n.trailing_zeros());
+    let new_prefix = prefix + bits_needed;
+    
+    if prefix > max_prefix {
+        return Err(anyhow::anyhow!("{} is already longer than the /{} limit", cidr, max_prefix));
+    }
+    
+    if new_prefix > max_prefix {
+        let fit = 1u64 << (max_prefix - prefix);
+        return Err(anyhow::anyhow!(
+            "{} subnets don't fit in {}: splitting it would need /{}, but the limit is /{} ({} /{} subnet(s) fit)",
+            count, cidr, new_prefix, max_prefix, fit, max_prefix
+        ));
+    }
+    
+    let hosts_per_subnet = hosts(new_prefix as u8);
+    
+    let subnet_size = 1u64 << (32 - new_prefix);
+    let (_, parent_end) = cidr::range(base_ip_num, prefix as u8);
+    
+    let subnets = (0..count as u64)
+        .map(|i| {
+            let subnet_ip_num = u64::from(base_ip_num) + i * subnet_size;
+            if subnet_ip_num + subnet_size - 1 > u64::from(parent_end) {
+                return Err(anyhow::anyhow!("Subnet {} would extend past the end of {}", i + 1, cidr));
+            }
+            Ok(format!("{}/{}", cidr::format_ip(subnet_ip_num as u32), new_prefix))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    Ok(SubnetPlan {
+        cidr: cidr.to_string(),
+        count,
+        original_prefix: prefix,
+        original_hosts: hosts(prefix as u8),
+        reservation: if aws { "aws" } else { "generic" },
+        new_prefix,
+        hosts_per_subnet,
+        subnets,
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct CidrRange {
+    cidr: String,
+    first: String,
+    last: String,
+    addresses: u64,
+}
+
+impl CidrRange {
+    fn new(first: u32, last: u32) -> Self {
+        let addresses = u64::from(last) - u64::from(first) + 1;
+        CidrRange {
+            cidr: format!("{}/{}", cidr::format_ip(first), 32 - addresses.trailing_zeros()),
+            first: cidr::format_ip(first),
+            last: cidr::format_ip(last),
+            addresses,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CidrComparison {
+    a: CidrRange,
+    b: CidrRange,
+    /// "equal", "contains" (a holds b), "within" (b holds a), "overlap", or "disjoint"
+    relationship: &'static str,
+    overlap: Option<CidrRange>,
+}
+
+fn compare_cidrs(a: &str, b: &str, format: OutputFormat) -> Result<()> {
+    let (a_base, a_prefix) = cidr::parse_network(a)?;
+    let (b_base, b_prefix) = cidr::parse_network(b)?;
+    let a_range = cidr::range(a_base, a_prefix);
+    let b_range = cidr::range(b_base, b_prefix);
+    let overlap = cidr::overlap(a_range, b_range);
+    
+    // Aligned blocks always nest or miss entirely; "overlap" only covers
+    // ranges that share addresses without either holding the other
+    let relationship = match overlap {
+        None => "disjoint",
+        Some(_) if a_range == b_range => "equal",
+        Some(shared) if shared == b_range => "contains",
+        Some(shared) if shared == a_range => "within",
+        Some(_) => "overlap",
+    };
+    
+    let comparison = CidrComparison {
+        a: CidrRange::new(a_range.0, a_range.1),
+        b: CidrRange::new(b_range.0, b_range.1),
+        relationship,
+        overlap: overlap.map(|(first, last)| CidrRange::new(first, last)),
+    };
+    
+    if format.is_structured() {
+        return output::print(format, &comparison);
+    }
+    
+    println!("{}", "CIDR Comparison".cyan().bold());
+    println!("{}", "═".repeat(70).bright_black());
+    for (label, range) in [("A", &comparison.a), ("B", &comparison.b)] {
+        println!("{}: {} {}", label, range.cidr.yellow(),
+            format!("({} - {}, {} addresses)", range.first, range.last, range.addresses).bright_black());
+    }
+    println!();
+    
+    let summary = match relationship {
+        "equal" => "A and B are the same network".green(),
+        "contains" => format!("A contains {}", comparison.b.cidr).green(),
+        "within" => format!("A is inside {}", comparison.b.cidr).green(),
+        "overlap" => "A and B partially overlap".yellow(),
+        _ => "A and B are disjoint".bright_black(),
+    };
+    println!("Relationship: {}", summary.bold());
+    if let Some(shared) = &comparison.overlap {
+        println!("Overlap: {} {}", shared.cidr.cyan(),
+            format!("({} - {}, {} addresses)", shared.first, shared.last, shared.addresses).bright_black());
+    }
+    
+    println!("{}", "═".repeat(70).bright_black());
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn cli_definition_is_consistent() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+    
+    #[test]
+    fn verbose_counts_before_the_subcommand_and_vpc_keeps_its_short_flag() {
+        let cli = Cli::try_parse_from(["netkit", "-vv", "compliance", "-v", "vpc-1"]).unwrap();
+        assert_eq!(cli.verbose, 2);
+        assert!(matches!(cli.command, Commands::Compliance { ref vpc, .. } if vpc == &["vpc-1"]));
+    }
+    
+    #[test]
+    fn splits_into_point_to_point_and_host_subnets() {
+        let plan = plan_subnets("192.168.1.0/30", 2, 32, false).unwrap();
+        assert_eq!(plan.new_prefix, 31);
+        assert_eq!(plan.hosts_per_subnet, 2);
+        assert_eq!(plan.subnets, ["192.168.1.0/31", "192.168.1.2/31"]);
+        
+        let plan = plan_subnets("192.168.1.0/31", 2, 32, false).unwrap();
+        assert_eq!(plan.original_hosts, 2);
+        assert_eq!(plan.hosts_per_subnet, 1);
+        assert_eq!(plan.subnets, ["192.168.1.0/32", "192.168.1.1/32"]);
+    }
+    
+    #[test]
+    fn whole_address_space() {
+        let plan = plan_subnets("0.0.0.0/0", 1, 28, false).unwrap();
+        assert_eq!(plan.new_prefix, 0);
+        assert_eq!(plan.original_hosts, (1u64 << 32) - 2);
+        assert_eq!(plan.subnets, ["0.0.0.0/0"]);
+        
+        let plan = plan_subnets("0.0.0.0/0", 2, 28, false).unwrap();
+        assert_eq!(plan.subnets, ["0.0.0.0/1", "128.0.0.0/1"]);
+    }
+    
+    #[test]
+    fn max_prefix_limits_the_split() {
+        assert!(plan_subnets("10.0.0.0/28", 2, 28, true).is_err());
+        assert!(plan_subnets("10.0.0.0/29", 1, 28, false).is_err());
+        assert_eq!(plan_subnets("10.0.0.0/28", 2, 29, false).unwrap().new_prefix, 29);
+        assert!(plan_subnets("10.0.0.0/24", 2, 33, false).is_err());
+    }
+    
+    #[test]
+    fn count_larger_than_the_space() {
+        assert!(plan_subnets("10.0.0.1/32", 2, 32, false).is_err());
+        assert!(plan_subnets("10.0.0.0/24", 257, 32, false).is_err());
+        assert!(plan_subnets("0.0.0.0/0", usize::MAX, 32, false).is_err());
+        assert!(plan_subnets("10.0.0.0/16", 0, 28, false).is_err());
+    }
+}
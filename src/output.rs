@@ -0,0 +1,395 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Write as _};
+use std::fs;
+use std::path::Path;
+
+/// Output format shared by every command via the global `--output` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Colored, human-readable output
+    #[default]
+    Table,
+    Json,
+    /// Newline-delimited JSON; `--all-regions` scans print one object per region as it finishes
+    Ndjson,
+    Yaml,
+    /// Comma-separated rows (cost only)
+    Csv,
+    /// Prometheus text exposition format (compliance and cost only)
+    Prometheus,
+}
+
+impl OutputFormat {
+    /// True for the machine-readable formats, where decorative output must be suppressed
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Table
+    }
+}
+
+/// Serialize a command's data model to stdout in the requested structured format.
+pub fn print<T: Serialize>(format: OutputFormat, value: &T) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(value)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        OutputFormat::Csv => return Err(anyhow::anyhow!("CSV output is not supported for this command")),
+        OutputFormat::Prometheus => return Err(anyhow::anyhow!("Prometheus output is only supported by compliance and cost")),
+        OutputFormat::Table => return Err(anyhow::anyhow!("Table output must be rendered by the command")),
+    }
+    
+    Ok(())
+}
+
+/// Gauges in the Prometheus text exposition format, grouped by metric name
+#[derive(Default)]
+pub struct Metrics {
+    families: Vec<MetricFamily>,
+}
+
+struct MetricFamily {
+    name: &'static str,
+    help: &'static str,
+    samples: Vec<String>,
+}
+
+impl Metrics {
+    /// Record one sample; the first sample for a name sets its `# HELP` text
+    pub fn gauge(&mut self, name: &'static str, help: &'static str, labels: &[(&str, &str)], value: impl Display) {
+        let labels: Vec<String> = labels.iter()
+            .map(|(key, val)| format!("{}=\"{}\"", key, escape_label(val)))
+            .collect();
+        let sample = if labels.is_empty() {
+            format!("{} {}", name, value)
+        } else {
+            format!("{}{{{}}} {}", name, labels.join(","), value)
+        };
+        
+        match self.families.iter_mut().find(|f| f.name == name) {
+            Some(family) => family.samples.push(sample),
+            None => self.families.push(MetricFamily { name, help, samples: vec![sample] }),
+        }
+    }
+    
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# HELP {} {}", family.name, family.help);
+            let _ = writeln!(out, "# TYPE {} gauge", family.name);
+            for sample in &family.samples {
+                let _ = writeln!(out, "{}", sample);
+            }
+        }
+        out
+    }
+    
+    /// Print to stdout, or replace `textfile` for node_exporter's textfile collector.
+    /// The file is written next to its destination and renamed into place so the
+    /// collector never scrapes a half-written file.
+    pub fn write(&self, textfile: Option<&Path>) -> Result<()> {
+        let Some(path) = textfile else {
+            print!("{}", self.render());
+            return Ok(());
+        };
+        
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".tmp");
+        fs::write(&partial, self.render())
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))?;
+        fs::rename(&partial, path)
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))?;
+        
+        Ok(())
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Text safe to place in HTML element content and quoted attributes
+pub fn html_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Standard base64 with padding (RFC 4648), for embedding images in HTML
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Rows of cells printed in columns padded to their widest cell, with a bold
+/// header row. Widths ignore color codes, so colored cells still line up.
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    /// Columns aligned to the right, such as counts
+    right: Vec<usize>,
+    /// Lines printed under a row (by index), starting at the last column and
+    /// left out of the width calculation
+    notes: Vec<(usize, String)>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Table { headers: headers.to_vec(), rows: Vec::new(), right: Vec::new(), notes: Vec::new() }
+    }
+    
+    pub fn right_align(mut self, column: usize) -> Self {
+        self.right.push(column);
+        self
+    }
+    
+    /// Add a row; missing trailing cells print empty
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+    
+    /// Add a line under the most recent row, e.g. detail too long for a cell
+    pub fn note(&mut self, text: String) {
+        if let Some(last) = self.rows.len().checked_sub(1) {
+            self.notes.push((last, text));
+        }
+    }
+    
+    pub fn print(&self) {
+        let cell = |row: &[String], i: usize| row.get(i).map_or(0, |c| visible_width(c));
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|i| self.rows.iter().map(|row| cell(row, i)).max().unwrap_or(0).max(self.headers[i].len()))
+            .collect();
+        let last = self.headers.len().saturating_sub(1);
+        
+        let line = |cells: Vec<String>| {
+            let padded: Vec<String> = cells.iter().enumerate()
+                .map(|(i, text)| {
+                    let pad = " ".repeat(widths[i].saturating_sub(visible_width(text)));
+                    if self.right.contains(&i) {
+                        format!("{}{}", pad, text)
+                    } else if i == last {
+                        // No trailing spaces after the final column
+                        text.clone()
+                    } else {
+                        format!("{}{}", text, pad)
+                    }
+                })
+                .collect();
+            println!("{}", padded.join("  "));
+        };
+        
+        line(self.headers.iter().map(|h| h.bold().to_string()).collect());
+        line(widths.iter().map(|w| "─".repeat(*w).bright_black().to_string()).collect());
+        let indent = " ".repeat(widths[..last].iter().map(|w| w + 2).sum());
+        for (index, row) in self.rows.iter().enumerate() {
+            line((0..self.headers.len()).map(|i| row.get(i).cloned().unwrap_or_default()).collect());
+            for (_, note) in self.notes.iter().filter(|(row, _)| *row == index) {
+                println!("{}{}", indent, note);
+            }
+        }
+    }
+}
+
+/// Widest the boxed views get; narrower terminals shrink them
+const DEFAULT_WIDTH: usize = 70;
+const MIN_WIDTH: usize = 40;
+
+/// Width and character set for the boxed topology view. Lines longer than
+/// the terminal (or `--width`) are cut with an ellipsis; `ascii` swaps box
+/// drawing, arrows, and emoji for plain characters that survive non-UTF-8
+/// consoles and CI logs.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    /// Length of rules and box edges
+    width: usize,
+    /// Column lines are cut at; piped output is never cut unless asked
+    limit: Option<usize>,
+    ascii: bool,
+}
+
+impl Layout {
+    /// Exactly `width` when given, otherwise the default narrowed to fit the terminal
+    pub fn new(width: Option<usize>, ascii: bool) -> Self {
+        let limit = width.or_else(terminal_width).map(|cols| cols.max(MIN_WIDTH));
+        let width = width.map_or_else(|| limit.map_or(DEFAULT_WIDTH, |cols| cols.min(DEFAULT_WIDTH)), |w| w.max(MIN_WIDTH));
+        Layout { width, limit, ascii }
+    }
+    
+    /// Full-width `═══` rule
+    pub fn rule(&self) -> String {
+        self.text(&"═".repeat(self.width))
+    }
+    
+    /// Top or bottom edge of a box, a little narrower than the rules around it
+    pub fn box_edge(&self, top: bool) -> String {
+        let (left, right) = if top { ('┌', '┐') } else { ('└', '┘') };
+        let edge = format!("{}{}{}", left, "─".repeat(self.width.saturating_sub(5)), right);
+        self.text(&edge)
+    }
+    
+    /// Print one line, transliterated and cut to fit
+    pub fn line(&self, text: &str) {
+        println!("{}", self.fit(&self.text(text)));
+    }
+    
+    fn text(&self, text: &str) -> String {
+        if !self.ascii {
+            return text.to_string();
+        }
+        
+        let mut out = String::with_capacity(text.len());
+        let mut dropped = false;
+        for c in text.chars() {
+            // Don't leave the gap a dropped icon sat in
+            if dropped && (c == ' ' || c == '\u{FE0F}') {
+                continue;
+            }
+            dropped = false;
+            
+            match c {
+                '┌' | '┐' | '└' | '┘' => out.push('+'),
+                '─' | '—' | '−' => out.push('-'),
+                '│' => out.push('|'),
+                '═' => out.push('='),
+                '→' | '↳' => out.push_str("->"),
+                '⇄' => out.push_str("<->"),
+                '•' => out.push('*'),
+                '⚠' => out.push('!'),
+                '✅' => out.push('+'),
+                '❌' => out.push('x'),
+                '\u{FE0F}' => {}
+                c if c.is_ascii() => out.push(c),
+                // Remaining emoji are decoration next to a text label
+                c if is_wide(c) => dropped = out.is_empty() || out.ends_with(' '),
+                _ => out.push('?'),
+            }
+        }
+        out
+    }
+    
+    /// Cut `text` to the line limit, counting only visible columns so color codes survive
+    fn fit(&self, text: &str) -> String {
+        let ellipsis = if self.ascii { "..." } else { "…" };
+        let Some(limit) = self.limit.filter(|limit| visible_width(text) > *limit) else {
+            return text.to_string();
+        };
+        
+        let limit = limit.saturating_sub(visible_width(ellipsis));
+        let mut out = String::new();
+        let mut used = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            // ANSI color sequences take no space
+            if c == '\u{1b}' {
+                out.push(c);
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+                continue;
+            }
+            
+            let w = char_width(c);
+            if used + w > limit {
+                break;
+            }
+            used += w;
+            out.push(c);
+        }
+        
+        out.push_str(ellipsis);
+        if out.contains('\u{1b}') {
+            out.push_str("\u{1b}[0m");
+        }
+        out
+    }
+}
+
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        match c {
+            '\u{1b}' => in_escape = true,
+            c if in_escape => in_escape = !c.is_ascii_alphabetic(),
+            c => width += char_width(c),
+        }
+    }
+    width
+}
+
+fn char_width(c: char) -> usize {
+    match c {
+        '\u{FE0F}' | '\u{200D}' => 0,
+        c if is_wide(c) => 2,
+        _ => 1,
+    }
+}
+
+/// Emoji and pictographs, which terminals draw two columns wide
+fn is_wide(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x23E9..=0x23FA)
+}
+
+/// `COLUMNS`, or the controlling terminal's size via `stty` when stdout is a terminal
+fn terminal_width() -> Option<usize> {
+    if let Some(cols) = std::env::var("COLUMNS").ok().and_then(|c| c.trim().parse().ok()) {
+        return Some(cols);
+    }
+    
+    use std::io::IsTerminal;
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    
+    let tty = fs::File::open("/dev/tty").ok()?;
+    let output = std::process::Command::new("stty").arg("size").stdin(tty).output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|cols| cols.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in vectors {
+            assert_eq!(base64(input.as_bytes()), expected, "base64({:?})", input);
+        }
+    }
+    
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64(&[0x00, 0x10, 0x83, 0x10, 0x51, 0x87]), "ABCDEFGH");
+        assert_eq!(base64(&[0xFB, 0xFF, 0xBF]), "+/+/");
+        assert_eq!(base64(&[0xFF]), "/w==");
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{awscli, config};
+
+// Built-in rates (approximate, us-east-1)
+const NAT_GATEWAY_HOURLY: f64 = 0.045;
+const NAT_GATEWAY_PER_GB: f64 = 0.045;
+const TGW_ATTACHMENT_HOURLY: f64 = 0.05;
+const TGW_PER_GB: f64 = 0.02;
+
+/// How long a live price stays in the local cache before it is refetched
+const CACHE_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Approximate on-demand Linux hourly rates: (region, instance type, USD/hr).
+/// Regions not listed fall back to the us-east-1 rate.
+const EC2_HOURLY_RATES: &[(&str, &str, f64)] = &[
+    ("us-east-1", "t2.micro", 0.0116),
+    ("us-east-1", "t2.small", 0.023),
+    ("us-east-1", "t2.medium", 0.0464),
+    ("us-east-1", "t2.large", 0.0928),
+    ("us-east-1", "t3.nano", 0.0052),
+    ("us-east-1", "t3.micro", 0.0104),
+    ("us-east-1", "t3.small", 0.0208),
+    ("us-east-1", "t3.medium", 0.0416),
+    ("us-east-1", "t3.large", 0.0832),
+    ("us-east-1", "t3.xlarge", 0.1664),
+    ("us-east-1", "t3.2xlarge", 0.3328),
+    ("us-east-1", "t3a.micro", 0.0094),
+    ("us-east-1", "t3a.small", 0.0188),
+    ("us-east-1", "t3a.medium", 0.0376),
+    ("us-east-1", "t3a.large", 0.0752),
+    ("us-east-1", "t4g.micro", 0.0084),
+    ("us-east-1", "t4g.small", 0.0168),
+    ("us-east-1", "t4g.medium", 0.0336),
+    ("us-east-1", "t4g.large", 0.0672),
+    ("us-east-1", "m5.large", 0.096),
+    ("us-east-1", "m5.xlarge", 0.192),
+    ("us-east-1", "m5.2xlarge", 0.384),
+    ("us-east-1", "m5.4xlarge", 0.768),
+    ("us-east-1", "m6i.large", 0.096),
+    ("us-east-1", "m6i.xlarge", 0.192),
+    ("us-east-1", "m6g.large", 0.077),
+    ("us-east-1", "m7g.large", 0.0816),
+    ("us-east-1", "c5.large", 0.085),
+    ("us-east-1", "c5.xlarge", 0.17),
+    ("us-east-1", "c5.2xlarge", 0.34),
+    ("us-east-1", "c6i.large", 0.085),
+    ("us-east-1", "c6g.large", 0.068),
+    ("us-east-1", "r5.large", 0.126),
+    ("us-east-1", "r5.xlarge", 0.252),
+    ("us-east-1", "r6i.large", 0.126),
+    ("us-east-1", "r6g.large", 0.1008),
+    ("eu-west-1", "t3.micro", 0.0114),
+    ("eu-west-1", "t3.small", 0.0228),
+    ("eu-west-1", "t3.medium", 0.0456),
+    ("eu-west-1", "t3.large", 0.0912),
+    ("eu-west-1", "m5.large", 0.107),
+    ("eu-west-1", "m5.xlarge", 0.214),
+    ("eu-west-1", "c5.large", 0.096),
+    ("eu-west-1", "r5.large", 0.141),
+    ("ap-northeast-1", "t3.micro", 0.0136),
+    ("ap-northeast-1", "t3.small", 0.0272),
+    ("ap-northeast-1", "t3.medium", 0.0544),
+    ("ap-northeast-1", "m5.large", 0.124),
+    ("ap-northeast-1", "c5.large", 0.107),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateSource {
+    BuiltIn,
+    Live,
+}
+
+impl RateSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RateSource::BuiltIn => "built-in",
+            RateSource::Live => "live",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rate {
+    pub usd: f64,
+    pub source: RateSource,
+}
+
+impl Rate {
+    fn built_in(usd: f64) -> Self {
+        Rate { usd, source: RateSource::BuiltIn }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRate {
+    usd: f64,
+    fetched_at: i64,
+}
+
+/// Resolves unit prices for a region, preferring the AWS Pricing API when
+/// `live` is set and falling back to the built-in table.
+pub struct Pricing {
+    region: String,
+    live: bool,
+    live_failed: bool,
+    cache: HashMap<String, CachedRate>,
+    cache_dirty: bool,
+}
+
+impl Pricing {
+    pub fn new(region: &str, live: bool) -> Self {
+        let cache = if live { load_cache() } else { HashMap::new() };
+        
+        Pricing {
+            region: region.to_string(),
+            live,
+            live_failed: false,
+            cache,
+            cache_dirty: false,
+        }
+    }
+    
+    pub fn nat_gateway_hourly(&mut self) -> Rate {
+        self.live_or("nat-hours", NAT_GATEWAY_HOURLY, |region| {
+            fetch_price("AmazonEC2", region, &[("productFamily", "NAT Gateway")], Some("NatGateway-Hours"))
+        })
+    }
+    
+    pub fn nat_gateway_per_gb(&mut self) -> Rate {
+        self.live_or("nat-bytes", NAT_GATEWAY_PER_GB, |region| {
+            fetch_price("AmazonEC2", region, &[("productFamily", "NAT Gateway")], Some("NatGateway-Bytes"))
+        })
+    }
+    
+    pub fn tgw_attachment_hourly(&mut self) -> Rate {
+        self.live_or("tgw-hours", TGW_ATTACHMENT_HOURLY, |region| {
+            fetch_price("AmazonVPC", region, &[("productFamily", "Transit Gateway")], Some("TransitGateway-Hours"))
+        })
+    }
+    
+    pub fn tgw_per_gb(&mut self) -> Rate {
+        self.live_or("tgw-bytes", TGW_PER_GB, |region| {
+            fetch_price("AmazonVPC", region, &[("productFamily", "Transit Gateway")], Some("TransitGateway-Bytes"))
+        })
+    }
+    
+    /// On-demand Linux hourly rate for an instance type, if known.
+    pub fn ec2_hourly(&mut self, instance_type: &str) -> Option<Rate> {
+        if let Some(rate) = self.live_rate(&format!("ec2/{}", instance_type), |region| {
+            fetch_price("AmazonEC2", region, &[
+                ("instanceType", instance_type),
+                ("operatingSystem", "Linux"),
+                ("tenancy", "Shared"),
+                ("preInstalledSw", "NA"),
+                ("capacitystatus", "Used"),
+                ("licenseModel", "No License required"),
+            ], None)
+        }) {
+            return Some(rate);
+        }
+        
+        ec2_built_in_rate(&self.region, instance_type).map(Rate::built_in)
+    }
+    
+    /// Whether a live lookup was attempted but the Pricing API could not be used.
+    pub fn live_failed(&self) -> bool {
+        self.live_failed
+    }
+    
+    /// Persist newly fetched live prices to the local cache.
+    pub fn save(&self) -> Result<()> {
+        if !self.cache_dirty {
+            return Ok(());
+        }
+        
+        let path = cache_path().ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&self.cache)?)?;
+        
+        Ok(())
+    }
+    
+    fn live_or(&mut self, key: &str, built_in: f64, fetch: impl FnOnce(&str) -> Result<Option<f64>>) -> Rate {
+        self.live_rate(key, fetch).unwrap_or(Rate::built_in(built_in))
+    }
+    
+    fn live_rate(&mut self, key: &str, fetch: impl FnOnce(&str) -> Result<Option<f64>>) -> Option<Rate> {
+        if !self.live {
+            return None;
+        }
+        
+        let cache_key = format!("{}/{}", self.region, key);
+        let now = chrono::Utc::now().timestamp();
+        
+        if let Some(cached) = self.cache.get(&cache_key) {
+            if now - cached.fetched_at < CACHE_MAX_AGE_SECS {
+                return Some(Rate { usd: cached.usd, source: RateSource::Live });
+            }
+        }
+        
+        // Don't keep paying for slow calls once the API has refused us
+        if self.live_failed {
+            return None;
+        }
+        
+        match fetch(&self.region) {
+            Ok(Some(usd)) => {
+                self.cache.insert(cache_key, CachedRate { usd, fetched_at: now });
+                self.cache_dirty = true;
+                Some(Rate { usd, source: RateSource::Live })
+            }
+            Ok(None) => None,
+            Err(_) => {
+                self.live_failed = true;
+                None
+            }
+        }
+    }
+}
+
+fn ec2_built_in_rate(region: &str, instance_type: &str) -> Option<f64> {
+    let lookup = |r: &str| EC2_HOURLY_RATES.iter()
+        .find(|(rate_region, t, _)| *rate_region == r && *t == instance_type)
+        .map(|(_, _, rate)| *rate);
+    
+    lookup(region).or_else(|| lookup("us-east-1"))
+}
+
+/// Query the Pricing API and return the first on-demand USD price matching
+/// the filters (and usage type suffix, when given).
+fn fetch_price(
+    service_code: &str,
+    region: &str,
+    filters: &[(&str, &str)],
+    usagetype_suffix: Option<&str>,
+) -> Result<Option<f64>> {
+    let mut args = vec![
+        "pricing".to_string(), "get-products".to_string(),
+        // The Pricing API is only served from a few regions
+        "--region".to_string(), "us-east-1".to_string(),
+        "--service-code".to_string(), service_code.to_string(),
+        "--max-results".to_string(), "100".to_string(),
+        "--filters".to_string(),
+        format!("Type=TERM_MATCH,Field=regionCode,Value={}", region),
+    ];
+    for (field, value) in filters {
+        args.push(format!("Type=TERM_MATCH,Field={},Value={}", field, value));
+    }
+    
+    let output = awscli::run(&args)?;
+    
+    if !output.success {
+        return Err(anyhow::anyhow!("Pricing API unavailable"));
+    }
+    
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let empty_vec = vec![];
+    
+    for entry in json["PriceList"].as_array().unwrap_or(&empty_vec) {
+        // Each price list entry is itself a JSON document encoded as a string
+        let Some(raw) = entry.as_str() else { continue };
+        let Ok(product) = serde_json::from_str::<Value>(raw) else { continue };
+        
+        if let Some(suffix) = usagetype_suffix {
+            let usagetype = product["product"]["attributes"]["usagetype"].as_str().unwrap_or("");
+            if !usagetype.ends_with(suffix) {
+                continue;
+            }
+        }
+        
+        if let Some(usd) = on_demand_usd(&product) {
+            return Ok(Some(usd));
+        }
+    }
+    
+    Ok(None)
+}
+
+fn on_demand_usd(product: &Value) -> Option<f64> {
+    let terms = product["terms"]["OnDemand"].as_object()?;
+    
+    terms.values()
+        .filter_map(|term| term["priceDimensions"].as_object())
+        .flat_map(|dims| dims.values())
+        .filter_map(|dim| dim["pricePerUnit"]["USD"].as_str())
+        .filter_map(|usd| usd.parse::<f64>().ok())
+        .find(|usd| *usd > 0.0)
+}
+
+fn cache_path() -> Option<PathBuf> {
+    config::cache_dir().map(|dir| dir.join("pricing.json"))
+}
+
+fn load_cache() -> HashMap<String, CachedRate> {
+    cache_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::cidr;
+
+/// Resource ID prefixes that get pseudonyms; anything else with a hex suffix is left alone
+const ID_PREFIXES: &[&str] = &[
+    "vpc", "subnet", "i", "sg", "igw", "eigw", "nat", "rtb", "acl", "eni", "eipalloc", "eipassoc",
+    "tgw", "tgw-attach", "tgw-rtb", "pcx", "vpce", "vgw", "cgw", "vpn", "dopt", "pl", "ami", "vol", "snap",
+];
+
+/// Pseudonyms handed out so far, in both directions, plus a counter per kind
+#[derive(Default)]
+struct Redactor {
+    pseudonyms: HashMap<String, String>,
+    originals: HashMap<String, String>,
+    counts: HashMap<String, usize>,
+    legend: Option<PathBuf>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REDACTOR: Mutex<Option<Redactor>> = Mutex::new(None);
+
+/// Writes the legend when dropped, so early returns and errors still leave one behind
+pub struct Legend;
+
+impl Drop for Legend {
+    fn drop(&mut self) {
+        if let Err(e) = finish() {
+            tracing::error!("Error: {}", e);
+        }
+    }
+}
+
+/// Turn redaction on. An existing `legend` file is loaded first so the same
+/// resources keep the same pseudonyms from one run to the next.
+pub fn enable(legend: Option<PathBuf>) -> Result<Legend> {
+    let mut redactor = Redactor::default();
+    
+    if let Some(path) = legend.as_ref().filter(|path| path.exists()) {
+        let raw = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read redaction map {}: {}", path.display(), e))?;
+        let saved: BTreeMap<String, String> = serde_json::from_str(&raw)
+            .map_err(|e| anyhow::anyhow!("Invalid redaction map {}: {}", path.display(), e))?;
+        for (pseudonym, original) in saved {
+            *redactor.counts.entry(kind(&original)).or_default() += 1;
+            redactor.pseudonyms.insert(original.clone(), pseudonym.clone());
+            redactor.originals.insert(pseudonym, original);
+        }
+    }
+    
+    redactor.legend = legend;
+    *lock() = Some(redactor);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(Legend)
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Replace resource IDs, account IDs, and public host addresses with stable
+/// pseudonyms (`vpc-0001`, `000000000001`, `203.0.113.1`). Pseudonyms stay
+/// well-formed, so redacted data still parses and CIDR math keeps working.
+pub fn text(raw: &str) -> String {
+    if !enabled() {
+        return raw.to_string();
+    }
+    
+    let mut guard = lock();
+    let Some(redactor) = guard.as_mut() else { return raw.to_string() };
+    redactor.text(raw)
+}
+
+/// Like `text` for an `aws` JSON response, but only its strings are
+/// rewritten: a 12-digit number (a CloudWatch byte count, say) is data, not
+/// an account ID, and must stay a valid number. Output that isn't JSON is
+/// redacted as text.
+pub fn json(raw: &[u8]) -> Vec<u8> {
+    if !enabled() {
+        return raw.to_vec();
+    }
+    let Ok(mut value) = serde_json::from_slice::<Value>(raw) else {
+        return text(&String::from_utf8_lossy(raw)).into_bytes();
+    };
+    
+    let mut guard = lock();
+    let Some(redactor) = guard.as_mut() else { return raw.to_vec() };
+    redactor.value(&mut value);
+    serde_json::to_vec(&value).unwrap_or_else(|_| raw.to_vec())
+}
+
+/// Map pseudonyms in an argument back to the real identifiers, so IDs taken
+/// from redacted responses can be passed to the next `aws` call
+pub fn restore(arg: &str) -> String {
+    if !enabled() {
+        return arg.to_string();
+    }
+    
+    let guard = lock();
+    let Some(redactor) = guard.as_ref() else { return arg.to_string() };
+    
+    let addresses_done = replace_tokens(arg, |c| c.is_ascii_digit() || c == '.', |token, _| {
+        redactor.originals.get(token).cloned()
+    });
+    replace_tokens(&addresses_done, |c| c.is_ascii_alphanumeric() || c == '-', |token, _| {
+        redactor.originals.get(token).cloned()
+    })
+}
+
+/// Write the pseudonym → original legend, if one was requested (once; later calls do nothing)
+pub fn finish() -> Result<()> {
+    let Some(redactor) = lock().take() else { return Ok(()) };
+    let Some(path) = &redactor.legend else { return Ok(()) };
+    
+    let legend: BTreeMap<&String, &String> = redactor.originals.iter().collect();
+    fs::write(path, serde_json::to_string_pretty(&legend)?)
+        .map_err(|e| anyhow::anyhow!("Cannot write redaction map {}: {}", path.display(), e))
+}
+
+impl Redactor {
+    fn text(&mut self, raw: &str) -> String {
+        let addresses_done = replace_tokens(raw, |c| c.is_ascii_digit() || c == '.', |token, rest| {
+            public_host(token, rest).then(|| self.pseudonym(token))
+        });
+        replace_tokens(&addresses_done, |c| c.is_ascii_alphanumeric() || c == '-', |token, _| {
+            // Public DNS names spell the address out: ec2-54-1-2-3.compute-1.amazonaws.com
+            if let Some(ip) = token.strip_prefix("ec2-").map(|octets| octets.replace('-', ".")) {
+                return public_host(&ip, "").then(|| format!("ec2-{}", self.pseudonym(&ip).replace('.', "-")));
+            }
+            (is_resource_id(token) || is_account_id(token)).then(|| self.pseudonym(token))
+        })
+    }
+    
+    /// Redact every string (and object key) in `value`, leaving numbers alone
+    fn value(&mut self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.text(s),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.value(item)),
+            Value::Object(map) => {
+                *map = std::mem::take(map).into_iter()
+                    .map(|(key, mut item)| {
+                        self.value(&mut item);
+                        (self.text(&key), item)
+                    })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+    
+    fn pseudonym(&mut self, original: &str) -> String {
+        if let Some(existing) = self.pseudonyms.get(original) {
+            return existing.clone();
+        }
+        
+        let kind = kind(original);
+        let count = self.counts.entry(kind.clone()).or_default();
+        *count += 1;
+        let n = *count;
+        
+        let pseudonym = match kind.as_str() {
+            "account" => format!("{:012}", n),
+            // Documentation range first (RFC 5737), then reserved 240/4 space
+            "ip" if n < 255 => format!("203.0.113.{}", n),
+            "ip" => cidr::format_ip(0xF000_0000 + n as u32),
+            prefix => format!("{}-{:04}", prefix, n),
+        };
+        
+        self.pseudonyms.insert(original.to_string(), pseudonym.clone());
+        self.originals.insert(pseudonym.clone(), original.to_string());
+        pseudonym
+    }
+}
+
+/// Counter key: "account", "ip", or the resource ID prefix
+fn kind(original: &str) -> String {
+    if is_account_id(original) {
+        "account".to_string()
+    } else if original.contains('.') {
+        "ip".to_string()
+    } else {
+        original.rsplit_once('-').map_or(original, |(prefix, _)| prefix).to_string()
+    }
+}
+
+/// `vpc-0a1b2c3d` style: a known prefix and an 8- or 17-digit hex suffix
+fn is_resource_id(token: &str) -> bool {
+    token.rsplit_once('-').is_some_and(|(prefix, suffix)| {
+        ID_PREFIXES.contains(&prefix)
+            && (suffix.len() == 8 || suffix.len() == 17)
+            && suffix.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+    })
+}
+
+fn is_account_id(token: &str) -> bool {
+    token.len() == 12 && token.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A public IPv4 address on its own or as a /32; broader CIDRs describe
+/// networks rather than hosts and are kept so rule analysis stays accurate
+fn public_host(token: &str, rest: &str) -> bool {
+    let prefix = rest.strip_prefix('/')
+        .map(|p| p.chars().take_while(char::is_ascii_digit).collect::<String>());
+    if prefix.as_deref().is_some_and(|p| p != "32") {
+        return false;
+    }
+    
+    let Ok((ip, _)) = cidr::parse_cidr(&format!("{}/32", token)) else { return false };
+    let reserved = [(0x0000_0000, 8), (0x7F00_0000, 8), (0xA9FE_0000, 16), (0xE000_0000, 3)];
+    !cidr::is_private(ip, 32) && !reserved.iter().any(|(net, len)| cidr::network(ip, *len) == *net)
+}
+
+/// Rewrite each maximal run of `token_char`s that `replace` returns a value for.
+/// `replace` also sees the text after the token, e.g. to check for a `/prefix`.
+fn replace_tokens(
+    text: &str,
+    token_char: impl Fn(char) -> bool,
+    mut replace: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    
+    while let Some(start) = rest.find(&token_char) {
+        out.push_str(&rest[..start]);
+        let token_and_after = &rest[start..];
+        let end = token_and_after.find(|c| !token_char(c)).unwrap_or(token_and_after.len());
+        let (token, after) = token_and_after.split_at(end);
+        
+        match replace(token, after) {
+            Some(replacement) => out.push_str(&replacement),
+            None => out.push_str(token),
+        }
+        rest = after;
+    }
+    
+    out.push_str(rest);
+    out
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Redactor>> {
+    REDACTOR.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn numbers_with_twelve_digits_are_not_account_ids() {
+        let mut redactor = Redactor::default();
+        let mut response = json!({"Datapoints": [{"Sum": 214748364800.0_f64, "Count": 123456789012_u64}], "OwnerId": "123456789012"});
+        redactor.value(&mut response);
+        
+        assert_eq!(response["Datapoints"][0]["Sum"], 214748364800.0);
+        assert_eq!(response["Datapoints"][0]["Count"], 123456789012_u64);
+        assert_eq!(response["OwnerId"], "000000000001");
+    }
+    
+    #[test]
+    fn arns_keep_their_shape() {
+        let mut redactor = Redactor::default();
+        assert_eq!(
+            redactor.text("arn:aws:ec2:us-east-1:123456789012:vpc/vpc-0a1b2c3d"),
+            "arn:aws:ec2:us-east-1:000000000001:vpc/vpc-0001",
+        );
+        // The same account maps to the same pseudonym every time
+        assert_eq!(redactor.text("arn:aws:iam::123456789012:role/admin"), "arn:aws:iam::000000000001:role/admin");
+    }
+    
+    #[test]
+    fn public_dns_names_hide_the_address() {
+        let mut redactor = Redactor::default();
+        assert_eq!(
+            redactor.text("ec2-54-1-2-3.compute-1.amazonaws.com and 54.1.2.3"),
+            "ec2-203-0-113-1.compute-1.amazonaws.com and 203.0.113.1",
+        );
+        // Private addresses aren't identifying
+        assert_eq!(redactor.text("ip-10-0-1-5.ec2.internal 10.0.1.5"), "ip-10-0-1-5.ec2.internal 10.0.1.5");
+    }
+    
+    #[test]
+    fn host_routes_are_redacted_but_networks_kept() {
+        let mut redactor = Redactor::default();
+        assert_eq!(redactor.text("198.51.100.7/32"), "203.0.113.1/32");
+        assert_eq!(redactor.text("198.51.100.0/24"), "198.51.100.0/24");
+        assert_eq!(redactor.text("0.0.0.0/0"), "0.0.0.0/0");
+    }
+}
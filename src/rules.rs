@@ -0,0 +1,264 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+
+/// Tunable inputs for `netkit compliance`, loaded from a `--rules` TOML file.
+/// Any section left out of the file keeps its built-in default.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ruleset {
+    /// Ports that must not be reachable from the internet or broad CIDRs
+    pub risky_ports: Vec<RiskyPort>,
+    /// Public (non-RFC1918) ingress sources this broad or broader are flagged
+    /// regardless of port; `0.0.0.0/0` is reported separately
+    pub broad_public_prefix: u8,
+    /// IPv4 ingress sources this broad or broader count as broad: all-traffic
+    /// rules from them are HIGH and risky ports open to them MEDIUM
+    pub broad_prefix: u8,
+    /// The same threshold for IPv6 sources
+    pub broad_prefix_v6: u8,
+    /// Ingress from `0.0.0.0/0` or `::/0` spanning more ports than this is HIGH,
+    /// whether or not a risky port falls inside
+    pub wide_port_range: u32,
+    /// Specific ranges that deserve a finding of their own when a rule references them
+    pub watched_sources: Vec<WatchedSource>,
+    /// Points each finding takes off the 100-point posture score
+    pub score_weights: ScoreWeights,
+    /// Governance: group naming convention and tags every group must carry
+    pub naming: Naming,
+    /// Commands that read the gathered security groups as JSON on stdin and
+    /// print extra findings as JSON on stdout
+    pub custom_checks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskyPort {
+    pub port: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchedSource {
+    pub cidr: String,
+    pub direction: Direction,
+    #[serde(default = "default_watched_severity")]
+    pub severity: Severity,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Ingress,
+    Egress,
+}
+
+/// Ordered worst first, so the most severe of several is the minimum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Critical => "CRITICAL",
+            Severity::High => "HIGH",
+            Severity::Medium => "MEDIUM",
+            Severity::Low => "LOW",
+        }
+    }
+    
+    pub fn parse(label: &str) -> Option<Severity> {
+        match label {
+            "CRITICAL" => Some(Severity::Critical),
+            "HIGH" => Some(Severity::High),
+            "MEDIUM" => Some(Severity::Medium),
+            "LOW" => Some(Severity::Low),
+            _ => None,
+        }
+    }
+}
+
+/// What `compliance --strict` exits with, picked by the worst finding
+#[derive(Debug, Clone, Copy)]
+pub struct ExitCodes {
+    /// Findings less severe than this never fail the run
+    pub fail_on: Severity,
+    pub critical: i32,
+    pub high: i32,
+    pub medium: i32,
+    pub low: i32,
+}
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        ExitCodes { fail_on: Severity::High, critical: 2, high: 1, medium: 1, low: 1 }
+    }
+}
+
+impl ExitCodes {
+    pub fn code(&self, worst: Option<Severity>) -> i32 {
+        match worst {
+            Some(severity) if severity <= self.fail_on => match severity {
+                Severity::Critical => self.critical,
+                Severity::High => self.high,
+                Severity::Medium => self.medium,
+                Severity::Low => self.low,
+            },
+            _ => 0,
+        }
+    }
+}
+
+/// Findings here are LOW: they concern ownership and hygiene, not exposure
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Naming {
+    /// Regex every group name must match; AWS-created `default` groups are exempt
+    pub group_name_pattern: Option<String>,
+    /// Tag keys every group must have, e.g. `Owner`
+    pub required_tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoreWeights {
+    pub critical: u32,
+    pub high: u32,
+    pub medium: u32,
+    pub low: u32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights { critical: 20, high: 10, medium: 3, low: 1 }
+    }
+}
+
+impl ScoreWeights {
+    /// Weighted sum of the findings, saturating at `u32::MAX`
+    pub fn penalty(&self, critical: usize, high: usize, medium: usize, low: usize) -> u32 {
+        [(critical, self.critical), (high, self.high), (medium, self.medium), (low, self.low)]
+            .iter()
+            .map(|(count, weight)| (*count as u64).saturating_mul(u64::from(*weight)))
+            .fold(0u64, u64::saturating_add)
+            .min(u64::from(u32::MAX)) as u32
+    }
+    
+    /// 100 minus the weighted findings, floored at 0
+    pub fn score(&self, critical: usize, high: usize, medium: usize, low: usize) -> u32 {
+        100u32.saturating_sub(self.penalty(critical, high, medium, low))
+    }
+}
+
+/// Letter grade for a posture score: A at 90 and up, then B, C, D by tens, F below 60
+pub fn grade(score: u32) -> &'static str {
+    match score {
+        90.. => "A",
+        80..=89 => "B",
+        70..=79 => "C",
+        60..=69 => "D",
+        _ => "F",
+    }
+}
+
+fn default_watched_severity() -> Severity {
+    Severity::High
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        let risky_ports = [
+            (22, "SSH"),
+            (3389, "RDP"),
+            (3306, "MySQL"),
+            (5432, "PostgreSQL"),
+            (1433, "MSSQL"),
+            (27017, "MongoDB"),
+            (6379, "Redis"),
+            (9200, "Elasticsearch"),
+        ];
+        
+        Ruleset {
+            risky_ports: risky_ports.iter()
+                .map(|(port, name)| RiskyPort { port: *port, name: name.to_string() })
+                .collect(),
+            broad_public_prefix: 8,
+            broad_prefix: 16,
+            broad_prefix_v6: 64,
+            wide_port_range: 100,
+            watched_sources: vec![
+                WatchedSource {
+                    cidr: "169.254.169.254/32".to_string(),
+                    direction: Direction::Egress,
+                    severity: Severity::High,
+                    description: "Egress explicitly allowed to the instance metadata service".to_string(),
+                },
+                WatchedSource {
+                    cidr: "169.254.0.0/16".to_string(),
+                    direction: Direction::Ingress,
+                    severity: Severity::Medium,
+                    description: "Ingress from the link-local range, which is never a legitimate peer".to_string(),
+                },
+            ],
+            score_weights: ScoreWeights::default(),
+            naming: Naming::default(),
+            custom_checks: Vec::new(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// The built-in ruleset, or the one in `path` layered over it
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else { return Ok(Ruleset::default()) };
+        
+        let raw = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read rules file {}: {}", path.display(), e))?;
+        let rules: Ruleset = toml::from_str(&raw)
+            .map_err(|e| anyhow::anyhow!("Invalid rules file {}: {}", path.display(), e))?;
+        
+        rules.validate(path)?;
+        Ok(rules)
+    }
+    
+    fn validate(&self, path: &Path) -> Result<()> {
+        if self.broad_public_prefix > 32 {
+            return Err(anyhow::anyhow!("Invalid rules file {}: broad_public_prefix must be 0-32", path.display()));
+        }
+        if self.broad_prefix > 32 {
+            return Err(anyhow::anyhow!("Invalid rules file {}: broad_prefix must be 0-32", path.display()));
+        }
+        if self.broad_prefix_v6 > 128 {
+            return Err(anyhow::anyhow!("Invalid rules file {}: broad_prefix_v6 must be 0-128", path.display()));
+        }
+        
+        for rule in &self.risky_ports {
+            if !(0..=65535).contains(&rule.port) {
+                return Err(anyhow::anyhow!("Invalid rules file {}: port {} is out of range (0-65535)", path.display(), rule.port));
+            }
+        }
+        
+        for watched in &self.watched_sources {
+            crate::cidr::parse_cidr(&watched.cidr)
+                .map_err(|e| anyhow::anyhow!("Invalid rules file {}: {}", path.display(), e))?;
+        }
+        
+        if let Some(pattern) = &self.naming.group_name_pattern {
+            regex::Regex::new(pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid rules file {}: group_name_pattern: {}", path.display(), e))?;
+        }
+        
+        Ok(())
+    }
+}
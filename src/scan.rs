@@ -0,0 +1,255 @@
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Outcome of a single TCP connect probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortStatus {
+    /// The handshake completed
+    Open,
+    /// The host answered with a reset
+    Closed,
+    /// No answer before the timeout, or the path rejected the probe
+    Filtered,
+}
+
+impl PortStatus {
+    pub fn label(self) -> ColoredString {
+        match self {
+            PortStatus::Open => "OPEN".green().bold(),
+            PortStatus::Closed => "CLOSED".red().bold(),
+            PortStatus::Filtered => "FILTERED".yellow().bold(),
+        }
+    }
+    
+    pub fn icon(self) -> ColoredString {
+        match self {
+            PortStatus::Open => "✅".green(),
+            PortStatus::Closed => "❌".red(),
+            PortStatus::Filtered => "🚧".yellow(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanResult {
+    pub target: String,
+    pub address: String,
+    pub port: u16,
+    pub status: PortStatus,
+}
+
+/// Most probes in flight at once; filtered ports each hold a thread for the full timeout
+const MAX_PARALLEL_PROBES: usize = 1024;
+
+/// One `--port` item: a single port or an inclusive `low-high` range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+impl PortRange {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let (first, last) = match raw.split_once('-') {
+            Some((first, last)) => (parse_port(first)?, parse_port(last)?),
+            None => {
+                let port = parse_port(raw)?;
+                (port, port)
+            }
+        };
+        if last < first {
+            return Err(format!("port range {}-{} is backwards", first, last));
+        }
+        Ok(PortRange { first, last })
+    }
+}
+
+pub fn parse_port(raw: &str) -> Result<u16, String> {
+    raw.trim().parse::<u16>()
+        .ok()
+        .filter(|p| *p > 0)
+        .ok_or_else(|| format!("port must be 1-65535, got '{}'", raw))
+}
+
+/// Every port the ranges cover, in the order given, each listed once
+pub fn expand_ports(ranges: &[PortRange]) -> Vec<u16> {
+    let mut seen = vec![false; usize::from(u16::MAX) + 1];
+    ranges.iter()
+        .flat_map(|range| range.first..=range.last)
+        .filter(|port| !std::mem::replace(&mut seen[usize::from(*port)], true))
+        .collect()
+}
+
+pub fn scan_port(target: &SocketAddr, timeout: Duration) -> PortStatus {
+    match TcpStream::connect_timeout(target, timeout) {
+        Ok(_) => PortStatus::Open,
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => PortStatus::Closed,
+        Err(_) => PortStatus::Filtered,
+    }
+}
+
+/// Probe `(port, address)` pairs in parallel; results come back in the order given.
+/// Filtered ports wait out the full timeout, so a batch takes about one timeout.
+pub fn scan_all(target: &str, addrs: &[(u16, SocketAddr)], timeout: Duration) -> Vec<ScanResult> {
+    let mut results = Vec::with_capacity(addrs.len());
+    for batch in addrs.chunks(MAX_PARALLEL_PROBES) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = batch.iter()
+                .map(|(port, socket_addr)| scope.spawn(move || ScanResult {
+                    target: target.to_string(),
+                    address: socket_addr.to_string(),
+                    port: *port,
+                    status: scan_port(socket_addr, timeout),
+                }))
+                .collect();
+            results.extend(handles.into_iter().map(|h| h.join().unwrap()));
+        });
+    }
+    results
+}
+
+/// [`scan_all`] through a proxy, which resolves `target` itself, so results
+/// carry `target:port` rather than a local address
+pub fn scan_all_via(proxy: &Socks5Proxy, target: &str, ports: &[u16], timeout: Duration) -> Result<Vec<ScanResult>> {
+    let mut results = Vec::with_capacity(ports.len());
+    for batch in ports.chunks(MAX_PARALLEL_PROBES) {
+        let statuses = std::thread::scope(|scope| {
+            let handles: Vec<_> = batch.iter()
+                .map(|&port| scope.spawn(move || scan_port_via(proxy, target, port, timeout)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Result<Vec<_>>>()
+        })?;
+        results.extend(batch.iter().zip(statuses).map(|(&port, status)| ScanResult {
+            target: target.to_string(),
+            address: format!("{}:{}", target, port),
+            port,
+            status,
+        }));
+    }
+    Ok(results)
+}
+
+/// A `socks5://[user:pass@]host:port` proxy that probes are relayed through,
+/// so reachability is tested from the proxy's side of the network
+#[derive(Debug, Clone)]
+pub struct Socks5Proxy {
+    pub host: String,
+    pub port: u16,
+    auth: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        let rest = uri.strip_prefix("socks5://")
+            .or_else(|| uri.strip_prefix("socks5h://"))
+            .ok_or_else(|| format!("expected socks5://host:port, got '{}'", uri))?;
+        
+        let (auth, endpoint) = match rest.rsplit_once('@') {
+            Some((creds, endpoint)) => {
+                let (user, pass) = creds.split_once(':').unwrap_or((creds, ""));
+                if user.len() > 255 || pass.len() > 255 {
+                    return Err("proxy username and password must be at most 255 bytes".to_string());
+                }
+                (Some((user.to_string(), pass.to_string())), endpoint)
+            }
+            None => (None, rest),
+        };
+        
+        let (host, port) = endpoint.trim_end_matches('/').rsplit_once(':')
+            .ok_or_else(|| format!("proxy '{}' is missing a port", uri))?;
+        let port = port.parse().map_err(|_| format!("invalid proxy port '{}'", port))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("proxy '{}' is missing a host", uri));
+        }
+        
+        Ok(Socks5Proxy { host: host.to_string(), port, auth })
+    }
+}
+
+impl std::fmt::Display for Socks5Proxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "socks5://{}:{}", self.host, self.port)
+    }
+}
+
+/// Probe `target:port` with a SOCKS5 CONNECT (RFC 1928). Hostnames are sent
+/// unresolved so the proxy's own DNS (e.g. a VPC resolver) is used. Errors
+/// mean the proxy itself could not be used, not that the port is closed.
+pub fn scan_port_via(proxy: &Socks5Proxy, target: &str, port: u16, timeout: Duration) -> Result<PortStatus> {
+    let proxy_addr = (proxy.host.as_str(), proxy.port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not resolve proxy {}", proxy))?;
+    let mut stream = TcpStream::connect_timeout(&proxy_addr, timeout)
+        .map_err(|e| anyhow::anyhow!("Could not connect to proxy {}: {}", proxy, e))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    
+    let method = if proxy.auth.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[0x05, 0x01, method])?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice)?;
+    if choice[0] != 0x05 || choice[1] != method {
+        return Err(anyhow::anyhow!("Proxy {} refused the {} authentication method", proxy,
+            if method == 0x02 { "username/password" } else { "no-auth" }));
+    }
+    
+    if let Some((user, pass)) = &proxy.auth {
+        // RFC 1929 username/password sub-negotiation
+        let mut request = vec![0x01, user.len() as u8];
+        request.extend(user.as_bytes());
+        request.push(pass.len() as u8);
+        request.extend(pass.as_bytes());
+        stream.write_all(&request)?;
+        
+        let mut status = [0u8; 2];
+        stream.read_exact(&mut status)?;
+        if status[1] != 0x00 {
+            return Err(anyhow::anyhow!("Proxy {} rejected the credentials", proxy));
+        }
+    }
+    
+    let mut request = vec![0x05, 0x01, 0x00];
+    match target.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            if target.len() > 255 {
+                return Err(anyhow::anyhow!("Hostname '{}' is too long for SOCKS5", target));
+            }
+            request.push(0x03);
+            request.push(target.len() as u8);
+            request.extend(target.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request)?;
+    
+    // Only the status byte matters; the bound address that follows is ignored
+    let mut reply = [0u8; 2];
+    match stream.read_exact(&mut reply) {
+        Ok(()) => {}
+        // The proxy is still waiting on the target
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(PortStatus::Filtered),
+        Err(e) => return Err(anyhow::anyhow!("Proxy {} closed the connection: {}", proxy, e)),
+    }
+    
+    Ok(match reply[1] {
+        0x00 => PortStatus::Open,
+        0x05 => PortStatus::Closed,
+        // 0x02 ruleset denial, 0x03/0x04 unreachable, 0x06 TTL expired, 0x01 general failure
+        0x01..=0x04 | 0x06 => PortStatus::Filtered,
+        code => return Err(anyhow::anyhow!("Proxy {} does not support CONNECT (reply code {:#04x})", proxy, code)),
+    })
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use colored::*;
+use std::process::Command;
+
+use crate::output::{self, OutputFormat};
+
+/// `ping`'s probe-count flag
+pub const PING_COUNT_FLAG: &str = if cfg!(windows) { "-n" } else { "-c" };
+
+#[cfg(not(windows))]
+pub fn show_interfaces(format: OutputFormat) -> Result<()> {
+    if format.is_structured() {
+        return print_ip_json(format, &["addr", "show"]);
+    }
+    
+    println!("{}", "🌐 Network Interfaces".cyan().bold());
+    println!("{}", "═".repeat(60).bright_black());
+    
+    let output = Command::new("ip")
+        .arg("addr")
+        .arg("show")
+        .output()?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    
+    for line in stdout.lines() {
+        if line.contains(": <") {
+            println!("{}", line.yellow().bold());
+        } else if line.contains("inet ") {
+            println!("{}", line.green());
+        } else if line.contains("link/") {
+            println!("{}", line.cyan());
+        } else {
+            println!("{}", line.bright_black());
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn show_routes(format: OutputFormat) -> Result<()> {
+    if format.is_structured() {
+        return print_ip_json(format, &["route", "show"]);
+    }
+    
+    println!("{}", "🗺️  Routing Table".cyan().bold());
+    println!("{}", "═".repeat(60).bright_black());
+    
+    let output = Command::new("ip")
+        .arg("route")
+        .arg("show")
+        .output()?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    
+    for line in stdout.lines() {
+        if line.contains("default") {
+            println!("{}", line.yellow().bold());
+        } else if line.contains("proto kernel") {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line.cyan());
+        }
+    }
+    
+    Ok(())
+}
+
+/// The first non-loopback IPv4 address with its prefix, e.g. "192.168.1.20/24"
+#[cfg(not(windows))]
+pub fn local_network() -> Result<String> {
+    let output = Command::new("ip")
+        .arg("addr")
+        .arg("show")
+        .output()?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    
+    stdout.lines()
+        .filter(|line| line.contains("inet ") && !line.contains("127.0.0.1"))
+        .find_map(|line| line.split_whitespace().nth(1))
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("Could not detect local network"))
+}
+
+/// Print `ip -j <args>` output in a structured format
+#[cfg(not(windows))]
+fn print_ip_json(format: OutputFormat, args: &[&str]) -> Result<()> {
+    let output = Command::new("ip")
+        .arg("-j")
+        .args(args)
+        .output()?;
+    
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    output::print(format, &json)
+}
+
+/// A network adapter as reported by `Get-NetAdapter`, with its `Get-NetIPAddress` addresses
+#[cfg(windows)]
+#[derive(Debug, serde::Serialize)]
+pub struct Interface {
+    pub name: String,
+    pub status: String,
+    pub mac: Option<String>,
+    pub mtu: Option<u64>,
+    /// "10.0.0.5/24" style, IPv4 first
+    pub addresses: Vec<String>,
+}
+
+/// One active IPv4 route from `route print`
+#[cfg(windows)]
+#[derive(Debug, serde::Serialize)]
+pub struct Route {
+    pub destination: String,
+    /// `None` for on-link routes
+    pub gateway: Option<String>,
+    /// Address of the local interface the route leaves through
+    pub interface: String,
+    pub metric: u32,
+}
+
+#[cfg(windows)]
+pub fn show_interfaces(format: OutputFormat) -> Result<()> {
+    let interfaces = interfaces()?;
+    if format.is_structured() {
+        return output::print(format, &interfaces);
+    }
+    
+    println!("{}", "🌐 Network Interfaces".cyan().bold());
+    println!("{}", "═".repeat(60).bright_black());
+    
+    for interface in &interfaces {
+        let mtu = interface.mtu.map(|mtu| format!(" mtu {}", mtu)).unwrap_or_default();
+        println!("{}", format!("{}: {}{}", interface.name, interface.status, mtu).yellow().bold());
+        if let Some(mac) = &interface.mac {
+            println!("    {}", format!("link/ether {}", mac).cyan());
+        }
+        for address in &interface.addresses {
+            let family = if address.contains(':') { "inet6" } else { "inet" };
+            println!("    {}", format!("{} {}", family, address).green());
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn show_routes(format: OutputFormat) -> Result<()> {
+    let output = Command::new("route").args(["print", "-4"]).output()
+        .map_err(|e| anyhow::anyhow!("Failed to run route print: {}", e))?;
+    let routes = parse_route_print(&String::from_utf8_lossy(&output.stdout));
+    
+    if format.is_structured() {
+        return output::print(format, &routes);
+    }
+    
+    println!("{}", "🗺️  Routing Table".cyan().bold());
+    println!("{}", "═".repeat(60).bright_black());
+    
+    for route in &routes {
+        let via = route.gateway.as_deref().map(|gw| format!(" via {}", gw)).unwrap_or_default();
+        let line = format!("{}{} dev {} metric {}", route.destination, via, route.interface, route.metric);
+        if route.destination == "0.0.0.0/0" {
+            println!("{}", line.yellow().bold());
+        } else if route.gateway.is_none() {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line.cyan());
+        }
+    }
+    
+    Ok(())
+}
+
+/// The first IPv4 address on an adapter that is up, skipping link-local
+/// (169.254/16) addresses Windows assigns when DHCP fails
+#[cfg(windows)]
+pub fn local_network() -> Result<String> {
+    interfaces()?
+        .into_iter()
+        .filter(|interface| interface.status == "Up")
+        .flat_map(|interface| interface.addresses)
+        .find(|address| !address.contains(':') && !address.starts_with("127.") && !address.starts_with("169.254."))
+        .ok_or_else(|| anyhow::anyhow!("Could not detect local network"))
+}
+
+/// Adapters from PowerShell's `Get-NetAdapter`, joined to `Get-NetIPAddress` by
+/// interface index. Both come back as JSON, which unlike `ipconfig` text
+/// doesn't change with the display language.
+#[cfg(windows)]
+pub fn interfaces() -> Result<Vec<Interface>> {
+    let adapters = powershell_json("Get-NetAdapter | Select-Object Name, Status, MacAddress, MtuSize, ifIndex")?;
+    let addresses = powershell_json("Get-NetIPAddress | Select-Object InterfaceIndex, IPAddress, PrefixLength")?;
+    
+    Ok(adapters.iter()
+        .map(|adapter| {
+            let index = adapter["ifIndex"].as_u64();
+            let mut addresses: Vec<String> = addresses.iter()
+                .filter(|a| index.is_some() && a["InterfaceIndex"].as_u64() == index)
+                .filter_map(|a| Some(format!("{}/{}", a["IPAddress"].as_str()?, a["PrefixLength"].as_u64()?)))
+                .collect();
+            addresses.sort_by_key(|a| a.contains(':'));
+            
+            Interface {
+                name: adapter["Name"].as_str().unwrap_or("unknown").to_string(),
+                status: adapter["Status"].as_str().unwrap_or("unknown").to_string(),
+                // Get-NetAdapter writes MACs as 00-15-5D-01-02-03
+                mac: adapter["MacAddress"].as_str()
+                    .filter(|mac| !mac.is_empty())
+                    .map(|mac| mac.replace('-', ":").to_lowercase()),
+                mtu: adapter["MtuSize"].as_u64(),
+                addresses,
+            }
+        })
+        .collect())
+}
+
+/// Run a PowerShell pipeline through `ConvertTo-Json`, always returning a list
+#[cfg(windows)]
+fn powershell_json(pipeline: &str) -> Result<Vec<serde_json::Value>> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &format!("{} | ConvertTo-Json", pipeline)])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run PowerShell: {}", e))?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("PowerShell failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    // A single result is written as a bare object, no results as nothing at all
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_str(&stdout)? {
+        serde_json::Value::Array(items) => Ok(items),
+        item => Ok(vec![item]),
+    }
+}
+
+/// Rows of the "Active Routes" table. They are matched by shape (two IPv4
+/// columns, then gateway, interface, metric) since the headings are localized.
+#[cfg(windows)]
+fn parse_route_print(stdout: &str) -> Vec<Route> {
+    use std::net::Ipv4Addr;
+    
+    stdout.lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let [destination, netmask, gateway, interface, metric] = columns[..] else { return None };
+            let destination: Ipv4Addr = destination.parse().ok()?;
+            let netmask: Ipv4Addr = netmask.parse().ok()?;
+            
+            Some(Route {
+                destination: format!("{}/{}", destination, u32::from(netmask).count_ones()),
+                // "On-link" in English; anything that isn't an address means the same
+                gateway: gateway.parse::<Ipv4Addr>().ok().map(|gw| gw.to_string()),
+                interface: interface.to_string(),
+                metric: metric.parse().ok()?,
+            })
+        })
+        .collect()
+}
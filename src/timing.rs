@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+
+/// Where a command's time went, collected when `--timing` is set and printed
+/// to stderr as the command finishes
+#[derive(Default)]
+struct Timings {
+    started: Option<Instant>,
+    phases: Vec<(String, Duration)>,
+    /// Per `service operation`: call count, total time, and the slowest call
+    calls: BTreeMap<String, CallStats>,
+}
+
+struct CallStats {
+    count: usize,
+    total: Duration,
+    slowest: Duration,
+    slowest_region: Option<String>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Mutex<Option<Timings>> = Mutex::new(None);
+
+/// Prints the report when dropped, so early returns and errors still get one
+pub struct Report;
+
+impl Drop for Report {
+    fn drop(&mut self) {
+        finish();
+    }
+}
+
+/// Start the clock; returns the guard that prints the breakdown
+pub fn enable() -> Report {
+    ENABLED.store(true, Ordering::Relaxed);
+    *lock() = Some(Timings { started: Some(Instant::now()), ..Default::default() });
+    Report
+}
+
+/// Record a phase that began at `started`; the label is only built when timing
+/// or trace logging is on
+pub fn record(started: Instant, label: impl FnOnce() -> String) {
+    let trace = tracing::enabled!(tracing::Level::TRACE);
+    if !trace && !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    
+    let elapsed = started.elapsed();
+    let label = label();
+    if trace {
+        tracing::trace!("{} took {}", label, seconds(elapsed));
+    }
+    if let Some(timings) = lock().as_mut() {
+        timings.phases.push((label, elapsed));
+    }
+}
+
+/// Record one `aws` invocation, grouped by its service and operation
+pub fn record_call(args: &[&str], elapsed: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    
+    let operation = args.iter().take(2).copied().collect::<Vec<_>>().join(" ");
+    let region = args.iter()
+        .position(|a| *a == "--region")
+        .and_then(|i| args.get(i + 1))
+        .map(|r| r.to_string());
+    
+    if let Some(timings) = lock().as_mut() {
+        let stats = timings.calls.entry(operation).or_insert(CallStats {
+            count: 0,
+            total: Duration::ZERO,
+            slowest: Duration::ZERO,
+            slowest_region: None,
+        });
+        stats.count += 1;
+        stats.total += elapsed;
+        if elapsed >= stats.slowest {
+            stats.slowest = elapsed;
+            stats.slowest_region = region;
+        }
+    }
+}
+
+/// Print the breakdown to stderr (once; later calls do nothing)
+pub fn finish() {
+    let Some(timings) = lock().take() else { return };
+    
+    eprintln!();
+    eprintln!("Timing:");
+    for (label, elapsed) in &timings.phases {
+        eprintln!("  {:<52} {:>8}", label, seconds(*elapsed));
+    }
+    
+    if !timings.calls.is_empty() {
+        // Calls overlap when fetched concurrently, so totals can exceed wall time
+        eprintln!("  aws calls:");
+        let mut calls: Vec<_> = timings.calls.iter().collect();
+        calls.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+        for (operation, stats) in calls {
+            let slowest_in = stats.slowest_region.as_deref()
+                .map(|r| format!(" in {}", r))
+                .unwrap_or_default();
+            eprintln!("    {:<36} {:>4} call(s) {:>8} total, slowest {}{}",
+                operation, stats.count, seconds(stats.total), seconds(stats.slowest), slowest_in);
+        }
+    }
+    
+    if let Some(started) = timings.started {
+        eprintln!("  {:<52} {:>8}", "total", seconds(started.elapsed()));
+    }
+}
+
+fn seconds(elapsed: Duration) -> String {
+    format!("{:.2}s", elapsed.as_secs_f64())
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Timings>> {
+    TIMINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const IANA_SERVER: &str = "whois.iana.org";
+/// Team Cymru's IP-to-ASN service answers over the WHOIS protocol
+const CYMRU_SERVER: &str = "whois.cymru.com";
+const WHOIS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ownership of an address, merged from its regional registry and the
+/// Cymru IP-to-ASN mapping
+#[derive(Debug, Default, Serialize)]
+pub struct WhoisInfo {
+    pub query: String,
+    /// Registry that holds the allocation (ARIN, RIPE, ...)
+    pub registry: Option<String>,
+    pub network: Option<String>,
+    pub org: Option<String>,
+    pub asn: Option<String>,
+    pub as_name: Option<String>,
+    pub bgp_prefix: Option<String>,
+    pub country: Option<String>,
+}
+
+impl WhoisInfo {
+    /// Compact "org, AS123, CC" label for annotating other reports
+    pub fn owner_label(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            // Cymru AS names already end in ", CC"
+            self.org.clone().or_else(|| self.as_name.as_deref().map(|name| {
+                let country = self.country.as_deref().unwrap_or_default();
+                name.strip_suffix(country).map_or(name, |n| n.trim_end_matches([',', ' '])).to_string()
+            })),
+            self.asn.as_ref().map(|asn| format!("AS{}", asn.trim_start_matches("AS"))),
+            self.country.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// The address a `whois` argument refers to: an IP, or the base of a CIDR
+pub fn parse_target(ip_or_cidr: &str) -> Result<IpAddr> {
+    let ip = ip_or_cidr.trim().split('/').next().unwrap_or_default();
+    ip.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid IP or CIDR '{}'", ip_or_cidr))
+}
+
+/// Full lookup: registry record via the IANA referral, plus ASN data.
+/// Either half may be missing; the lookup only fails when both do.
+pub fn lookup(ip: IpAddr) -> Result<WhoisInfo> {
+    let mut info = WhoisInfo { query: ip.to_string(), ..Default::default() };
+    
+    let registry = registry_record(ip, &mut info);
+    let asn = asn_record(ip, &mut info);
+    
+    match (registry, asn) {
+        (Err(e), Err(_)) => Err(e),
+        _ => Ok(info),
+    }
+}
+
+/// ASN, BGP prefix, country, and AS name only; one fast query, used to
+/// annotate compliance findings
+pub fn lookup_asn(ip: IpAddr) -> Result<WhoisInfo> {
+    let mut info = WhoisInfo { query: ip.to_string(), ..Default::default() };
+    asn_record(ip, &mut info)?;
+    Ok(info)
+}
+
+fn registry_record(ip: IpAddr, info: &mut WhoisInfo) -> Result<()> {
+    let iana = query(IANA_SERVER, &ip.to_string())?;
+    let server = field(&iana, &["refer", "whois"])
+        .ok_or_else(|| anyhow::anyhow!("IANA has no registry referral for {}", ip))?;
+    
+    // ARIN returns only a referral summary unless asked for the network record
+    let request = if server.contains("arin.net") { format!("n + {}", ip) } else { ip.to_string() };
+    let record = query(&server, &request)?;
+    
+    info.registry = Some(server.trim_start_matches("whois.").trim_end_matches(".net").to_uppercase());
+    info.network = field(&record, &["CIDR", "inetnum", "inet6num", "NetRange"]);
+    info.org = field(&record, &["OrgName", "org-name", "Organization", "owner", "descr", "netname"]);
+    info.asn = info.asn.take().or_else(|| field(&record, &["OriginAS", "origin", "aut-num"]));
+    info.country = info.country.take().or_else(|| field(&record, &["Country", "country"]));
+    
+    Ok(())
+}
+
+/// `AS | IP | BGP Prefix | CC | Registry | Allocated | AS Name`
+fn asn_record(ip: IpAddr, info: &mut WhoisInfo) -> Result<()> {
+    let response = query(CYMRU_SERVER, &format!(" -v {}", ip))?;
+    let row = response.lines()
+        .find(|line| line.contains('|') && !line.starts_with("AS "))
+        .ok_or_else(|| anyhow::anyhow!("No ASN data for {}", ip))?;
+    
+    let columns: Vec<&str> = row.split('|').map(str::trim).collect();
+    let column = |i: usize| columns.get(i)
+        .filter(|c| !c.is_empty() && **c != "NA")
+        .map(|c| c.to_string());
+    
+    info.asn = column(0);
+    info.bgp_prefix = column(2);
+    info.country = column(3).map(|c| c.to_uppercase());
+    info.registry = info.registry.take().or_else(|| column(4).map(|r| r.to_uppercase()));
+    info.as_name = column(6);
+    
+    Ok(())
+}
+
+/// First value for any of `keys` in a `Key: value` WHOIS response, in key priority order
+fn field(response: &str, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        response.lines()
+            .filter(|line| !line.starts_with('%') && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(':'))
+            .find(|(k, v)| k.trim().eq_ignore_ascii_case(key) && !v.trim().is_empty())
+            .map(|(_, v)| v.trim().to_string())
+    })
+}
+
+/// One WHOIS exchange (RFC 3912): send the query line, read until the server closes
+fn query(server: &str, request: &str) -> Result<String> {
+    let addr = (server, 43).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not resolve {}", server))?;
+    let mut stream = TcpStream::connect_timeout(&addr, WHOIS_TIMEOUT)
+        .map_err(|e| anyhow::anyhow!("Could not reach {}: {}", server, e))?;
+    stream.set_read_timeout(Some(WHOIS_TIMEOUT))?;
+    
+    stream.write_all(format!("{}\r\n", request).as_bytes())?;
+    
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)
+        .map_err(|e| anyhow::anyhow!("No response from {}: {}", server, e))?;
+    
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
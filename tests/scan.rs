@@ -0,0 +1,81 @@
+use netkit::scan::{self, PortRange, PortStatus};
+use std::net::{SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A loopback port with nothing listening on it: bind one, then let it go
+fn unbound_port() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+#[test]
+fn bound_port_is_open() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    
+    let started = Instant::now();
+    assert_eq!(scan::scan_port(&addr, TIMEOUT), PortStatus::Open);
+    assert!(started.elapsed() < TIMEOUT);
+}
+
+#[test]
+fn unbound_port_is_not_open() {
+    let addr = unbound_port();
+    
+    let started = Instant::now();
+    let status = scan::scan_port(&addr, TIMEOUT);
+    assert!(matches!(status, PortStatus::Closed | PortStatus::Filtered), "got {:?}", status);
+    // Allow for scheduling slack on a loaded machine, but not a hang
+    assert!(started.elapsed() < TIMEOUT * 2);
+}
+
+#[test]
+fn parses_single_ports_and_ranges() {
+    assert_eq!(PortRange::parse("22"), Ok(PortRange { first: 22, last: 22 }));
+    assert_eq!(PortRange::parse(" 8000-8010 "), Ok(PortRange { first: 8000, last: 8010 }));
+    assert_eq!(PortRange::parse("1-65535"), Ok(PortRange { first: 1, last: 65535 }));
+}
+
+#[test]
+fn rejects_invalid_ports() {
+    for raw in ["0", "65536", "ssh", "", "10-", "-10", "10-5", "1-2-3"] {
+        assert!(PortRange::parse(raw).is_err(), "'{}' should not parse", raw);
+    }
+}
+
+#[test]
+fn expands_ranges_in_order_without_duplicates() {
+    let ranges = [
+        PortRange::parse("443").unwrap(),
+        PortRange::parse("20-23").unwrap(),
+        PortRange::parse("22").unwrap(),
+        PortRange::parse("80").unwrap(),
+    ];
+    assert_eq!(scan::expand_ports(&ranges), vec![443, 20, 21, 22, 23, 80]);
+}
+
+#[test]
+fn parallel_scan_keeps_input_order() {
+    let listeners: Vec<TcpListener> = (0..4).map(|_| TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+    let mut addrs: Vec<(u16, SocketAddr)> = listeners.iter()
+        .map(|l| l.local_addr().unwrap())
+        .map(|addr| (addr.port(), addr))
+        .collect();
+    // Interleave unbound ports so open and closed answers finish at different times
+    for i in [3, 1] {
+        let addr = unbound_port();
+        addrs.insert(i, (addr.port(), addr));
+    }
+    
+    let results = scan::scan_all("127.0.0.1", &addrs, TIMEOUT);
+    
+    let ports: Vec<u16> = results.iter().map(|r| r.port).collect();
+    let expected: Vec<u16> = addrs.iter().map(|(port, _)| *port).collect();
+    assert_eq!(ports, expected);
+    for (result, (port, addr)) in results.iter().zip(&addrs) {
+        assert_eq!(result.address, addr.to_string());
+        let open = listeners.iter().any(|l| l.local_addr().unwrap().port() == *port);
+        assert_eq!(result.status == PortStatus::Open, open, "port {}", port);
+    }
+}